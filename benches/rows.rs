@@ -0,0 +1,132 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde::{Deserialize, Serialize};
+use serde_sheets::MockSheets;
+use tokio::runtime::Runtime;
+
+const DOCUMENT_ID: &str = "bench";
+const TAB_NAME: &str = "Bench";
+const SIZES: [u64; 3] = [1_000, 10_000, 100_000];
+
+#[derive(Serialize, Deserialize)]
+struct ExampleObject {
+    name: String,
+    number_of_foos: u64,
+    number_of_bars: f64,
+}
+
+fn generate_sample_objects(n: u64) -> Vec<ExampleObject> {
+    (0..n)
+        .map(|i| ExampleObject {
+            name: format!("Object {}", i),
+            number_of_foos: i * 10,
+            number_of_bars: i as f64 + 0.5,
+        })
+        .collect()
+}
+
+fn generate_matrix(n: u64) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|i| vec![i as f64, i as f64 * 10.0, i as f64 + 0.5])
+        .collect()
+}
+
+fn write(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let sheets = MockSheets::new();
+    let mut group = c.benchmark_group("write");
+    for n in SIZES {
+        let objects = generate_sample_objects(n);
+        let matrix = generate_matrix(n);
+        group.throughput(Throughput::Elements(n));
+        group.bench_with_input(BenchmarkId::new("write_page", n), &objects, |b, objects| {
+            b.iter(|| {
+                runtime
+                    .block_on(serde_sheets::write_page(
+                        &sheets,
+                        DOCUMENT_ID,
+                        TAB_NAME,
+                        objects,
+                    ))
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("write_matrix", n), &matrix, |b, matrix| {
+            b.iter(|| {
+                runtime
+                    .block_on(serde_sheets::write_matrix(
+                        &sheets,
+                        DOCUMENT_ID,
+                        TAB_NAME,
+                        matrix.clone(),
+                    ))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Rows the way they used to be built: written out as CSV text, then parsed back
+fn serialize_via_csv(objects: &[ExampleObject]) -> Vec<Vec<String>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for object in objects {
+        writer.serialize(object).unwrap();
+    }
+    let text = writer.into_inner().unwrap();
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(text.as_slice())
+        .records()
+        .map(|record| record.unwrap().iter().map(str::to_string).collect())
+        .collect()
+}
+
+fn serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    for n in SIZES {
+        let objects = generate_sample_objects(n);
+        group.throughput(Throughput::Elements(n));
+        group.bench_with_input(BenchmarkId::new("csv_text", n), &objects, |b, objects| {
+            b.iter(|| serialize_via_csv(objects))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("serialize_rows", n),
+            &objects,
+            |b, objects| b.iter(|| serde_sheets::serialize_rows(objects, true).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn read(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let sheets = MockSheets::new();
+    let mut group = c.benchmark_group("read");
+    for n in SIZES {
+        let rows = serde_sheets::serialize_rows(&generate_sample_objects(n), true).unwrap();
+        sheets.set_tab(DOCUMENT_ID, TAB_NAME, rows);
+        group.throughput(Throughput::Elements(n));
+        group.bench_function(BenchmarkId::new("read_all", n), |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(serde_sheets::read_all::<ExampleObject, _>(
+                        &sheets,
+                        DOCUMENT_ID,
+                        TAB_NAME,
+                    ))
+                    .unwrap()
+            })
+        });
+        group.bench_function(BenchmarkId::new("read_matrix", n), |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(serde_sheets::read_matrix(&sheets, DOCUMENT_ID, TAB_NAME))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, serialize, write, read);
+criterion_main!(benches);
@@ -1,8 +1,16 @@
 use serde::{Deserialize, Serialize};
-use serde_sheets::{get_sheets, service_account_from_env};
+use serde_sheets::{
+    fixtures::{Recorder, Replayer},
+    get_sheets, service_account_from_env, SheetsBackend,
+};
 
 const DOCUMENT_ID: &str = "17jj0gGuYCAfML2ZGA9Go493Pdozn2ogZQ0d2P9I6r6A";
 const TAB_NAME: &str = "IntegrationTest";
+/// The calls `run` makes and what the live API returned, replayed when there are no credentials
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/fixtures/example.json"
+);
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct ExampleObject {
@@ -21,32 +29,47 @@ fn generate_sample_objects(n: u64) -> Vec<ExampleObject> {
         .collect()
 }
 
-#[tokio::main]
-async fn main() {
-    let service_account = service_account_from_env().unwrap();
-    let mut sheets = get_sheets(service_account, Some("token_cache.json"))
-        .await
-        .unwrap();
-
+async fn run(sheets: &impl SheetsBackend) {
     let objects = generate_sample_objects(50);
 
     // write first 45 rows to sheet
-    serde_sheets::write_page(&mut sheets, DOCUMENT_ID, TAB_NAME, &objects[0..45])
+    serde_sheets::write_page(sheets, DOCUMENT_ID, TAB_NAME, &objects[0..45])
         .await
         .unwrap();
 
     // append last 5 rows
     for obj in &objects[45..50] {
-        serde_sheets::append_row(&mut sheets, DOCUMENT_ID, TAB_NAME, obj)
+        serde_sheets::append_row(sheets, DOCUMENT_ID, TAB_NAME, obj)
             .await
             .unwrap();
     }
 
     // fetch all rows
-    let returned: Vec<ExampleObject> = serde_sheets::read_all(&mut sheets, DOCUMENT_ID, TAB_NAME)
+    let returned: Vec<ExampleObject> = serde_sheets::read_all(sheets, DOCUMENT_ID, TAB_NAME)
         .await
         .unwrap();
 
     // check data is same
     assert_eq!(objects, returned);
 }
+
+/// Runs against the live API, re-recording the fixture, if `SERVICE_ACCOUNT_JSON` is set, and
+/// replays the fixture otherwise
+#[tokio::main]
+async fn main() {
+    match service_account_from_env() {
+        Ok(service_account) => {
+            let sheets = get_sheets(service_account, Some("token_cache.json"))
+                .await
+                .unwrap();
+            let recorder = Recorder::new(sheets, FIXTURE);
+            run(&recorder).await;
+            recorder.save().unwrap();
+        }
+        Err(_) => {
+            let replayer = Replayer::from_file(FIXTURE).unwrap();
+            run(&replayer).await;
+            assert_eq!(replayer.remaining(), 0);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use serde::Serialize;
+use serde_sheets::serialize_rows;
+use std::time::Instant;
+
+const ROWS: u64 = 100_000;
+
+#[derive(Serialize)]
+struct ExampleObject {
+    name: String,
+    number_of_foos: u64,
+    number_of_bars: f64,
+}
+
+fn generate_sample_objects(n: u64) -> Vec<ExampleObject> {
+    (0..n)
+        .map(|i| ExampleObject {
+            name: format!("Object {}", i),
+            number_of_foos: i * 10,
+            number_of_bars: i as f64 + 0.5,
+        })
+        .collect()
+}
+
+/// Rows the way they used to be built: written out as CSV text, then parsed back
+fn serialize_via_csv(objects: &[ExampleObject]) -> Vec<Vec<String>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for object in objects {
+        writer.serialize(object).unwrap();
+    }
+    let text = writer.into_inner().unwrap();
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(text.as_slice())
+        .records()
+        .map(|record| record.unwrap().iter().map(str::to_string).collect())
+        .collect()
+}
+
+fn main() {
+    let objects = generate_sample_objects(ROWS);
+
+    let start = Instant::now();
+    let via_csv = serialize_via_csv(&objects);
+    let csv_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let direct = serialize_rows(&objects, true).unwrap();
+    let direct_elapsed = start.elapsed();
+
+    assert_eq!(via_csv, direct);
+    println!("{} rows via CSV text: {:?}", ROWS, csv_elapsed);
+    println!("{} rows direct:       {:?}", ROWS, direct_elapsed);
+    println!(
+        "speedup: {:.1}x",
+        csv_elapsed.as_secs_f64() / direct_elapsed.as_secs_f64()
+    );
+}
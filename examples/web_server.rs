@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_sheets::{get_sheets, service_account_from_env, Sheets, WriteReceipt};
+use std::{net::SocketAddr, sync::Arc};
+
+const DOCUMENT_ID: &str = "17jj0gGuYCAfML2ZGA9Go493Pdozn2ogZQ0d2P9I6r6A";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExampleObject {
+    name: String,
+    number_of_foos: u64,
+    number_of_bars: f64,
+}
+
+/// One client shared by every request; tokens are refreshed behind it as needed
+type AppState = Arc<Sheets>;
+
+async fn list(
+    State(sheets): State<AppState>,
+    Path(tab_name): Path<String>,
+) -> Result<Json<Vec<ExampleObject>>, String> {
+    serde_sheets::read_all(&*sheets, DOCUMENT_ID, &tab_name)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_string())
+}
+
+async fn append(
+    State(sheets): State<AppState>,
+    Path(tab_name): Path<String>,
+    Json(obj): Json<ExampleObject>,
+) -> Result<Json<WriteReceipt>, String> {
+    serde_sheets::append_row(&*sheets, DOCUMENT_ID, &tab_name, obj)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_string())
+}
+
+#[tokio::main]
+async fn main() {
+    let service_account = service_account_from_env().unwrap();
+    let sheets = get_sheets(service_account, Some("token_cache.json"))
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route("/:tab_name", get(list).post(append))
+        .with_state(Arc::new(sheets));
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
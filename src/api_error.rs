@@ -0,0 +1,437 @@
+use crate::SheetsError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt, time::Duration};
+
+/// What the API said about a failed request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiErrorDetails {
+    /// HTTP status code, e.g. `403`
+    pub status: u16,
+    /// Canonical error status, e.g. `PERMISSION_DENIED`, if the response had one
+    pub reason: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ApiErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "{} ({} {})", self.message, self.status, reason),
+            None => write!(f, "{} ({})", self.message, self.status),
+        }
+    }
+}
+
+/// Broad categories of `SheetsError`, for deciding whether to retry, alert or fix data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Credentials are missing, invalid or could not be refreshed
+    Auth,
+    /// The API could not be reached or failed on its side; retrying may help
+    Network,
+    /// The document, tab, column or row doesn't exist
+    NotFound,
+    PermissionDenied,
+    /// Rate or usage limits; retry after a delay
+    Quota,
+    /// The sheet's layout doesn't match what was expected, e.g. a different header
+    Schema,
+    /// A row could not be converted to or from a record
+    Serde,
+    /// Misuse of the crate or an unexpected response
+    Other,
+}
+
+impl SheetsError {
+    /// The category of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SheetsError::EnvVarNotFound(_)
+            | SheetsError::MissingConfig { .. }
+            | SheetsError::ServiceAccountFile { .. }
+            | SheetsError::InvalidServiceAccountJSON(_)
+            | SheetsError::TokenCachePathError(_)
+            | SheetsError::Auth(_)
+            | SheetsError::SheetsError(google_sheets4::Error::MissingToken(_)) => ErrorKind::Auth,
+            SheetsError::Network(_) | SheetsError::ServerError { .. } => ErrorKind::Network,
+            SheetsError::NotFound { .. }
+            | SheetsError::TabNotFound { .. }
+            | SheetsError::ColumnNotFound { .. }
+            | SheetsError::RowIdNotFound { .. } => ErrorKind::NotFound,
+            SheetsError::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            SheetsError::QuotaExceeded { .. } => ErrorKind::Quota,
+            SheetsError::HeaderMismatch { .. }
+            | SheetsError::DuplicateHeader { .. }
+            | SheetsError::UnknownColumns { .. }
+            | SheetsError::InvalidRange { .. }
+            | SheetsError::MalformedRange { .. }
+            | SheetsError::TooManyRows { .. }
+            | SheetsError::TooManyCells { .. }
+            | SheetsError::SchemaDrift { .. } => ErrorKind::Schema,
+            SheetsError::Serde { .. }
+            | SheetsError::CSVError(_)
+            | SheetsError::Json { .. }
+            | SheetsError::InvalidRecord { .. } => ErrorKind::Serde,
+            SheetsError::PartialWrite { source, .. } => source.kind(),
+            SheetsError::ChunksFailed { failed, .. } => failed
+                .first()
+                .map_or(ErrorKind::Other, |(_, error)| error.kind()),
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// `TabNotFound` for `requested`, suggesting the closest of `available` if it is within a
+    /// few typos
+    pub(crate) fn tab_not_found(requested: &str, available: Vec<String>) -> SheetsError {
+        let requested_lower = requested.to_lowercase();
+        let max_distance = (requested.chars().count() / 3).max(2);
+        let suggestion = available
+            .iter()
+            .map(|tab| (edit_distance(&requested_lower, &tab.to_lowercase()), tab))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, tab)| tab.clone());
+        SheetsError::TabNotFound {
+            requested: requested.to_string(),
+            available,
+            suggestion,
+        }
+    }
+
+    /// The error the API gives for adding a tab called `tab_name` when the document has one,
+    /// for backends without an API to give it
+    pub(crate) fn duplicate_tab(tab_name: &str) -> SheetsError {
+        SheetsError::SheetsError(google_sheets4::Error::BadRequest(serde_json::json!({
+            "error": {
+                "code": 400,
+                "message": format!(
+                    "Invalid requests[0].addSheet: A sheet with the name \"{}\" already exists. \
+                     Please enter another name.",
+                    tab_name
+                ),
+                "status": "INVALID_ARGUMENT",
+            }
+        })))
+    }
+
+    /// Classify an error from a request against document `document_id` into a typed variant,
+    /// falling back to `SheetsError::SheetsError` for errors without a specific one
+    pub(crate) fn from_api(error: google_sheets4::Error, document_id: &str) -> SheetsError {
+        let retry_after = match &error {
+            google_sheets4::Error::BadRequest(body) => retry_after_from_body(body),
+            google_sheets4::Error::Failure(response) => response
+                .headers()
+                .get(hyper::header::RETRY_AFTER)
+                .and_then(|value| parse_retry_after(value.to_str().ok()?)),
+            _ => None,
+        };
+        let details = match &error {
+            google_sheets4::Error::BadRequest(body) => details_from_body(body),
+            google_sheets4::Error::Failure(response) => Some(ApiErrorDetails {
+                status: response.status().as_u16(),
+                reason: None,
+                message: response
+                    .status()
+                    .canonical_reason()
+                    .unwrap_or_default()
+                    .to_string(),
+            }),
+            _ => None,
+        };
+        let details = match details {
+            Some(details) => details,
+            None => {
+                return match error {
+                    google_sheets4::Error::HttpError(_) | google_sheets4::Error::Io(_) => {
+                        SheetsError::Network(error)
+                    }
+                    error => SheetsError::SheetsError(error),
+                }
+            }
+        };
+
+        classify(details, retry_after, document_id)
+            .unwrap_or_else(|_| SheetsError::SheetsError(error))
+    }
+
+    /// Like `from_api`, for requests that don't target a document, such as creating one, so
+    /// a 404 is not reported as a missing document with an empty ID
+    pub(crate) fn from_api_without_document(error: google_sheets4::Error) -> SheetsError {
+        match SheetsError::from_api(error, "") {
+            SheetsError::NotFound { details, .. } => SheetsError::UnexpectedResponse {
+                reason: details.to_string(),
+            },
+            error => error,
+        }
+    }
+}
+
+/// The typed variant for an API error described by `details`, or the details back if there
+/// is none
+pub(crate) fn classify(
+    details: ApiErrorDetails,
+    retry_after: Option<Duration>,
+    document_id: &str,
+) -> Result<SheetsError, ApiErrorDetails> {
+    let reason = details.reason.clone();
+    match (details.status, reason.as_deref()) {
+        (429, _) | (_, Some("RESOURCE_EXHAUSTED")) => Ok(SheetsError::QuotaExceeded {
+            retry_after,
+            details,
+        }),
+        (401 | 403, _) | (_, Some("PERMISSION_DENIED")) => {
+            Ok(SheetsError::PermissionDenied { details })
+        }
+        (404, _) | (_, Some("NOT_FOUND")) => Ok(SheetsError::NotFound {
+            document_id: document_id.to_string(),
+            details,
+        }),
+        (400, _) if details.message.starts_with("Unable to parse range") => {
+            Ok(SheetsError::InvalidRange { details })
+        }
+        (500..=599, _) => Ok(SheetsError::ServerError { details }),
+        _ => Err(details),
+    }
+}
+
+/// Details from a JSON error body like `{"error": {"code": 403, "message": .., "status": ..}}`
+fn details_from_body(body: &Value) -> Option<ApiErrorDetails> {
+    let error = body.get("error")?;
+    Some(ApiErrorDetails {
+        status: error.get("code")?.as_u64()? as u16,
+        reason: error
+            .get("status")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        message: error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// The delay from a `google.rpc.RetryInfo` entry in the error's details, e.g. `"retryDelay": "30s"`
+fn retry_after_from_body(body: &Value) -> Option<Duration> {
+    body.get("error")?
+        .get("details")?
+        .as_array()?
+        .iter()
+        .find_map(|detail| detail.get("retryDelay")?.as_str())
+        .and_then(|delay| delay.strip_suffix('s')?.parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// The delay in a `Retry-After` header, given either as seconds or as an HTTP date such as
+/// `Wed, 21 Oct 2015 07:28:00 GMT`; a date already past means no delay
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Levenshtein distance between `a` and `b`, in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn details(status: u16, reason: Option<&str>, message: &str) -> ApiErrorDetails {
+        ApiErrorDetails {
+            status,
+            reason: reason.map(str::to_string),
+            message: message.to_string(),
+        }
+    }
+
+    fn bad_request(code: u16, status: &str, message: &str) -> google_sheets4::Error {
+        google_sheets4::Error::BadRequest(json!({
+            "error": { "code": code, "message": message, "status": status }
+        }))
+    }
+
+    fn failure(status: u16, retry_after: Option<&str>) -> google_sheets4::Error {
+        let mut response = hyper::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            response = response.header(hyper::header::RETRY_AFTER, retry_after);
+        }
+        google_sheets4::Error::Failure(response.body(hyper::Body::empty()).unwrap())
+    }
+
+    #[test]
+    fn classifies_by_status_and_reason() {
+        let delay = Some(Duration::from_secs(30));
+
+        let quota = classify(details(429, None, "Too many requests"), delay, "doc");
+        assert!(matches!(
+            quota,
+            Ok(SheetsError::QuotaExceeded { retry_after, .. }) if retry_after == delay
+        ));
+        let exhausted = classify(details(403, Some("RESOURCE_EXHAUSTED"), ""), None, "doc");
+        assert!(matches!(exhausted, Ok(SheetsError::QuotaExceeded { .. })));
+
+        for status in [401, 403] {
+            let denied = classify(
+                details(status, None, "The caller does not have permission"),
+                None,
+                "doc",
+            );
+            assert!(matches!(denied, Ok(SheetsError::PermissionDenied { .. })));
+        }
+
+        let missing = classify(
+            details(404, Some("NOT_FOUND"), "Requested entity was not found."),
+            None,
+            "doc",
+        );
+        assert!(matches!(
+            missing,
+            Ok(SheetsError::NotFound { document_id, .. }) if document_id == "doc"
+        ));
+
+        let range = classify(
+            details(
+                400,
+                Some("INVALID_ARGUMENT"),
+                "Unable to parse range: Nope!A1",
+            ),
+            None,
+            "doc",
+        );
+        assert!(matches!(range, Ok(SheetsError::InvalidRange { .. })));
+
+        for status in [500, 503] {
+            let server = classify(details(status, None, "Internal error"), None, "doc");
+            assert!(matches!(server, Ok(SheetsError::ServerError { .. })));
+        }
+
+        let other = details(
+            400,
+            Some("INVALID_ARGUMENT"),
+            "Invalid requests[0].addSheet",
+        );
+        assert_eq!(classify(other.clone(), None, "doc").unwrap_err(), other);
+    }
+
+    #[test]
+    fn classifies_json_error_bodies() {
+        let denied = SheetsError::from_api(
+            bad_request(
+                403,
+                "PERMISSION_DENIED",
+                "The caller does not have permission",
+            ),
+            "doc",
+        );
+        match &denied {
+            SheetsError::PermissionDenied { details: d } => assert_eq!(
+                d,
+                &details(
+                    403,
+                    Some("PERMISSION_DENIED"),
+                    "The caller does not have permission"
+                )
+            ),
+            error => panic!("unexpected {:?}", error),
+        }
+        assert_eq!(denied.kind(), ErrorKind::PermissionDenied);
+
+        let quota = SheetsError::from_api(
+            google_sheets4::Error::BadRequest(json!({
+                "error": {
+                    "code": 429,
+                    "message": "Quota exceeded",
+                    "status": "RESOURCE_EXHAUSTED",
+                    "details": [
+                        { "@type": "type.googleapis.com/google.rpc.ErrorInfo" },
+                        { "@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "1.5s" }
+                    ]
+                }
+            })),
+            "doc",
+        );
+        assert!(matches!(
+            quota,
+            SheetsError::QuotaExceeded { retry_after: Some(delay), .. }
+                if delay == Duration::from_millis(1500)
+        ));
+        assert_eq!(quota.kind(), ErrorKind::Quota);
+
+        // errors without a typed variant keep the original API error
+        let duplicate = SheetsError::from_api(
+            bad_request(
+                400,
+                "INVALID_ARGUMENT",
+                "A sheet with the name \"Tab\" already exists",
+            ),
+            "doc",
+        );
+        assert!(matches!(
+            duplicate,
+            SheetsError::SheetsError(google_sheets4::Error::BadRequest(_))
+        ));
+        assert_eq!(duplicate.kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn classifies_http_failures() {
+        let quota = SheetsError::from_api(failure(429, Some("7")), "doc");
+        assert!(matches!(
+            quota,
+            SheetsError::QuotaExceeded { retry_after: Some(delay), .. }
+                if delay == Duration::from_secs(7)
+        ));
+
+        let server = SheetsError::from_api(failure(503, None), "doc");
+        match &server {
+            SheetsError::ServerError { details: d } => {
+                assert_eq!(d, &details(503, None, "Service Unavailable"))
+            }
+            error => panic!("unexpected {:?}", error),
+        }
+        assert_eq!(server.kind(), ErrorKind::Network);
+
+        let missing = SheetsError::from_api(failure(404, None), "doc");
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+        let missing = SheetsError::from_api_without_document(failure(404, None));
+        assert!(matches!(missing, SheetsError::UnexpectedResponse { .. }));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(90));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-5"), None);
+    }
+}
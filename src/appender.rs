@@ -0,0 +1,154 @@
+use crate::{serialize_rows, SheetsBackend, SheetsError};
+use serde::Serialize;
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::Instant,
+};
+
+enum Command {
+    Row(Vec<String>),
+    Flush(oneshot::Sender<Result<(), SheetsError>>),
+}
+
+/// Collects rows appended with `push` and sends them in batches, instead of one
+/// `values.append` call per row.
+///
+/// A batch is sent once `capacity` rows are waiting or `max_delay` after the first of them
+/// was pushed, whichever comes first. Sending happens on a background task; an error from an
+/// automatic send drops that batch and is returned by the next `flush` or `close`. Dropping
+/// the appender sends the remaining rows in the background, as long as the runtime keeps
+/// running; use `close` to wait for them and see the result.
+pub struct BufferedAppender<T> {
+    sender: mpsc::UnboundedSender<Command>,
+    task: JoinHandle<Result<(), SheetsError>>,
+    _records: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize> BufferedAppender<T> {
+    /// Append to tab `tab_name` in document `document_id`. Must be called within a Tokio
+    /// runtime.
+    pub fn new<B: SheetsBackend + ?Sized + 'static>(
+        sheets: Arc<B>,
+        document_id: impl Into<String>,
+        tab_name: impl Into<String>,
+        capacity: usize,
+        max_delay: Duration,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run(
+            sheets,
+            document_id.into(),
+            tab_name.into(),
+            capacity.max(1),
+            max_delay,
+            receiver,
+        ));
+        Self {
+            sender,
+            task,
+            _records: PhantomData,
+        }
+    }
+
+    /// Queue `obj` to be appended, without waiting for the API
+    pub fn push(&self, obj: &T) -> Result<(), SheetsError> {
+        let row = serialize_rows(std::slice::from_ref(obj), false)?
+            .pop()
+            .unwrap_or_default();
+        self.sender
+            .send(Command::Row(row))
+            .map_err(|_| SheetsError::AppenderStopped)
+    }
+
+    /// Send every queued row now, returning the first error since the last flush
+    pub async fn flush(&self) -> Result<(), SheetsError> {
+        let (done, result) = oneshot::channel();
+        self.sender
+            .send(Command::Flush(done))
+            .map_err(|_| SheetsError::AppenderStopped)?;
+        result.await.map_err(|_| SheetsError::AppenderStopped)?
+    }
+
+    /// Send every queued row and stop the background task
+    pub async fn close(self) -> Result<(), SheetsError> {
+        let BufferedAppender { sender, task, .. } = self;
+        // the task sends what is left once the channel closes
+        drop(sender);
+        match task.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => Err(SheetsError::AppenderStopped),
+        }
+    }
+}
+
+async fn run<B: SheetsBackend + ?Sized>(
+    sheets: Arc<B>,
+    document_id: String,
+    tab_name: String,
+    capacity: usize,
+    max_delay: Duration,
+    mut receiver: mpsc::UnboundedReceiver<Command>,
+) -> Result<(), SheetsError> {
+    let mut buffer = vec![];
+    let mut deadline: Option<Instant> = None;
+    let mut failed: Option<SheetsError> = None;
+
+    loop {
+        let command = match deadline {
+            Some(at) => match tokio::time::timeout_at(at, receiver.recv()).await {
+                Ok(command) => command,
+                Err(_) => {
+                    deadline = None;
+                    if let Err(e) = send(&*sheets, &document_id, &tab_name, &mut buffer).await {
+                        failed.get_or_insert(e);
+                    }
+                    continue;
+                }
+            },
+            None => receiver.recv().await,
+        };
+
+        match command {
+            Some(Command::Row(row)) => {
+                if buffer.is_empty() {
+                    deadline = Some(Instant::now() + max_delay);
+                }
+                buffer.push(row);
+                if buffer.len() >= capacity {
+                    deadline = None;
+                    if let Err(e) = send(&*sheets, &document_id, &tab_name, &mut buffer).await {
+                        failed.get_or_insert(e);
+                    }
+                }
+            }
+            Some(Command::Flush(done)) => {
+                deadline = None;
+                let result = send(&*sheets, &document_id, &tab_name, &mut buffer).await;
+                let _ = done.send(failed.take().map_or(result, Err));
+            }
+            None => {
+                let result = send(&*sheets, &document_id, &tab_name, &mut buffer).await;
+                return failed.map_or(result, Err);
+            }
+        }
+    }
+}
+
+/// Append the rows in `buffer`, leaving it empty
+async fn send<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    buffer: &mut Vec<Vec<String>>,
+) -> Result<(), SheetsError> {
+    if buffer.is_empty() {
+        return Ok(());
+    }
+    sheets
+        .append_values(document_id, tab_name, std::mem::take(buffer))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use crate::{quote_tab_name, serialize_rows, SheetsBackend, SheetsError, TabInfo, WriteReceipt};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Tab that audit entries are appended to unless configured otherwise
+pub const DEFAULT_AUDIT_TAB: &str = "_audit";
+
+/// A single row of the audit tab
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub operation: String,
+    pub range: String,
+    pub row_count: usize,
+    pub actor: String,
+}
+
+/// Backend wrapper that appends an `AuditEntry` to the audit tab of the same document for
+/// every write, append, clear and structural change made through it. The audit tab must
+/// already exist; its header is written if it is empty.
+pub struct Audited<B> {
+    inner: B,
+    actor: String,
+    audit_tab: String,
+    header_checked: AtomicBool,
+}
+
+impl<B: SheetsBackend> Audited<B> {
+    /// Audit mutations made through `inner`, recording `actor` as the caller
+    pub fn new(inner: B, actor: impl Into<String>) -> Self {
+        Self {
+            inner,
+            actor: actor.into(),
+            audit_tab: DEFAULT_AUDIT_TAB.to_string(),
+            header_checked: AtomicBool::new(false),
+        }
+    }
+
+    /// Append audit entries to `audit_tab` instead of `_audit`
+    pub fn audit_tab(mut self, audit_tab: impl Into<String>) -> Self {
+        self.audit_tab = audit_tab.into();
+        self.header_checked = AtomicBool::new(false);
+        self
+    }
+
+    /// The wrapped backend
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Append an entry for `operation` on `range` to the audit tab
+    async fn record(
+        &self,
+        document_id: &str,
+        operation: &str,
+        range: &str,
+        row_count: usize,
+    ) -> Result<(), SheetsError> {
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            operation: operation.to_string(),
+            range: range.to_string(),
+            row_count,
+            actor: self.actor.clone(),
+        };
+
+        let needs_header = !self.header_checked.swap(true, Ordering::SeqCst)
+            && self
+                .inner
+                .read_values(
+                    document_id,
+                    &format!("{}!1:1", quote_tab_name(&self.audit_tab)),
+                )
+                .await?
+                .is_empty();
+
+        self.inner
+            .append_values(
+                document_id,
+                &self.audit_tab,
+                serialize_rows(&[entry], needs_header)?,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<B: SheetsBackend> SheetsBackend for Audited<B> {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.inner.read_values(document_id, range).await
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.inner.read_formulas(document_id, range).await
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        self.inner.read_ranges(document_id, ranges).await
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let row_count = rows.len();
+        self.inner.write_values(document_id, range, rows).await?;
+        self.record(document_id, "write", range, row_count).await
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let row_count = rows.len();
+        self.inner
+            .write_raw_values(document_id, range, rows)
+            .await?;
+        self.record(document_id, "write", range, row_count).await
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        let summary: Vec<(String, usize)> = data
+            .iter()
+            .map(|(range, rows)| (range.clone(), rows.len()))
+            .collect();
+        self.inner.write_ranges(document_id, data).await?;
+        for (range, row_count) in summary {
+            self.record(document_id, "write", &range, row_count).await?;
+        }
+        Ok(())
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let row_count = rows.len();
+        let receipt = self.inner.append_values(document_id, range, rows).await?;
+        self.record(document_id, "append", range, row_count).await?;
+        Ok(receipt)
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        self.inner.clear_range(document_id, range).await?;
+        self.record(document_id, "clear", range, 0).await
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        self.inner
+            .delete_rows(document_id, tab_name, start, end)
+            .await?;
+        let range = format!("{}!{}:{}", quote_tab_name(tab_name), start + 1, end);
+        self.record(document_id, "delete_rows", &range, end - start)
+            .await
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        self.inner.list_tabs(document_id).await
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let tab = self.inner.add_tab(document_id, tab_name).await?;
+        self.record(document_id, "add_tab", tab_name, 0).await?;
+        Ok(tab)
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        self.inner.delete_tab(document_id, tab_name).await?;
+        self.record(document_id, "delete_tab", tab_name, 0).await
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        let tab = self
+            .inner
+            .duplicate_tab(document_id, tab_name, new_name)
+            .await?;
+        self.record(document_id, "duplicate_tab", new_name, 0)
+            .await?;
+        Ok(tab)
+    }
+}
@@ -0,0 +1,873 @@
+use crate::{
+    batch_update, column_letter, grid::tab_and_span, quote_tab_name, trace, SheetsError,
+    Spreadsheet,
+};
+use async_trait::async_trait;
+use google_sheets4::{
+    api::{
+        AddSheetRequest, BatchUpdateValuesRequest, ClearValuesRequest, DeleteDimensionRequest,
+        DeleteSheetRequest, DimensionRange, DuplicateSheetRequest, Request, SheetProperties,
+        ValueRange,
+    },
+    Sheets,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// A tab within a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabInfo {
+    pub sheet_id: i32,
+    pub title: String,
+    /// Rows in the tab's grid, including empty ones
+    pub row_count: usize,
+    /// Columns in the tab's grid, including empty ones
+    pub column_count: usize,
+}
+
+/// Where a write landed, so the rows it created can be addressed later
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteReceipt {
+    /// A1 range of the cells written, e.g. `'Tab'!A5:C7`
+    pub updated_range: String,
+    pub updated_rows: usize,
+    /// One-based sheet row of the first row written, or `0` if no rows were written
+    pub first_row_index: usize,
+    /// When the server took the write, as RFC 3339 from its `Date` header, for backends that
+    /// have a server to ask
+    #[serde(default)]
+    pub server_time: Option<String>,
+}
+
+impl WriteReceipt {
+    /// Receipt for `rows` written to tab `tab_name` from zero-based `(row, column)`
+    pub fn new(tab_name: &str, row: usize, column: usize, rows: &[Vec<String>]) -> Self {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        Self::covering(tab_name, row, column, rows.len(), width)
+    }
+
+    /// Receipt for `row_count` rows `width` cells wide, from zero-based `(row, column)`
+    pub(crate) fn covering(
+        tab_name: &str,
+        row: usize,
+        column: usize,
+        row_count: usize,
+        width: usize,
+    ) -> Self {
+        if row_count == 0 || width == 0 {
+            return Self {
+                updated_range: quote_tab_name(tab_name),
+                updated_rows: 0,
+                first_row_index: 0,
+                server_time: None,
+            };
+        }
+        Self {
+            updated_range: format!(
+                "{}!{}{}:{}{}",
+                quote_tab_name(tab_name),
+                column_letter(column),
+                row + 1,
+                column_letter(column + width - 1),
+                row + row_count
+            ),
+            updated_rows: row_count,
+            first_row_index: row + 1,
+            server_time: None,
+        }
+    }
+}
+
+/// The operations the crate is built on, abstracted over the transport.
+///
+/// Implemented for `google_sheets4::Sheets`. Every function generic over `SheetsBackend`
+/// works unchanged with alternative implementations such as test doubles.
+/// Ranges are A1 notation, e.g. `Tab`, `'My Tab'!A2` or `'My Tab'!3:10`.
+#[async_trait]
+pub trait SheetsBackend: Send + Sync {
+    /// Raw cell values in `range`, without trailing empty rows and columns
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError>;
+
+    /// Like `read_values`, but cells holding formulas give the formula, e.g. `=IMAGE(...)`,
+    /// instead of its result. Backends that store plain text need not override this.
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.read_values(document_id, range).await
+    }
+
+    /// `read_values` of each of `ranges`, in order. Sheets fetches them in one request;
+    /// other backends need not override this.
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        let mut values = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            values.push(self.read_values(document_id, range).await?);
+        }
+        Ok(values)
+    }
+
+    /// Overwrite cells starting at the top-left of `range` with `rows`
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError>;
+
+    /// Like `write_values`, but cells are stored exactly as given instead of being parsed as
+    /// numbers, dates or formulas. Backends that store plain text need not override this.
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.write_values(document_id, range, rows).await
+    }
+
+    /// Overwrite several ranges, as `(range, rows)` pairs
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        for (range, rows) in data {
+            self.write_values(document_id, &range, rows).await?;
+        }
+        Ok(())
+    }
+
+    /// Append `rows` after the last row of data in the table at `range`, returning where they
+    /// landed
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError>;
+
+    /// Clear all values in `range`
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError>;
+
+    /// Remove zero-based rows `start..end` of tab `tab_name`, shifting later rows up
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError>;
+
+    /// All tabs of document `document_id`, in display order
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError>;
+
+    /// Create an empty tab called `tab_name`
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError>;
+
+    /// Delete tab `tab_name` and all its contents
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError>;
+
+    /// Copy tab `tab_name` to a new tab called `new_name`, with its formatting where the
+    /// backend has any. Backends that store plain values need not override this.
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        let rows = self.read_values(document_id, tab_name).await?;
+        let tab = self.add_tab(document_id, new_name).await?;
+        if !rows.is_empty() {
+            self.write_raw_values(document_id, new_name, rows).await?;
+        }
+        Ok(tab)
+    }
+
+    /// Document `document_id` as a handle, e.g. `sheets.spreadsheet(ID).worksheet("Tab")`
+    fn spreadsheet(&self, document_id: &str) -> Spreadsheet<'_, Self>
+    where
+        Self: Sized,
+    {
+        Spreadsheet::new(self, document_id)
+    }
+
+    /// The tab called `tab_name`
+    async fn tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let tabs = self.list_tabs(document_id).await?;
+        match tabs.iter().position(|tab| tab.title == tab_name) {
+            Some(i) => Ok(tabs[i].clone()),
+            None => Err(SheetsError::tab_not_found(
+                tab_name,
+                tabs.into_iter().map(|tab| tab.title).collect(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl SheetsBackend for Sheets {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(rows = tracing::field::Empty, latency_ms = tracing::field::Empty))
+    )]
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        get_values(self, document_id, range, "FORMATTED_VALUE").await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(rows = tracing::field::Empty, latency_ms = tracing::field::Empty))
+    )]
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        get_values(self, document_id, range, "FORMULA").await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(rows = tracing::field::Empty, latency_ms = tracing::field::Empty))
+    )]
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        if ranges.is_empty() {
+            return Ok(vec![]);
+        }
+        let started = Instant::now();
+        let mut call = self
+            .spreadsheets()
+            .values_batch_get(document_id)
+            .value_render_option("FORMATTED_VALUE");
+        for range in ranges {
+            call = call.add_ranges(range);
+        }
+        let (_body, response) = match call.doit().await {
+            Ok(response) => response,
+            Err(e) => {
+                let error = SheetsError::from_api(e, document_id);
+                return Err(check_tab(self, document_id, &ranges[0], error).await);
+            }
+        };
+        trace::record_latency(started);
+
+        let mut values: Vec<Vec<Vec<String>>> = response
+            .value_ranges
+            .unwrap_or_default()
+            .into_iter()
+            .map(|value_range| value_range.values.unwrap_or_default())
+            .collect();
+        values.resize(ranges.len(), vec![]);
+        trace::record("rows", values.iter().map(Vec::len).sum::<usize>() as u64);
+        Ok(values)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, rows), fields(rows = rows.len(), latency_ms = tracing::field::Empty))
+    )]
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        update_values(self, document_id, range, rows, "USER_ENTERED").await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, rows), fields(rows = rows.len(), latency_ms = tracing::field::Empty))
+    )]
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        update_values(self, document_id, range, rows, "RAW").await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, data), fields(ranges = data.len(), latency_ms = tracing::field::Empty))
+    )]
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        let started = Instant::now();
+        let mut rows_written = 0;
+        for batch in batch_payload(data)? {
+            let first_range = batch[0].0.clone();
+            let batch_rows: usize = batch.iter().map(|(_, rows)| rows.len()).sum();
+            let req = BatchUpdateValuesRequest {
+                data: Some(
+                    batch
+                        .into_iter()
+                        .map(|(range, rows)| ValueRange {
+                            major_dimension: None,
+                            range: Some(range),
+                            values: Some(rows),
+                        })
+                        .collect(),
+                ),
+                value_input_option: Some("USER_ENTERED".to_string()),
+                ..Default::default()
+            };
+            self.spreadsheets()
+                .values_batch_update(req, document_id)
+                .doit()
+                .await
+                .map_err(|e| {
+                    partial_write(
+                        SheetsError::from_api(e, document_id),
+                        first_range,
+                        rows_written,
+                    )
+                })?;
+            rows_written += batch_rows;
+        }
+        trace::record_latency(started);
+
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, rows), fields(rows = rows.len(), latency_ms = tracing::field::Empty))
+    )]
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let started = Instant::now();
+        let (tab_name, _) = tab_and_span(range)?;
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut rows_written = 0;
+        // where the first chunk landed, as zero-based `(row, column)`
+        let mut origin = None;
+        let mut server_time = None;
+        // each chunk lands after the previous one, so they all append to the same range
+        for (_offset, chunk) in split_payload(rows) {
+            let chunk_rows = chunk.len();
+            let req = ValueRange {
+                major_dimension: None,
+                range: Some(range.to_string()),
+                values: Some(chunk),
+            };
+            match self
+                .spreadsheets()
+                .values_append(req, document_id, range)
+                .value_input_option("USER_ENTERED")
+                .include_values_in_response(false)
+                .param("fields", "updates.updatedRange")
+                .doit()
+                .await
+            {
+                Ok((http_response, response)) => {
+                    if origin.is_none() {
+                        server_time = http_response
+                            .headers()
+                            .get(hyper::header::DATE)
+                            .and_then(|date| date.to_str().ok())
+                            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+                            .map(|date| date.with_timezone(&chrono::Utc).to_rfc3339());
+                        origin = response
+                            .updates
+                            .and_then(|updates| updates.updated_range)
+                            .and_then(|updated| tab_and_span(&updated).ok())
+                            .map(|(_, span)| (span.start_row, span.start_column));
+                    }
+                }
+                Err(e) => {
+                    let error = check_tab(
+                        self,
+                        document_id,
+                        range,
+                        SheetsError::from_api(e, document_id),
+                    );
+                    return Err(partial_write(error.await, range.to_string(), rows_written));
+                }
+            }
+            rows_written += chunk_rows;
+        }
+        trace::record_latency(started);
+
+        let receipt = match origin {
+            Some((row, column)) => {
+                WriteReceipt::covering(&tab_name, row, column, rows_written, width)
+            }
+            None => WriteReceipt::covering(&tab_name, 0, 0, 0, 0),
+        };
+        Ok(WriteReceipt {
+            server_time,
+            ..receipt
+        })
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(latency_ms = tracing::field::Empty))
+    )]
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        let started = Instant::now();
+        if let Err(e) = self
+            .spreadsheets()
+            .values_clear(ClearValuesRequest::default(), document_id, range)
+            .doit()
+            .await
+        {
+            let error = SheetsError::from_api(e, document_id);
+            return Err(check_tab(self, document_id, range, error).await);
+        }
+        trace::record_latency(started);
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(latency_ms = tracing::field::Empty))
+    )]
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        let sheet_id = self.tab(document_id, tab_name).await?.sheet_id;
+        let request = Request {
+            delete_dimension: Some(DeleteDimensionRequest {
+                range: Some(DimensionRange {
+                    dimension: Some("ROWS".to_string()),
+                    sheet_id: Some(sheet_id),
+                    start_index: Some(start as i32),
+                    end_index: Some(end as i32),
+                }),
+            }),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        batch_update(self, document_id, vec![request]).await?;
+        trace::record_latency(started);
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(latency_ms = tracing::field::Empty))
+    )]
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        let started = Instant::now();
+        let (_body, spreadsheet) = self
+            .spreadsheets()
+            .get(document_id)
+            .param("fields", TAB_FIELDS)
+            .doit()
+            .await
+            .map_err(|e| SheetsError::from_api(e, document_id))?;
+        trace::record_latency(started);
+
+        Ok(spreadsheet
+            .sheets
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|sheet| sheet.properties)
+            .map(tab_info)
+            .collect())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(latency_ms = tracing::field::Empty))
+    )]
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let request = Request {
+            add_sheet: Some(AddSheetRequest {
+                properties: Some(SheetProperties {
+                    title: Some(tab_name.to_string()),
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let response = batch_update(self, document_id, vec![request]).await?;
+        trace::record_latency(started);
+
+        response
+            .replies
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|reply| reply.add_sheet?.properties)
+            .map(tab_info)
+            .ok_or_else(|| SheetsError::UnexpectedResponse {
+                reason: "addSheet reply has no properties".to_string(),
+            })
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(latency_ms = tracing::field::Empty))
+    )]
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        let sheet_id = self.tab(document_id, tab_name).await?.sheet_id;
+        let request = Request {
+            delete_sheet: Some(DeleteSheetRequest {
+                sheet_id: Some(sheet_id),
+            }),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        batch_update(self, document_id, vec![request]).await?;
+        trace::record_latency(started);
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self), fields(latency_ms = tracing::field::Empty))
+    )]
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        let sheet_id = self.tab(document_id, tab_name).await?.sheet_id;
+        let request = Request {
+            duplicate_sheet: Some(DuplicateSheetRequest {
+                source_sheet_id: Some(sheet_id),
+                new_sheet_name: Some(new_name.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let response = batch_update(self, document_id, vec![request]).await?;
+        trace::record_latency(started);
+
+        response
+            .replies
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|reply| reply.duplicate_sheet?.properties)
+            .map(tab_info)
+            .ok_or_else(|| SheetsError::UnexpectedResponse {
+                reason: "duplicateSheet reply has no properties".to_string(),
+            })
+    }
+}
+
+/// Cells of `range`, rendered according to `value_render_option`
+async fn get_values(
+    sheets: &Sheets,
+    document_id: &str,
+    range: &str,
+    value_render_option: &str,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let started = Instant::now();
+    let (_body, value_range) = match sheets
+        .spreadsheets()
+        .values_get(document_id, range)
+        .value_render_option(value_render_option)
+        .doit()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            let error = SheetsError::from_api(e, document_id);
+            return Err(check_tab(sheets, document_id, range, error).await);
+        }
+    };
+    trace::record_latency(started);
+
+    let rows = value_range.values.unwrap_or_default();
+    trace::record("rows", rows.len() as u64);
+    Ok(rows)
+}
+
+/// Overwrite cells starting at `range` with `rows`, interpreted according to
+/// `value_input_option`, in as many requests as the payload limit needs
+async fn update_values(
+    sheets: &Sheets,
+    document_id: &str,
+    range: &str,
+    rows: Vec<Vec<String>>,
+    value_input_option: &str,
+) -> Result<(), SheetsError> {
+    let started = Instant::now();
+    let mut rows_written = 0;
+    for (offset, chunk) in split_payload(rows) {
+        let chunk_range = offset_range(range, offset)?;
+        let chunk_rows = chunk.len();
+        let req = ValueRange {
+            major_dimension: None,
+            range: Some(chunk_range.clone()),
+            values: Some(chunk),
+        };
+        if let Err(e) = sheets
+            .spreadsheets()
+            .values_update(req, document_id, &chunk_range)
+            .value_input_option(value_input_option)
+            .include_values_in_response(false)
+            .doit()
+            .await
+        {
+            let error = check_tab(
+                sheets,
+                document_id,
+                range,
+                SheetsError::from_api(e, document_id),
+            );
+            return Err(partial_write(error.await, chunk_range, rows_written));
+        }
+        rows_written += chunk_rows;
+    }
+    trace::record_latency(started);
+
+    Ok(())
+}
+
+/// `error` from a request on `range`, replaced by `SheetsError::TabNotFound` if the request
+/// failed because the document has no such tab
+async fn check_tab(
+    sheets: &Sheets,
+    document_id: &str,
+    range: &str,
+    error: SheetsError,
+) -> SheetsError {
+    if !matches!(error, SheetsError::InvalidRange { .. }) {
+        return error;
+    }
+    let tab_name = match tab_and_span(range) {
+        Ok((tab_name, _)) => tab_name,
+        Err(_) => return error,
+    };
+    match sheets.list_tabs(document_id).await {
+        Ok(tabs) if !tabs.iter().any(|tab| tab.title == tab_name) => {
+            SheetsError::tab_not_found(&tab_name, tabs.into_iter().map(|tab| tab.title).collect())
+        }
+        _ => error,
+    }
+}
+
+/// Partial-response mask for `list_tabs`, so it doesn't download the formatting of every cell
+const TAB_FIELDS: &str = "sheets.properties(sheetId,title,gridProperties(rowCount,columnCount))";
+
+/// Largest request body sent in one values call. The API rejects bodies much over this with
+/// 400 or 413 errors that don't say why, so bigger writes are split.
+const MAX_PAYLOAD_BYTES: usize = 2 * 1024 * 1024;
+
+/// Approximate size of `row` as JSON, counting quotes, separators and escapes
+fn payload_size(row: &[String]) -> usize {
+    let cells: usize = row
+        .iter()
+        .map(|cell| {
+            cell.bytes()
+                .map(|b| match b {
+                    b'"' | b'\\' => 2,
+                    0..=0x1f => 6,
+                    _ => 1,
+                })
+                .sum::<usize>()
+                + 3
+        })
+        .sum();
+    cells + 3
+}
+
+/// Split `rows` into runs under `MAX_PAYLOAD_BYTES`, each with the index of its first row.
+/// A single row over the limit is sent on its own.
+fn split_payload(rows: Vec<Vec<String>>) -> Vec<(usize, Vec<Vec<String>>)> {
+    let mut chunks = vec![(0, vec![])];
+    let mut size = 0;
+    for (i, row) in rows.into_iter().enumerate() {
+        let row_size = payload_size(&row);
+        let (_, current) = chunks.last_mut().unwrap();
+        if !current.is_empty() && size + row_size > MAX_PAYLOAD_BYTES {
+            chunks.push((i, vec![]));
+            size = 0;
+        }
+        size += row_size;
+        chunks.last_mut().unwrap().1.push(row);
+    }
+    chunks
+}
+
+/// Group `(range, rows)` pairs into batches under `MAX_PAYLOAD_BYTES`, splitting large ranges.
+/// Ranges without rows are left out, so no batch is empty.
+fn batch_payload(
+    data: Vec<(String, Vec<Vec<String>>)>,
+) -> Result<Vec<Vec<(String, Vec<Vec<String>>)>>, SheetsError> {
+    let mut batches: Vec<Vec<(String, Vec<Vec<String>>)>> = vec![];
+    let mut size = 0;
+    for (range, rows) in data {
+        for (offset, chunk) in split_payload(rows) {
+            // writing no rows to a range changes nothing, so it needn't be sent
+            if chunk.is_empty() {
+                continue;
+            }
+            let chunk_size: usize = chunk.iter().map(|row| payload_size(row)).sum();
+            if batches.is_empty() || size + chunk_size > MAX_PAYLOAD_BYTES {
+                batches.push(vec![]);
+                size = 0;
+            }
+            size += chunk_size;
+            batches
+                .last_mut()
+                .unwrap()
+                .push((offset_range(&range, offset)?, chunk));
+        }
+    }
+    Ok(batches)
+}
+
+/// The top-left cell `offset` rows below the start of `range`, or `range` itself for `0`
+fn offset_range(range: &str, offset: usize) -> Result<String, SheetsError> {
+    if offset == 0 {
+        return Ok(range.to_string());
+    }
+    let (tab_name, span) = tab_and_span(range)?;
+    Ok(format!(
+        "{}!{}{}",
+        quote_tab_name(&tab_name),
+        column_letter(span.start_column),
+        span.start_row + offset + 1
+    ))
+}
+
+/// `error` from a split write, noting where it stopped if earlier requests succeeded
+fn partial_write(error: SheetsError, range: String, rows_written: usize) -> SheetsError {
+    if rows_written == 0 {
+        return error;
+    }
+    SheetsError::PartialWrite {
+        range,
+        rows_written,
+        source: Box::new(error),
+    }
+}
+
+fn tab_info(properties: SheetProperties) -> TabInfo {
+    let grid = properties.grid_properties.unwrap_or_default();
+    TabInfo {
+        sheet_id: properties.sheet_id.unwrap_or(0),
+        title: properties.title.unwrap_or_default(),
+        row_count: grid.row_count.unwrap_or(0) as usize,
+        column_count: grid.column_count.unwrap_or(0) as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(count: usize, cell: &str) -> Vec<Vec<String>> {
+        vec![vec![cell.to_string()]; count]
+    }
+
+    /// A cell big enough that a row holding it is over `MAX_PAYLOAD_BYTES` on its own
+    fn huge_cell() -> String {
+        "x".repeat(MAX_PAYLOAD_BYTES)
+    }
+
+    #[test]
+    fn splits_nothing_into_one_empty_chunk() {
+        assert_eq!(split_payload(vec![]), vec![(0, vec![])]);
+    }
+
+    #[test]
+    fn splits_rows_at_the_payload_limit() {
+        let cell = "x".repeat(1024);
+        let per_chunk = MAX_PAYLOAD_BYTES / payload_size(&[cell.clone()]);
+        let chunks = split_payload(rows(per_chunk + 1, &cell));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].1.len(), per_chunk);
+        assert_eq!(chunks[1], (per_chunk, rows(1, &cell)));
+    }
+
+    #[test]
+    fn sends_a_row_over_the_limit_on_its_own() {
+        let huge = huge_cell();
+        let chunks = split_payload(vec![
+            vec!["a".to_string()],
+            vec![huge.clone()],
+            vec!["b".to_string()],
+        ]);
+        assert_eq!(
+            chunks,
+            vec![(0, rows(1, "a")), (1, rows(1, &huge)), (2, rows(1, "b"))]
+        );
+    }
+
+    #[test]
+    fn batches_nothing_into_no_batches() {
+        assert!(batch_payload(vec![]).unwrap().is_empty());
+        let empty = vec![("Sheet1!A1".to_string(), vec![])];
+        assert!(batch_payload(empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn leaves_out_an_empty_first_range() {
+        let data = vec![
+            ("Sheet1!A1".to_string(), vec![]),
+            ("Sheet1!C1".to_string(), rows(2, "a")),
+        ];
+        assert_eq!(
+            batch_payload(data).unwrap(),
+            vec![vec![("Sheet1!C1".to_string(), rows(2, "a"))]]
+        );
+    }
+
+    #[test]
+    fn batches_small_ranges_together() {
+        let data = vec![
+            ("Sheet1!A1".to_string(), rows(2, "a")),
+            ("Sheet1!C1".to_string(), rows(3, "b")),
+        ];
+        assert_eq!(batch_payload(data.clone()).unwrap(), vec![data]);
+    }
+
+    #[test]
+    fn batches_a_row_over_the_limit_on_its_own() {
+        let huge = huge_cell();
+        let data = vec![
+            ("Sheet1!A1".to_string(), rows(1, "a")),
+            (
+                "Sheet1!C1".to_string(),
+                vec![vec![huge.clone()], vec!["b".to_string()]],
+            ),
+        ];
+        assert_eq!(
+            batch_payload(data).unwrap(),
+            vec![
+                vec![("Sheet1!A1".to_string(), rows(1, "a"))],
+                vec![("Sheet1!C1".to_string(), rows(1, &huge))],
+                vec![("'Sheet1'!C2".to_string(), rows(1, "b"))],
+            ]
+        );
+    }
+}
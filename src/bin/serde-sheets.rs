@@ -0,0 +1,273 @@
+//! `serde-sheets`: dump, load, tail and list the tabs of a spreadsheet from the command line.
+//!
+//! Credentials and the default document come from the environment, as for
+//! `SheetsConfig::from_env`.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{Map, Value};
+use serde_sheets::{
+    dynamic_rows, import_csv, range::quote_tab_name, CellRef, DocumentId, ImportMode,
+    ImportOptions, RangeRef, Sheets, SheetsBackend, SheetsConfig, SheetsError,
+};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Parser)]
+#[command(name = "serde-sheets", version, about)]
+struct Cli {
+    /// Spreadsheet ID or URL, instead of `SHEETS_DOCUMENT_ID`
+    #[arg(long, short, global = true)]
+    document: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the tabs of the document
+    Tabs,
+    /// Write every row of a tab to a file, or stdout
+    Dump {
+        tab: String,
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Defaults to the output's extension, or CSV
+        #[arg(long, short)]
+        format: Option<Format>,
+    },
+    /// Write a file, whose first row or keys make the header, to a tab
+    Load {
+        tab: String,
+        input: PathBuf,
+        /// Defaults to the input's extension, or CSV
+        #[arg(long, short)]
+        format: Option<Format>,
+        /// Append to the rows already there instead of replacing them
+        #[arg(long)]
+        append: bool,
+    },
+    /// Print rows as they are appended to a tab, until interrupted
+    Tail {
+        tab: String,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        #[arg(long, short, default_value = "csv")]
+        format: Format,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    fn of(path: Option<&Path>, given: Option<Format>) -> Format {
+        let extension = path.and_then(Path::extension).and_then(|ext| ext.to_str());
+        match (given, extension) {
+            (Some(format), _) => format,
+            (None, Some("ndjson" | "jsonl")) => Format::Ndjson,
+            _ => Format::Csv,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("serde-sheets: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let config = SheetsConfig::from_env()?;
+    let document_id = match cli.document {
+        Some(document) => DocumentId::parse(&document)?,
+        None => config
+            .document_id
+            .clone()
+            .ok_or(SheetsError::MissingConfig {
+                name: serde_sheets::config::SHEETS_DOCUMENT_ID,
+            })?,
+    };
+    let sheets = config.connect().await?;
+    let document_id = document_id.as_str();
+
+    match cli.command {
+        Command::Tabs => {
+            for tab in sheets.list_tabs(document_id).await? {
+                println!("{}\t{}x{}", tab.title, tab.row_count, tab.column_count);
+            }
+        }
+        Command::Dump {
+            tab,
+            output,
+            format,
+        } => {
+            let rows = sheets.read_values(document_id, &tab).await?;
+            let format = Format::of(output.as_deref(), format);
+            match output {
+                Some(path) => write_rows(File::create(path)?, format, &rows, true)?,
+                None => write_rows(io::stdout().lock(), format, &rows, true)?,
+            }
+        }
+        Command::Load {
+            tab,
+            input,
+            format,
+            append,
+        } => {
+            let mode = if append {
+                ImportMode::Append
+            } else {
+                ImportMode::Replace
+            };
+            let loaded = match Format::of(Some(&input), format) {
+                Format::Csv => {
+                    let options = ImportOptions {
+                        mode,
+                        ..ImportOptions::default()
+                    };
+                    import_csv(&sheets, document_id, &tab, &input, &options).await?
+                }
+                Format::Ndjson => load_ndjson(&sheets, document_id, &tab, &input, mode).await?,
+            };
+            eprintln!("loaded {} rows into {}", loaded, tab);
+        }
+        Command::Tail {
+            tab,
+            interval,
+            format,
+        } => tail(&sheets, document_id, &tab, interval, format).await?,
+    }
+    Ok(())
+}
+
+/// Write `rows` as `format`, the first being the header. CSV rows are padded to the widest;
+/// NDJSON has one object per data row, keyed by the header.
+fn write_rows(
+    mut writer: impl Write,
+    format: Format,
+    rows: &[Vec<String>],
+    with_header: bool,
+) -> Result<(), Error> {
+    let (header, data) = match rows.split_first() {
+        Some((header, data)) => (header, data),
+        None => return Ok(()),
+    };
+    match format {
+        Format::Csv => {
+            let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+            let mut wtr = csv::WriterBuilder::new().from_writer(writer);
+            let rows = if with_header { rows } else { data };
+            for row in rows {
+                let padding = std::iter::repeat("").take(width - row.len());
+                wtr.write_record(row.iter().map(String::as_str).chain(padding))?;
+            }
+            wtr.flush()?;
+        }
+        Format::Ndjson => {
+            for row in data {
+                let object: Map<String, Value> = header
+                    .iter()
+                    .zip(row.iter().chain(std::iter::repeat(&String::new())))
+                    .map(|(key, cell)| (key.clone(), Value::String(cell.clone())))
+                    .collect();
+                serde_json::to_writer(&mut writer, &object)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Load the JSON objects in `path`, one per line, with columns keyed by the tab's header when
+/// appending and by first appearance otherwise
+async fn load_ndjson(
+    sheets: &Sheets,
+    document_id: &str,
+    tab: &str,
+    path: &Path,
+    mode: ImportMode,
+) -> Result<usize, Error> {
+    let mut records = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            records.push(serde_json::from_str::<Map<String, Value>>(&line)?);
+        }
+    }
+
+    let header = match mode {
+        ImportMode::Replace => vec![],
+        ImportMode::Append => {
+            let header_range = format!("{}!1:1", quote_tab_name(tab));
+            let rows = sheets.read_values(document_id, &header_range).await?;
+            rows.into_iter().next().unwrap_or_default()
+        }
+    };
+    let column_order: Vec<&str> = header.iter().map(String::as_str).collect();
+    let mut rows = dynamic_rows(&records, &column_order)?;
+
+    if mode == ImportMode::Replace {
+        serde_sheets::clear_tab(sheets, document_id, tab).await?;
+    } else if !header.is_empty() {
+        let found = rows.remove(0);
+        if found != header {
+            return Err(SheetsError::HeaderMismatch {
+                expected: header,
+                found,
+            }
+            .into());
+        }
+    }
+    sheets.append_values(document_id, tab, rows).await?;
+    Ok(records.len())
+}
+
+/// Print the rows appended to `tab` every `interval` seconds, skipping the ones already there
+async fn tail(
+    sheets: &Sheets,
+    document_id: &str,
+    tab: &str,
+    interval: u64,
+    format: Format,
+) -> Result<(), Error> {
+    let rows = sheets.read_values(document_id, tab).await?;
+    let mut header = rows.first().cloned().unwrap_or_default();
+    let mut seen = rows.len();
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let range = RangeRef {
+            tab_name: tab.to_string(),
+            start: CellRef::new(seen, 0),
+            end_row: None,
+            end_column: None,
+        };
+        let new_rows = sheets.read_values(document_id, &range.to_string()).await?;
+        if new_rows.is_empty() {
+            continue;
+        }
+        seen += new_rows.len();
+
+        let mut rows = new_rows;
+        if header.is_empty() {
+            header = rows[0].clone();
+        } else {
+            rows.insert(0, header.clone());
+        }
+        write_rows(io::stdout().lock(), format, &rows, false)?;
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{deserialize_rows, trace, SheetsBackend, SheetsError};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Read-through cache of raw cell values, keyed by `(document_id, range)`.
+///
+/// Entries older than the configured TTL are refetched on the next read. Writes made
+/// through the crate do not invalidate the cache, call `invalidate` after writing.
+pub struct ReadCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), CacheEntry>>,
+}
+
+struct CacheEntry {
+    fetched_at: Instant,
+    rows: Vec<Vec<String>>,
+}
+
+impl ReadCache {
+    /// Create an empty cache whose entries expire after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached equivalent of `serde_sheets::read_all`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, sheets)))]
+    pub async fn read_all<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &B,
+        document_id: &str,
+        tab_name: &str,
+    ) -> Result<Vec<T>, SheetsError> {
+        let rows = self.read_values(sheets, document_id, tab_name).await?;
+        deserialize_rows(rows)
+    }
+
+    /// Fetch the raw cell values in `range`, serving from the cache if the entry is fresh
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, sheets), fields(cache_hit = tracing::field::Empty))
+    )]
+    pub async fn read_values<B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &B,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let key = (document_id.to_string(), range.to_string());
+
+        if let Some(entry) = self.entries.lock().unwrap().get(&key) {
+            if entry.fetched_at.elapsed() < self.ttl {
+                trace::record("cache_hit", 1);
+                return Ok(entry.rows.clone());
+            }
+        }
+
+        trace::record("cache_hit", 0);
+        let rows = sheets.read_values(document_id, range).await?;
+        self.entries.lock().unwrap().insert(
+            key,
+            CacheEntry {
+                fetched_at: Instant::now(),
+                rows: rows.clone(),
+            },
+        );
+
+        Ok(rows)
+    }
+
+    /// Drop the cached entry for `range` in document `document_id`
+    pub fn invalidate(&self, document_id: &str, range: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&(document_id.to_string(), range.to_string()));
+    }
+
+    /// Drop all cached entries for document `document_id`
+    pub fn invalidate_document(&self, document_id: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(document, _), _| document != document_id);
+    }
+
+    /// Drop every cached entry
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,186 @@
+//! Embedded charts over ranges written by this crate, such as a `WriteReceipt`'s
+//! `updated_range`.
+
+use crate::{batch_update, range::CellRef, RangeRef, SheetsBackend, SheetsError};
+use google_sheets4::{
+    api::{
+        AddChartRequest, BasicChartDomain, BasicChartSeries, BasicChartSpec, ChartData,
+        ChartSourceRange, ChartSpec, EmbeddedChart, EmbeddedObjectPosition, GridCoordinate,
+        GridRange, OverlayPosition, PieChartSpec, Request,
+    },
+    Sheets,
+};
+
+/// The kind of chart `Chart` draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartType {
+    Line,
+    /// Horizontal bars
+    Bar,
+    /// Vertical bars
+    Column,
+    /// One slice per row, sized by the second column
+    Pie,
+}
+
+/// An embedded chart of a table whose first column labels the rows, whose other columns are
+/// the series and whose first row is the header, e.g.
+///
+/// `Chart::new(ChartType::Line).title("Daily signups").add(&sheets, DOCUMENT_ID, &receipt.updated_range)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chart {
+    chart_type: ChartType,
+    title: Option<String>,
+    position: Option<(String, String)>,
+    size: Option<(i32, i32)>,
+}
+
+impl Chart {
+    pub fn new(chart_type: ChartType) -> Self {
+        Self {
+            chart_type,
+            title: None,
+            position: None,
+            size: None,
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Put the chart's top-left corner at cell `anchor`, e.g. `H2`, of tab `tab_name`,
+    /// instead of just right of the data
+    pub fn position(mut self, tab_name: impl Into<String>, anchor: impl Into<String>) -> Self {
+        self.position = Some((tab_name.into(), anchor.into()));
+        self
+    }
+
+    /// Size in pixels, instead of the API's default
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width as i32, height as i32));
+        self
+    }
+
+    /// Add the chart of `data_range`, e.g. `'Signups'!A1:C31`, returning its chart ID. The
+    /// range's columns must be bounded, as they are in a `WriteReceipt`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, sheets)))]
+    pub async fn add(
+        &self,
+        sheets: &Sheets,
+        document_id: &str,
+        data_range: &str,
+    ) -> Result<i32, SheetsError> {
+        let range = RangeRef::parse(data_range)?;
+        let end_column = range
+            .end_column
+            .ok_or_else(|| SheetsError::MalformedRange {
+                range: data_range.to_string(),
+            })?;
+        let (first, last) = (range.start.column, end_column);
+        let source_id = sheets.tab(document_id, &range.tab_name).await?.sheet_id;
+        let columns = |start: usize, end: usize| ChartData {
+            source_range: Some(ChartSourceRange {
+                sources: Some(vec![GridRange {
+                    sheet_id: Some(source_id),
+                    start_row_index: Some(range.start.row as i32),
+                    end_row_index: range.end_row.map(|end| end as i32 + 1),
+                    start_column_index: Some(start as i32),
+                    end_column_index: Some(end as i32),
+                }]),
+            }),
+            ..Default::default()
+        };
+
+        let mut spec = ChartSpec {
+            title: self.title.clone(),
+            ..Default::default()
+        };
+        match self.chart_type {
+            ChartType::Pie => {
+                spec.pie_chart = Some(PieChartSpec {
+                    domain: Some(columns(first, first + 1)),
+                    series: Some(columns(first + 1, first + 2)),
+                    legend_position: Some("RIGHT_LEGEND".to_string()),
+                    ..Default::default()
+                });
+            }
+            chart_type => {
+                spec.basic_chart = Some(BasicChartSpec {
+                    chart_type: Some(
+                        match chart_type {
+                            ChartType::Bar => "BAR",
+                            ChartType::Column => "COLUMN",
+                            _ => "LINE",
+                        }
+                        .to_string(),
+                    ),
+                    legend_position: Some("BOTTOM_LEGEND".to_string()),
+                    header_count: Some(1),
+                    domains: Some(vec![BasicChartDomain {
+                        domain: Some(columns(first, first + 1)),
+                        ..Default::default()
+                    }]),
+                    series: Some(
+                        (first + 1..=last)
+                            .map(|column| BasicChartSeries {
+                                series: Some(columns(column, column + 1)),
+                                target_axis: Some(
+                                    if chart_type == ChartType::Bar {
+                                        "BOTTOM_AXIS"
+                                    } else {
+                                        "LEFT_AXIS"
+                                    }
+                                    .to_string(),
+                                ),
+                                ..Default::default()
+                            })
+                            .collect(),
+                    ),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let (sheet_id, anchor) = match &self.position {
+            Some((tab_name, anchor)) => (
+                sheets.tab(document_id, tab_name).await?.sheet_id,
+                CellRef::parse(anchor)?,
+            ),
+            None => (source_id, CellRef::new(range.start.row, last + 2)),
+        };
+        let (width, height) = self.size.unzip();
+        let chart = EmbeddedChart {
+            spec: Some(spec),
+            position: Some(EmbeddedObjectPosition {
+                overlay_position: Some(OverlayPosition {
+                    anchor_cell: Some(GridCoordinate {
+                        sheet_id: Some(sheet_id),
+                        row_index: Some(anchor.row as i32),
+                        column_index: Some(anchor.column as i32),
+                    }),
+                    width_pixels: width,
+                    height_pixels: height,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let request = Request {
+            add_chart: Some(AddChartRequest { chart: Some(chart) }),
+            ..Default::default()
+        };
+        let response = batch_update(sheets, document_id, vec![request]).await?;
+        response
+            .replies
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|reply| reply.add_chart?.chart?.chart_id)
+            .ok_or_else(|| SheetsError::UnexpectedResponse {
+                reason: "addChart reply has no chart ID".to_string(),
+            })
+    }
+}
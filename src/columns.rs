@@ -0,0 +1,55 @@
+/// The narrowest type every non-empty cell of a column parses as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnType {
+    Boolean,
+    Integer,
+    Float,
+    Text,
+}
+
+/// Infer the type of column `index` from the data rows of a tab. Cells missing from short
+/// rows and empty cells are nulls and don't affect the result.
+pub(crate) fn infer_column_type(rows: &[Vec<String>], index: usize) -> ColumnType {
+    let mut cells = rows
+        .iter()
+        .filter_map(|row| row.get(index))
+        .filter(|cell| !cell.is_empty())
+        .peekable();
+    if cells.peek().is_none() {
+        return ColumnType::Text;
+    }
+
+    let cells: Vec<&String> = cells.collect();
+    if cells.iter().all(|cell| parse_bool(cell).is_some()) {
+        ColumnType::Boolean
+    } else if cells.iter().all(|cell| cell.parse::<i64>().is_ok()) {
+        ColumnType::Integer
+    } else if cells.iter().all(|cell| cell.parse::<f64>().is_ok()) {
+        ColumnType::Float
+    } else {
+        ColumnType::Text
+    }
+}
+
+/// Parse a boolean as Sheets formats it, e.g. `TRUE`
+pub(crate) fn parse_bool(cell: &str) -> Option<bool> {
+    if cell.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if cell.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Cell `index` of `row`, `None` if it is empty or missing
+pub(crate) fn cell(row: &[String], index: usize) -> Option<&str> {
+    row.get(index)
+        .map(String::as_str)
+        .filter(|cell| !cell.is_empty())
+}
+
+/// Format a boolean the way Sheets displays it
+pub(crate) fn format_bool(value: bool) -> String {
+    if value { "TRUE" } else { "FALSE" }.to_string()
+}
@@ -0,0 +1,93 @@
+use crate::{
+    clear_tab, matrix::IntoCell, serialize_rows, SheetsBackend, SheetsError, WriteReceipt,
+};
+use serde::Serialize;
+
+type Compute<'a, T> = Box<dyn Fn(&T, usize) -> String + Send + Sync + 'a>;
+
+/// Extra columns computed from each record as it's written, to the right of its own fields,
+/// so derived values don't have to be fields of the record type, e.g.
+///
+/// `ComputedColumns::new().with_column("total", |o: &Order| o.qty as f64 * o.price)`
+///
+/// Reads ignore the extra columns, as they do any column the record type has no field for.
+pub struct ComputedColumns<'a, T> {
+    columns: Vec<(String, Compute<'a, T>)>,
+}
+
+impl<'a, T: Serialize> ComputedColumns<'a, T> {
+    pub fn new() -> Self {
+        Self { columns: vec![] }
+    }
+
+    /// Add column `name` holding `compute` of each record
+    pub fn with_column<C: IntoCell>(
+        mut self,
+        name: impl Into<String>,
+        compute: impl Fn(&T) -> C + Send + Sync + 'a,
+    ) -> Self {
+        self.columns.push((
+            name.into(),
+            Box::new(move |obj, _| compute(obj).into_cell()),
+        ));
+        self
+    }
+
+    /// Add column `name` holding a formula built from each record and its one-based row
+    /// number, to refer to cells of the same row, e.g. `|_, row| format!("=B{}*C{}", row, row)`
+    pub fn with_formula<C: IntoCell>(
+        mut self,
+        name: impl Into<String>,
+        compute: impl Fn(&T, usize) -> C + Send + Sync + 'a,
+    ) -> Self {
+        self.columns.push((
+            name.into(),
+            Box::new(move |obj, row| compute(obj, row).into_cell()),
+        ));
+        self
+    }
+
+    /// The rows `write_page` sends for `objects`, header first
+    pub fn rows(&self, objects: &[T]) -> Result<Vec<Vec<String>>, SheetsError> {
+        let mut rows = serialize_rows(objects, true)?;
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let has_header = rows.len() > objects.len();
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.resize(width, String::new());
+            match i.checked_sub(has_header as usize) {
+                Some(index) => row.extend(
+                    self.columns
+                        .iter()
+                        .map(|(_, compute)| compute(&objects[index], i + 1)),
+                ),
+                None => row.extend(self.columns.iter().map(|(name, _)| name.clone())),
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Like `crate::write_page`, with the computed columns after each record's fields
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, sheets, objects), fields(rows = objects.len()))
+    )]
+    pub async fn write_page<B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &B,
+        document_id: &str,
+        tab_name: &str,
+        objects: &[T],
+    ) -> Result<WriteReceipt, SheetsError> {
+        let rows = self.rows(objects)?;
+        clear_tab(sheets, document_id, tab_name).await?;
+        let receipt = WriteReceipt::new(tab_name, 0, 0, &rows);
+        sheets.write_values(document_id, tab_name, rows).await?;
+        Ok(receipt)
+    }
+}
+
+impl<'a, T: Serialize> Default for ComputedColumns<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
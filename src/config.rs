@@ -0,0 +1,61 @@
+use crate::{
+    get_sheets, DocumentId, ServiceAccountKey, Sheets, SheetsBackend, SheetsError, Spreadsheet,
+};
+use std::{env, path::PathBuf};
+
+/// Environment variable holding the service account key JSON
+pub const SERVICE_ACCOUNT_JSON: &str = "SERVICE_ACCOUNT_JSON";
+
+/// Environment variable holding the default document, as an ID or spreadsheet URL
+pub const SHEETS_DOCUMENT_ID: &str = "SHEETS_DOCUMENT_ID";
+
+/// Environment variable holding the path to cache access tokens at
+pub const SHEETS_TOKEN_CACHE: &str = "SHEETS_TOKEN_CACHE";
+
+/// Everything needed to build a client, and the document a tool works on by default
+#[derive(Debug, Clone)]
+pub struct SheetsConfig {
+    pub service_account: ServiceAccountKey,
+    pub document_id: Option<DocumentId>,
+    pub token_cache: Option<PathBuf>,
+}
+
+impl SheetsConfig {
+    /// Read the key from `SERVICE_ACCOUNT_JSON`, and the optional `SHEETS_DOCUMENT_ID` and
+    /// `SHEETS_TOKEN_CACHE`. Empty variables count as unset.
+    pub fn from_env() -> Result<Self, SheetsError> {
+        let service_account = var(SERVICE_ACCOUNT_JSON).ok_or(SheetsError::MissingConfig {
+            name: SERVICE_ACCOUNT_JSON,
+        })?;
+        Ok(Self {
+            service_account: serde_json::from_str(&service_account)?,
+            document_id: var(SHEETS_DOCUMENT_ID)
+                .map(|id| DocumentId::parse(&id))
+                .transpose()?,
+            token_cache: var(SHEETS_TOKEN_CACHE).map(PathBuf::from),
+        })
+    }
+
+    /// A client authenticated with the service account
+    pub async fn connect(&self) -> Result<Sheets, SheetsError> {
+        get_sheets(self.service_account.clone(), self.token_cache.clone()).await
+    }
+
+    /// The default document, through `sheets`
+    pub fn spreadsheet<'a, B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &'a B,
+    ) -> Result<Spreadsheet<'a, B>, SheetsError> {
+        let document_id = self
+            .document_id
+            .as_ref()
+            .ok_or(SheetsError::MissingConfig {
+                name: SHEETS_DOCUMENT_ID,
+            })?;
+        Ok(Spreadsheet::new(sheets, document_id.as_str()))
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
@@ -0,0 +1,240 @@
+use crate::{
+    grid::{clear_span, read_span, tab_and_span, trim_grid, write_at},
+    SheetsBackend, SheetsError, TabInfo, WriteReceipt,
+};
+use async_trait::async_trait;
+use csv::{ReaderBuilder, WriterBuilder};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+/// `SheetsBackend` over a directory of CSV files, one per tab (`<dir>/<tab_name>.csv`).
+///
+/// The directory stands in for a single document: `document_id` is ignored, so code
+/// written against a real spreadsheet runs unchanged against local fixtures.
+pub struct CsvDirectory {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl CsvDirectory {
+    /// Use the CSV files in `dir`, which must exist
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// `<dir>/<tab_name>.csv`, failing with `SheetsError::InvalidSheetName` for names that
+    /// would point outside the directory
+    fn path(&self, tab_name: &str) -> Result<PathBuf, SheetsError> {
+        let invalid = |reason: &str| SheetsError::InvalidSheetName {
+            name: tab_name.to_string(),
+            reason: reason.to_string(),
+        };
+        if tab_name.contains(&['/', '\\'][..]) {
+            return Err(invalid("name contains a path separator"));
+        }
+        if tab_name == "." || tab_name == ".." {
+            return Err(invalid("name is a relative path"));
+        }
+        let file_name = format!("{}.csv", tab_name);
+        let mut components = Path::new(&file_name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(self.dir.join(file_name)),
+            _ => Err(invalid("name is not a plain file name")),
+        }
+    }
+
+    fn load(&self, tab_name: &str) -> Result<Vec<Vec<String>>, SheetsError> {
+        let path = self.path(tab_name)?;
+        if !path.exists() {
+            return Err(SheetsError::tab_not_found(
+                tab_name,
+                self.titles().unwrap_or_default(),
+            ));
+        }
+        load_csv(&path)
+    }
+
+    /// Names of the tabs in the directory, sorted
+    fn titles(&self) -> Result<Vec<String>, SheetsError> {
+        let mut titles = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "csv") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    titles.push(stem.to_string());
+                }
+            }
+        }
+        titles.sort();
+        Ok(titles)
+    }
+
+    fn load_or_create(&self, tab_name: &str) -> Result<Vec<Vec<String>>, SheetsError> {
+        match self.load(tab_name) {
+            Err(SheetsError::TabNotFound { .. }) => Ok(vec![]),
+            result => result,
+        }
+    }
+
+    fn save(&self, tab_name: &str, rows: &[Vec<String>]) -> Result<(), SheetsError> {
+        let mut wtr = WriterBuilder::new()
+            .flexible(true)
+            .from_path(self.path(tab_name)?)?;
+        for row in rows {
+            wtr.write_record(row)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SheetsBackend for CsvDirectory {
+    async fn read_values(
+        &self,
+        _document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        let (tab_name, span) = tab_and_span(range)?;
+        Ok(read_span(&self.load(&tab_name)?, &span))
+    }
+
+    async fn write_values(
+        &self,
+        _document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut grid = self.load_or_create(&tab_name)?;
+        write_at(&mut grid, span.start_row, span.start_column, rows);
+        self.save(&tab_name, &grid)
+    }
+
+    async fn append_values(
+        &self,
+        _document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut grid = trim_grid(self.load_or_create(&tab_name)?);
+        let next_row = grid.len().max(span.start_row);
+        let receipt = WriteReceipt::new(&tab_name, next_row, span.start_column, &rows);
+        write_at(&mut grid, next_row, span.start_column, rows);
+        self.save(&tab_name, &grid)?;
+        Ok(receipt)
+    }
+
+    async fn clear_range(&self, _document_id: &str, range: &str) -> Result<(), SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut grid = self.load(&tab_name)?;
+        clear_span(&mut grid, &span);
+        self.save(&tab_name, &grid)
+    }
+
+    async fn delete_rows(
+        &self,
+        _document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut grid = self.load(tab_name)?;
+        let end = end.min(grid.len());
+        if start < end {
+            grid.drain(start..end);
+        }
+        self.save(tab_name, &grid)
+    }
+
+    async fn list_tabs(&self, _document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        self.titles()?
+            .into_iter()
+            .map(|title| {
+                let grid = load_csv(&self.path(&title)?)?;
+                Ok(TabInfo {
+                    sheet_id: sheet_id(&title),
+                    row_count: grid.len(),
+                    column_count: grid.iter().map(Vec::len).max().unwrap_or(0),
+                    title,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_tab(&self, _document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        if self.path(tab_name)?.exists() {
+            return Err(SheetsError::duplicate_tab(tab_name));
+        }
+        self.save(tab_name, &[])?;
+        Ok(TabInfo {
+            sheet_id: sheet_id(tab_name),
+            title: tab_name.to_string(),
+            row_count: 0,
+            column_count: 0,
+        })
+    }
+
+    async fn delete_tab(&self, _document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        let _guard = self.lock.lock().unwrap();
+        self.load(tab_name)?;
+        fs::remove_file(self.path(tab_name)?)?;
+        Ok(())
+    }
+}
+
+fn load_csv(path: &Path) -> Result<Vec<Vec<String>>, SheetsError> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+
+    let mut rows = vec![];
+    for record in rdr.records() {
+        rows.push(record?.iter().map(str::to_string).collect());
+    }
+    Ok(rows)
+}
+
+/// Sheet ID derived from the tab name, so it is the same for every call
+fn sheet_id(tab_name: &str) -> i32 {
+    let mut hasher = DefaultHasher::new();
+    tab_name.hash(&mut hasher);
+    (hasher.finish() & 0x7fff_ffff) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_a_duplicate_tab_like_the_api() {
+        let dir = std::env::temp_dir().join(format!("csv-dir-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let sheets = CsvDirectory::new(&dir);
+
+        sheets.add_tab("doc", "Tab").await.unwrap();
+        let error = sheets.add_tab("doc", "Tab").await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            SheetsError::duplicate_tab("Tab").to_string()
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{
+    clear_tab,
+    columns::{cell, format_bool, infer_column_type, parse_bool, ColumnType},
+    delete, trace, SheetsBackend, SheetsError,
+};
+use polars::prelude::{AnyValue, DataFrame, NamedFrom, Series};
+
+/// Read tab `tab_name` in document `document_id` into a `DataFrame`, one column per header
+/// cell. Column dtypes are inferred: `Boolean`, `Int64`, `Float64`, falling back to `Utf8`.
+/// Empty cells become nulls, and soft-deleted rows are skipped like in `read_all`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_dataframe<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<DataFrame, SheetsError> {
+    let rows = delete::without_soft_deleted(sheets.read_values(document_id, tab_name).await?);
+    let (header, rows) = match rows.split_first() {
+        Some((header, rows)) => (header, rows),
+        None => return Ok(DataFrame::default()),
+    };
+    trace::record("rows", rows.len() as u64);
+
+    let columns = header
+        .iter()
+        .enumerate()
+        .map(|(index, name)| column_series(name, rows, index))
+        .collect();
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Write `df` to tab `tab_name` in document `document_id`, with the column names as the
+/// header row. The sheet will be cleared before writing. Nulls are written as empty cells.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, df), fields(rows = df.height()))
+)]
+pub async fn write_dataframe<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    df: &DataFrame,
+) -> Result<(), SheetsError> {
+    let columns = df.get_columns();
+    let mut rows = Vec::with_capacity(df.height() + 1);
+    rows.push(columns.iter().map(|s| s.name().to_string()).collect());
+    for i in 0..df.height() {
+        rows.push(
+            columns
+                .iter()
+                .map(|s| Ok(format_value(s.get(i)?)))
+                .collect::<Result<_, SheetsError>>()?,
+        );
+    }
+
+    clear_tab(sheets, document_id, tab_name).await?;
+    sheets.write_values(document_id, tab_name, rows).await
+}
+
+fn column_series(name: &str, rows: &[Vec<String>], index: usize) -> Series {
+    let cells = rows.iter().map(|row| cell(row, index));
+    match infer_column_type(rows, index) {
+        ColumnType::Boolean => {
+            let values: Vec<Option<bool>> = cells.map(|c| c.and_then(parse_bool)).collect();
+            Series::new(name, values)
+        }
+        ColumnType::Integer => {
+            let values: Vec<Option<i64>> = cells.map(|c| c.and_then(|c| c.parse().ok())).collect();
+            Series::new(name, values)
+        }
+        ColumnType::Float => {
+            let values: Vec<Option<f64>> = cells.map(|c| c.and_then(|c| c.parse().ok())).collect();
+            Series::new(name, values)
+        }
+        ColumnType::Text => {
+            let values: Vec<Option<&str>> = cells.collect();
+            Series::new(name, values)
+        }
+    }
+}
+
+fn format_value(value: AnyValue<'_>) -> String {
+    match value {
+        AnyValue::Null => String::new(),
+        AnyValue::Boolean(b) => format_bool(b),
+        AnyValue::Utf8(s) => s.to_string(),
+        other => other.to_string(),
+    }
+}
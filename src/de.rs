@@ -0,0 +1,550 @@
+//! Deserializes rows one at a time against their header, without building the whole table
+//! as CSV text first.
+
+use crate::{
+    column_letter,
+    delete::DELETED_AT_COLUMN,
+    options::{SOURCE_RUN_ID_COLUMN, UPDATED_AT_COLUMN, UPDATED_BY_COLUMN},
+    DecimalSeparator, HeaderKey, HeaderMatching, ReadOptions, SheetsError,
+};
+use csv::{StringRecord, Trim};
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use std::borrow::Cow;
+
+/// Turns data rows into records using the header row of their tab
+pub(crate) struct RowDecoder {
+    header: StringRecord,
+    /// Buffer reused for every row, so decoding doesn't allocate per cell
+    record: StringRecord,
+    /// Header of the non-blank cells in `record`, if blank cells are treated as missing
+    present: Option<StringRecord>,
+    /// Column of `deleted_at` stamps, if soft-deleted rows are skipped and the tab has one
+    deleted_at: Option<usize>,
+    cleaning: Cleaning,
+    skip_empty_rows: bool,
+    flexible: bool,
+    /// Rewrite comma decimals such as `0,5` with a period, for serde to parse
+    decimal_comma: bool,
+}
+
+impl RowDecoder {
+    /// Decoder of rows into `T`s, with `header` matched to `T`'s fields as `options` say.
+    /// Fails with `SheetsError::DuplicateHeader` if two columns would fill the same field.
+    pub fn new<T: DeserializeOwned>(
+        header: Vec<String>,
+        options: &ReadOptions,
+    ) -> Result<Self, SheetsError> {
+        let deleted_at = header
+            .iter()
+            .position(|h| h == DELETED_AT_COLUMN)
+            .filter(|_| !options.include_deleted);
+        let mut header = StringRecord::from(header);
+        if matches!(options.csv.trim, Trim::Headers | Trim::All) {
+            header.trim();
+        }
+        if options.trim_whitespace {
+            header = header.iter().map(|name| tidy(name).into_owned()).collect();
+        }
+        if options.header_matching == HeaderMatching::Normalized
+            || !options.header_aliases.is_empty()
+        {
+            header = field_names(&header, struct_fields::<T>(), options);
+        }
+        check_duplicates(&header)?;
+        if options.deny_unknown_columns {
+            let columns = unknown_columns(&header, struct_fields::<T>());
+            if !columns.is_empty() {
+                return Err(SheetsError::UnknownColumns { columns });
+            }
+        }
+        Ok(Self {
+            header,
+            record: StringRecord::new(),
+            present: options.blank_as_missing.then(StringRecord::new),
+            deleted_at,
+            cleaning: Cleaning {
+                trim_fields: matches!(options.csv.trim, Trim::Fields | Trim::All),
+                trim_whitespace: options.trim_whitespace,
+                null_values: options.null_values.clone(),
+            },
+            skip_empty_rows: options.skip_empty_rows,
+            flexible: options.csv.flexible,
+            decimal_comma: options.decimal_separator == DecimalSeparator::Comma,
+        })
+    }
+
+    /// Position of the column that fills field `name`, after header matching and aliases
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.header.iter().position(|h| h == name)
+    }
+
+    /// The record in sheet row `row_number` (one-based) of cells `row`, or `None` if it is
+    /// empty or soft-deleted. Rows shorter than the header, as the API returns rows ending in
+    /// blank cells, are read as if padded with blank cells. A row that doesn't deserialize
+    /// fails with `SheetsError::Serde`.
+    pub fn try_decode<T: DeserializeOwned>(
+        &mut self,
+        row: &[String],
+        row_number: usize,
+    ) -> Result<Option<T>, SheetsError> {
+        if row.is_empty() || (self.skip_empty_rows && is_blank(row)) {
+            return Ok(None);
+        }
+        let decimal_comma = self.decimal_comma;
+        if let Some(cell) = self.deleted_at.and_then(|i| row.get(i)) {
+            if !cell.is_empty() {
+                return Ok(None);
+            }
+        }
+        if let Some(present) = &mut self.present {
+            // only non-blank cells are given to serde, so `#[serde(default)]` fills the rest
+            present.clear();
+            self.record.clear();
+            for (name, cell) in self.header.iter().zip(row) {
+                let cell = self.cleaning.apply(cell);
+                if !cell.trim().is_empty() {
+                    present.push_field(name);
+                    self.record.push_field(&text(&cell, decimal_comma));
+                }
+            }
+            return deserialize(&self.record, Some(present), row_number).map(Some);
+        }
+        // headerless reads of types without named fields go by position alone
+        let header = (!self.header.is_empty()).then_some(&self.header);
+        if !self.flexible && header.map_or(false, |header| row.len() > header.len()) {
+            return Err(SheetsError::Serde {
+                row: row_number,
+                column: None,
+                message: format!("expected {} fields, found {}", self.header.len(), row.len()),
+            });
+        }
+
+        self.record.clear();
+        for cell in row {
+            let cell = self.cleaning.apply(cell);
+            self.record.push_field(&text(&cell, decimal_comma));
+        }
+        for _ in row.len()..self.header.len() {
+            self.record.push_field("");
+        }
+        deserialize(&self.record, header, row_number).map(Some)
+    }
+
+    /// Records in `rows`, the first of which is sheet row `first_row`, skipping those
+    /// `try_decode` skips and failing at the first that doesn't deserialize
+    pub fn decode_all<T: DeserializeOwned>(
+        &mut self,
+        rows: impl IntoIterator<Item = Vec<String>>,
+        first_row: usize,
+    ) -> Result<Vec<T>, SheetsError> {
+        let mut records = vec![];
+        for (row_number, row) in (first_row..).zip(rows) {
+            records.extend(self.try_decode(&row, row_number)?);
+        }
+        Ok(records)
+    }
+}
+
+/// Whether every cell of `row` is blank or whitespace
+pub(crate) fn is_blank(row: &[String]) -> bool {
+    row.iter().all(|cell| cell.trim().is_empty())
+}
+
+/// How cells are tidied before anything else reads them
+struct Cleaning {
+    trim_fields: bool,
+    trim_whitespace: bool,
+    null_values: Vec<String>,
+}
+
+impl Cleaning {
+    /// `cell` trimmed as configured, or blank if it is then one of `null_values`
+    fn apply<'c>(&self, cell: &'c str) -> Cow<'c, str> {
+        let cell = if self.trim_fields { cell.trim() } else { cell };
+        let cell = if self.trim_whitespace {
+            tidy(cell)
+        } else {
+            Cow::Borrowed(cell)
+        };
+        if self.null_values.iter().any(|null| *null == *cell) {
+            Cow::Borrowed("")
+        } else {
+            cell
+        }
+    }
+}
+
+/// `cell` without surrounding whitespace, including non-breaking and zero-width spaces and
+/// byte order marks, and with non-breaking spaces inside it made plain spaces
+fn tidy(cell: &str) -> Cow<'_, str> {
+    let cell = cell.trim_matches(|c: char| c.is_whitespace() || c == '\u{200B}' || c == '\u{FEFF}');
+    let non_breaking = |c: char| c == '\u{A0}' || c == '\u{202F}';
+    if cell.contains(non_breaking) {
+        Cow::Owned(cell.replace(non_breaking, " "))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+/// The text serde gets for `cell`
+fn text(cell: &str, decimal_comma: bool) -> Cow<'_, str> {
+    // an integer kept as text, as backends storing plain text return it; Sheets drops the `'`
+    let is_integer = |s: &str| {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+    };
+    let cell = match cell.strip_prefix('\'') {
+        Some(number) if is_integer(number) => number,
+        _ => cell,
+    };
+    if decimal_comma {
+        period_decimal(cell)
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+/// `cell` with a comma decimal number such as `-1.234,5` rewritten as `-1234.5`. Whole
+/// numbers are left as they are, since `1.234` could be either.
+fn period_decimal(cell: &str) -> Cow<'_, str> {
+    let number = |cell: &str| {
+        let (whole, fraction) = cell.strip_prefix('-').unwrap_or(cell).split_once(',')?;
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let mut groups = whole.split('.');
+        let first = groups.next()?;
+        let grouped = whole.contains('.');
+        let valid = digits(fraction)
+            && digits(first)
+            && (!grouped || first.len() <= 3)
+            && groups.all(|group| group.len() == 3 && digits(group));
+        valid.then(|| cell.replace('.', "").replace(',', "."))
+    };
+    match number(cell) {
+        Some(number) => Cow::Owned(number),
+        None => Cow::Borrowed(cell),
+    }
+}
+
+/// The header to match fields against, taken from the first `options.header_rows` of `rows`
+pub(crate) fn header_row(
+    rows: &mut impl Iterator<Item = Vec<String>>,
+    options: &ReadOptions,
+) -> Option<Vec<String>> {
+    let mut header_rows: Vec<Vec<String>> = rows.take(options.header_rows.max(1)).collect();
+    let bottom = header_rows.pop()?;
+    let separator = match &options.header_key {
+        HeaderKey::Joined(separator) if !header_rows.is_empty() => separator,
+        _ => return Some(bottom),
+    };
+
+    let width = header_rows
+        .iter()
+        .map(Vec::len)
+        .chain([bottom.len()])
+        .max()
+        .unwrap_or(0);
+    // text of each upper row, carried right over blank (merged) cells
+    let mut groups = vec![String::new(); header_rows.len()];
+    Some(
+        (0..width)
+            .map(|column| {
+                let mut parts = vec![];
+                for (group, row) in groups.iter_mut().zip(&header_rows) {
+                    if let Some(cell) = row.get(column).filter(|cell| !cell.is_empty()) {
+                        *group = cell.clone();
+                    }
+                    if !group.is_empty() {
+                        parts.push(group.clone());
+                    }
+                }
+                parts.push(bottom.get(column).cloned().unwrap_or_default());
+                parts.join(separator)
+            })
+            .collect(),
+    )
+}
+
+/// `SheetsError::DuplicateHeader` for the first non-blank name in `header` more than once
+fn check_duplicates(header: &StringRecord) -> Result<(), SheetsError> {
+    for (i, name) in header.iter().enumerate() {
+        if name.is_empty() || header.iter().take(i).any(|earlier| earlier == name) {
+            continue;
+        }
+        let columns: Vec<String> = header
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| *other == name)
+            .map(|(j, _)| column_letter(j))
+            .collect();
+        if columns.len() > 1 {
+            return Err(SheetsError::DuplicateHeader {
+                name: name.to_string(),
+                columns,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Named columns of `header` that aren't in `fields`, other than the soft-delete marker and
+/// provenance columns. Types without named fields have no unknown columns.
+pub(crate) fn unknown_columns<'a>(
+    header: impl IntoIterator<Item = &'a str>,
+    fields: &[&'static str],
+) -> Vec<String> {
+    if fields.is_empty() {
+        return vec![];
+    }
+    header
+        .into_iter()
+        .filter(|name| {
+            !name.is_empty()
+                && ![
+                    DELETED_AT_COLUMN,
+                    UPDATED_AT_COLUMN,
+                    UPDATED_BY_COLUMN,
+                    SOURCE_RUN_ID_COLUMN,
+                ]
+                .contains(name)
+                && !fields.contains(name)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// `header` with each column renamed to the field it matches: its alias, or with
+/// `HeaderMatching::Normalized`, the field of `fields` with the same normalized name
+fn field_names(
+    header: &StringRecord,
+    fields: &[&'static str],
+    options: &ReadOptions,
+) -> StringRecord {
+    let normalized = options.header_matching == HeaderMatching::Normalized;
+    header
+        .iter()
+        .map(|column| {
+            let alias = options.header_aliases.get(column).or_else(|| {
+                let column = normalize(column);
+                options
+                    .header_aliases
+                    .iter()
+                    .find(|(alias, _)| normalized && normalize(alias) == column)
+                    .map(|(_, field)| field)
+            });
+            if let Some(field) = alias {
+                return field.as_str();
+            }
+            if normalized {
+                let key = normalize(column);
+                if let Some(field) = fields.iter().find(|field| normalize(field) == key) {
+                    return *field;
+                }
+            }
+            column
+        })
+        .collect()
+}
+
+/// `name` lowercased, without whitespace, underscores or other punctuation, so
+/// `Number Of Foos` matches `number_of_foos`
+pub(crate) fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Names of the fields `T` deserializes from, or none if it is not a struct
+pub(crate) fn struct_fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Deserializer that only records which fields a struct asks for, then fails
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names were wanted"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(
+    record: &StringRecord,
+    header: Option<&StringRecord>,
+    row_number: usize,
+) -> Result<T, SheetsError> {
+    record.deserialize(header).map_err(|e| {
+        let names: Vec<String> = header
+            .map(|header| header.iter().map(str::to_string).collect())
+            .unwrap_or_default();
+        serde_error(e, row_number, &names)
+    })
+}
+
+/// `error` from deserializing one-based sheet row `row` against `header`, naming the column
+/// it failed at if `csv` says
+pub(crate) fn serde_error(error: csv::Error, row: usize, header: &[String]) -> SheetsError {
+    match error.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => SheetsError::Serde {
+            row,
+            column: err.field().and_then(|i| header.get(i as usize)).cloned(),
+            message: err.kind().to_string(),
+        },
+        _ => SheetsError::Serde {
+            row,
+            column: None,
+            message: error.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Note {
+        id: u32,
+        text: String,
+    }
+
+    fn decoder(options: &ReadOptions) -> RowDecoder {
+        RowDecoder::new::<Note>(vec!["id".to_string(), "text".to_string()], options).unwrap()
+    }
+
+    #[test]
+    fn decodes_awkward_cells_verbatim() {
+        let mut decoder = decoder(&ReadOptions::default());
+        for text in [
+            "line one\nline two",
+            "windows\r\nline",
+            "say \"hi\"",
+            "a, b, c",
+            "col\tumn",
+            "naïve café ☕ 日本語",
+            "\"\n,\t\"",
+        ] {
+            let note: Note = decoder
+                .decode(&["7".to_string(), text.to_string()])
+                .unwrap();
+            assert_eq!(
+                note,
+                Note {
+                    id: 7,
+                    text: text.to_string()
+                }
+            );
+        }
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Entry {
+        id: Option<u32>,
+        text: String,
+    }
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    /// A table with a blank row between two records
+    fn table_with_blank_row() -> Vec<Vec<String>> {
+        vec![
+            strings(&["id", "text"]),
+            strings(&["1", "first"]),
+            strings(&["", " "]),
+            strings(&["2", "second"]),
+        ]
+    }
+
+    fn entry(id: Option<u32>, text: &str) -> Entry {
+        Entry {
+            id,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn skips_blank_rows_by_default() {
+        let entries: Vec<Entry> =
+            crate::decode_rows(table_with_blank_row(), &ReadOptions::default()).unwrap();
+        assert_eq!(
+            entries,
+            vec![entry(Some(1), "first"), entry(Some(2), "second")]
+        );
+    }
+
+    #[test]
+    fn decodes_blank_rows_when_not_skipping() {
+        let options = ReadOptions::default().skip_empty_rows(false);
+        let entries: Vec<Entry> = crate::decode_rows(table_with_blank_row(), &options).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                entry(Some(1), "first"),
+                entry(None, " "),
+                entry(Some(2), "second")
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_blank_row() {
+        let options = ReadOptions::default().stop_at_empty_row(true);
+        let entries: Vec<Entry> = crate::decode_rows(table_with_blank_row(), &options).unwrap();
+        assert_eq!(entries, vec![entry(Some(1), "first")]);
+    }
+
+    #[test]
+    fn pads_rows_the_api_cut_short() {
+        let mut decoder = decoder(&ReadOptions::default());
+        let note: Note = decoder.try_decode(&strings(&["7"]), 2).unwrap().unwrap();
+        assert_eq!(
+            note,
+            Note {
+                id: 7,
+                text: String::new()
+            }
+        );
+        assert!(decoder
+            .try_decode::<Note>(&strings(&["7", "text", "extra"]), 2)
+            .is_err());
+    }
+
+    #[test]
+    fn fails_on_the_first_row_that_does_not_deserialize() {
+        let rows = vec![
+            strings(&["id", "text"]),
+            strings(&["1", "first"]),
+            strings(&["two", "second"]),
+        ];
+        match crate::decode_rows::<Note>(rows, &ReadOptions::default()) {
+            Err(SheetsError::Serde { row, column, .. }) => {
+                assert_eq!(row, 3);
+                assert_eq!(column.as_deref(), Some("id"));
+            }
+            other => panic!("expected a Serde error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,576 @@
+use crate::{
+    batch_update, column_letter, quote_tab_name, trace, RangeRef, SheetsBackend, SheetsError,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use csv::StringRecord;
+use google_sheets4::{
+    api::{DeleteDuplicatesRequest, DimensionRange, GridRange, Request},
+    Sheets,
+};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// Column that soft-deleted rows are stamped in
+pub const DELETED_AT_COLUMN: &str = "deleted_at";
+
+/// How `delete_rows_where` removes matching rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteMode {
+    /// Remove the rows from the sheet, shifting later rows up
+    Hard,
+    /// Keep the rows but write the current time to their `deleted_at` column, adding the
+    /// column if the tab does not have one. `read_all` skips such rows.
+    Soft,
+}
+
+/// Delete every row of tab `tab_name` in document `document_id` that deserializes into a `T`
+/// matching `predicate`. Rows that fail to deserialize are kept. Returns the number of rows deleted.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, predicate), fields(deleted = tracing::field::Empty))
+)]
+pub async fn delete_rows_where<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    mode: DeleteMode,
+    predicate: impl Fn(&T) -> bool,
+) -> Result<usize, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    let header = match rows.first() {
+        Some(header) => header.iter().collect::<StringRecord>(),
+        None => return Ok(0),
+    };
+    let deleted_at = header.iter().position(|h| h == DELETED_AT_COLUMN);
+
+    // zero-based sheet row indices of matching rows
+    let matches: Vec<usize> = rows
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, row)| mode == DeleteMode::Hard || !is_soft_deleted(row, deleted_at))
+        .filter(|(_, row)| {
+            row.iter()
+                .collect::<StringRecord>()
+                .deserialize::<T>(Some(&header))
+                .map_or(false, |obj| predicate(&obj))
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    if matches.is_empty() {
+        return Ok(0);
+    }
+
+    match mode {
+        DeleteMode::Hard => {
+            // delete bottom-up so earlier deletions don't shift the remaining indices
+            for (start, end) in contiguous_runs(&matches).into_iter().rev() {
+                sheets
+                    .delete_rows(document_id, tab_name, start, end)
+                    .await?;
+            }
+        }
+        DeleteMode::Soft => {
+            let tab = quote_tab_name(tab_name);
+            let column = column_letter(deleted_at.unwrap_or(header.len()));
+            let timestamp = chrono::Utc::now().to_rfc3339();
+
+            let mut data = vec![];
+            if deleted_at.is_none() {
+                data.push(single_cell(
+                    format!("{}!{}1", tab, column),
+                    DELETED_AT_COLUMN,
+                ));
+            }
+            data.extend(
+                matches
+                    .iter()
+                    .map(|i| single_cell(format!("{}!{}{}", tab, column, i + 1), &timestamp)),
+            );
+
+            sheets.write_ranges(document_id, data).await?;
+        }
+    }
+
+    trace::record("deleted", matches.len() as u64);
+    Ok(matches.len())
+}
+
+/// What `prune_rows` does with the rows it removes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PruneMode {
+    /// Delete them
+    Delete,
+    /// Append them to this tab, created with the same header if needed, then delete them
+    Archive(String),
+}
+
+/// Remove the rows of tab `tab_name` in document `document_id` whose `timestamp_column` is
+/// more than `older_than` ago, so long-running logs stay under the cell limit. Returns the
+/// number of rows removed.
+///
+/// Timestamps may be RFC 3339, as written by `Audited` and soft deletes, or
+/// `YYYY-MM-DD[ HH:MM:SS]` taken as UTC. Rows whose timestamp is blank or doesn't parse are
+/// kept.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(deleted = tracing::field::Empty))
+)]
+pub async fn prune_rows<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    timestamp_column: &str,
+    older_than: Duration,
+    mode: &PruneMode,
+) -> Result<usize, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    let header = match rows.first() {
+        Some(header) => header,
+        None => return Ok(0),
+    };
+    let column = header
+        .iter()
+        .position(|h| h == timestamp_column)
+        .ok_or_else(|| SheetsError::ColumnNotFound {
+            column: timestamp_column.to_string(),
+        })?;
+    let cutoff =
+        Utc::now() - chrono::Duration::from_std(older_than).unwrap_or(chrono::Duration::zero());
+
+    // zero-based sheet row indices of expired rows
+    let expired: Vec<usize> = rows
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, row)| {
+            row.get(column)
+                .and_then(|cell| parse_timestamp(cell))
+                .map_or(false, |timestamp| timestamp < cutoff)
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if expired.is_empty() {
+        return Ok(0);
+    }
+
+    if let PruneMode::Archive(archive) = mode {
+        let header_range = format!("{}!1:1", quote_tab_name(archive));
+        let archive_header = match sheets.read_values(document_id, &header_range).await {
+            Ok(rows) => rows.into_iter().next(),
+            Err(SheetsError::TabNotFound { .. }) => {
+                sheets.add_tab(document_id, archive).await?;
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        let mut archived = vec![];
+        if archive_header.map_or(true, |h| h.is_empty()) {
+            archived.push(header.clone());
+        }
+        archived.extend(expired.iter().map(|&i| rows[i].clone()));
+        sheets.append_values(document_id, archive, archived).await?;
+    }
+
+    // delete bottom-up so earlier deletions don't shift the remaining indices
+    for (start, end) in contiguous_runs(&expired).into_iter().rev() {
+        sheets
+            .delete_rows(document_id, tab_name, start, end)
+            .await?;
+    }
+
+    trace::record("deleted", expired.len() as u64);
+    Ok(expired.len())
+}
+
+fn parse_timestamp(cell: &str) -> Option<DateTime<Utc>> {
+    let cell = cell.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(cell) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    let timestamp = match NaiveDateTime::parse_from_str(cell, "%Y-%m-%d %H:%M:%S") {
+        Ok(timestamp) => timestamp,
+        Err(_) => NaiveDate::parse_from_str(cell, "%Y-%m-%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?,
+    };
+    Some(DateTime::from_naive_utc_and_offset(timestamp, Utc))
+}
+
+/// Drop data rows with a non-empty `deleted_at` cell, keeping the header
+#[cfg(any(feature = "arrow", feature = "polars", feature = "sqlite"))]
+pub(crate) fn without_soft_deleted(mut rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let deleted_at = rows
+        .first()
+        .and_then(|header| header.iter().position(|h| h == DELETED_AT_COLUMN));
+
+    if deleted_at.is_some() {
+        let mut index = 0;
+        rows.retain(|row| {
+            index += 1;
+            index == 1 || !is_soft_deleted(row, deleted_at)
+        });
+    }
+
+    rows
+}
+
+fn is_soft_deleted(row: &[String], deleted_at: Option<usize>) -> bool {
+    deleted_at
+        .and_then(|i| row.get(i))
+        .map_or(false, |cell| !cell.is_empty())
+}
+
+/// Group sorted indices into half-open `(start, end)` runs of consecutive values
+fn contiguous_runs(indices: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for &i in indices {
+        match runs.last_mut() {
+            Some((_, end)) if *end == i => *end = i + 1,
+            _ => runs.push((i, i + 1)),
+        }
+    }
+    runs
+}
+
+fn single_cell(range: String, value: &str) -> (String, Vec<Vec<String>>) {
+    (range, vec![vec![value.to_string()]])
+}
+
+/// Remove the rows of `range`, e.g. `Orders` or `'Orders'!A1:F`, that repeat an earlier row's
+/// values in `key_columns`, named by the range's first row, or in every column if none are
+/// given. The first row of each set of duplicates is kept. Returns the number of rows removed.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(deleted = tracing::field::Empty))
+)]
+pub async fn dedupe(
+    sheets: &Sheets,
+    document_id: &str,
+    range: &str,
+    key_columns: &[&str],
+) -> Result<usize, SheetsError> {
+    let range = RangeRef::parse(range)?;
+    let header_row = range.start.row + 1;
+    let header_range = format!(
+        "{}!{}:{}",
+        quote_tab_name(&range.tab_name),
+        header_row,
+        header_row
+    );
+    let header = sheets
+        .read_values(document_id, &header_range)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let sheet_id = sheets.tab(document_id, &range.tab_name).await?.sheet_id;
+    let request = delete_duplicates(&range, &header, key_columns, sheet_id)?;
+    let response = batch_update(sheets, document_id, vec![request]).await?;
+    let removed = response
+        .replies
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .and_then(|reply| reply.delete_duplicates)
+        .and_then(|reply| reply.duplicates_removed_count)
+        .unwrap_or(0);
+    trace::record("deleted", removed as u64);
+    Ok(removed as usize)
+}
+
+/// The `DeleteDuplicates` request for the rows of `range` below its `header` row, comparing
+/// `key_columns`, or every column if none are given
+fn delete_duplicates(
+    range: &RangeRef,
+    header: &[String],
+    key_columns: &[&str],
+    sheet_id: i32,
+) -> Result<Request, SheetsError> {
+    let header_row = range.start.row + 1;
+    let within =
+        |i: usize| i >= range.start.column && range.end_column.map_or(true, |end| i <= end);
+    let columns = key_columns
+        .iter()
+        .map(|column| {
+            (0..header.len())
+                .find(|&i| header[i] == *column && within(i))
+                .ok_or_else(|| SheetsError::ColumnNotFound {
+                    column: column.to_string(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Request {
+        delete_duplicates: Some(DeleteDuplicatesRequest {
+            // the header row is not a duplicate candidate
+            range: Some(GridRange {
+                sheet_id: Some(sheet_id),
+                start_row_index: Some(header_row as i32),
+                end_row_index: range.end_row.map(|end| end as i32 + 1),
+                start_column_index: Some(range.start.column as i32),
+                end_column_index: range.end_column.map(|end| end as i32 + 1),
+            }),
+            comparison_columns: (!columns.is_empty()).then(|| {
+                columns
+                    .into_iter()
+                    .map(|column| DimensionRange {
+                        sheet_id: Some(sheet_id),
+                        dimension: Some("COLUMNS".to_string()),
+                        start_index: Some(column as i32),
+                        end_index: Some(column as i32 + 1),
+                    })
+                    .collect()
+            }),
+        }),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_all, MockSheets};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Order {
+        id: u32,
+        status: String,
+    }
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    fn orders(rows: &[[&str; 2]]) -> MockSheets {
+        let sheets = MockSheets::new();
+        let mut grid = vec![strings(&["id", "status"])];
+        grid.extend(rows.iter().map(|row| strings(row)));
+        sheets.set_tab("doc", "Orders", grid);
+        sheets
+    }
+
+    async fn delete(sheets: &MockSheets, mode: DeleteMode) -> usize {
+        delete_rows_where(sheets, "doc", "Orders", mode, |order: &Order| {
+            order.status == "cancelled"
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn hard_deletes_matching_rows_and_keeps_undecodable_ones() {
+        let sheets = orders(&[
+            ["1", "open"],
+            ["2", "cancelled"],
+            ["3", "cancelled"],
+            ["4", "open"],
+            ["5", "cancelled"],
+            ["x", "cancelled"],
+        ]);
+
+        let deleted = delete(&sheets, DeleteMode::Hard).await;
+        assert_eq!(deleted, 3);
+        assert_eq!(
+            sheets.get_tab("doc", "Orders").unwrap(),
+            vec![
+                strings(&["id", "status"]),
+                strings(&["1", "open"]),
+                strings(&["4", "open"]),
+                strings(&["x", "cancelled"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn soft_deletes_by_stamping_a_new_deleted_at_column() {
+        let sheets = orders(&[["1", "open"], ["2", "cancelled"], ["3", "open"]]);
+
+        let deleted = delete(&sheets, DeleteMode::Soft).await;
+        assert_eq!(deleted, 1);
+        let grid = sheets.get_tab("doc", "Orders").unwrap();
+        assert_eq!(grid[0], strings(&["id", "status", DELETED_AT_COLUMN]));
+        assert!(parse_timestamp(&grid[2][2]).is_some());
+        assert!(grid[1].get(2).map_or(true, String::is_empty));
+        assert!(grid[3].get(2).map_or(true, String::is_empty));
+
+        let remaining: Vec<Order> = read_all(&sheets, "doc", "Orders").await.unwrap();
+        assert_eq!(remaining.iter().map(|o| o.id).collect::<Vec<_>>(), [1, 3]);
+
+        // already tombstoned rows are not stamped again
+        let deleted = delete(&sheets, DeleteMode::Soft).await;
+        assert_eq!(deleted, 0);
+        assert_eq!(sheets.get_tab("doc", "Orders").unwrap(), grid);
+    }
+
+    #[tokio::test]
+    async fn deletes_nothing_from_an_empty_tab() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "Orders", vec![]);
+
+        let deleted = delete(&sheets, DeleteMode::Hard).await;
+        assert_eq!(deleted, 0);
+        assert_eq!(sheets.calls(), 1);
+    }
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn log() -> (MockSheets, String) {
+        let now = Utc::now().to_rfc3339();
+        let sheets = MockSheets::new();
+        sheets.set_tab(
+            "doc",
+            "Log",
+            vec![
+                strings(&["id", "at"]),
+                strings(&["1", "2000-01-01T00:00:00Z"]),
+                strings(&["2", "2000-01-02"]),
+                strings(&["3", &now]),
+                strings(&["4", "2000-01-02 10:00:00"]),
+                strings(&["5", ""]),
+                strings(&["6", "yesterday"]),
+            ],
+        );
+        (sheets, now)
+    }
+
+    #[tokio::test]
+    async fn prunes_expired_rows_and_keeps_unparseable_ones() {
+        let (sheets, now) = log();
+
+        let pruned = prune_rows(&sheets, "doc", "Log", "at", DAY, &PruneMode::Delete)
+            .await
+            .unwrap();
+        assert_eq!(pruned, 3);
+        assert_eq!(
+            sheets.get_tab("doc", "Log").unwrap(),
+            vec![
+                strings(&["id", "at"]),
+                strings(&["3", &now]),
+                strings(&["5", ""]),
+                strings(&["6", "yesterday"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn archives_into_a_new_tab_with_the_header() {
+        let (sheets, _) = log();
+        let mode = PruneMode::Archive("Archive".to_string());
+
+        let pruned = prune_rows(&sheets, "doc", "Log", "at", DAY, &mode)
+            .await
+            .unwrap();
+        assert_eq!(pruned, 3);
+        assert_eq!(sheets.get_tab("doc", "Log").unwrap().len(), 4);
+        assert_eq!(
+            sheets.get_tab("doc", "Archive").unwrap(),
+            vec![
+                strings(&["id", "at"]),
+                strings(&["1", "2000-01-01T00:00:00Z"]),
+                strings(&["2", "2000-01-02"]),
+                strings(&["4", "2000-01-02 10:00:00"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn archives_below_an_existing_header() {
+        let (sheets, _) = log();
+        sheets.set_tab(
+            "doc",
+            "Archive",
+            vec![strings(&["id", "at"]), strings(&["0", "1999-12-31"])],
+        );
+        let mode = PruneMode::Archive("Archive".to_string());
+
+        prune_rows(&sheets, "doc", "Log", "at", DAY, &mode)
+            .await
+            .unwrap();
+        let archive = sheets.get_tab("doc", "Archive").unwrap();
+        assert_eq!(archive.len(), 5);
+        assert_eq!(archive[1], strings(&["0", "1999-12-31"]));
+        assert_eq!(archive[2], strings(&["1", "2000-01-01T00:00:00Z"]));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_missing_timestamp_column() {
+        let (sheets, _) = log();
+
+        let err = prune_rows(&sheets, "doc", "Log", "created", DAY, &PruneMode::Delete)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SheetsError::ColumnNotFound { column } if column == "created"));
+        assert_eq!(sheets.get_tab("doc", "Log").unwrap().len(), 7);
+    }
+
+    #[test]
+    fn parses_rfc_3339_and_plain_timestamps() {
+        let midnight = parse_timestamp("2024-03-01").unwrap();
+        assert_eq!(midnight.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(
+            parse_timestamp(" 2024-03-01 12:30:00 ")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-03-01T12:30:00+00:00"
+        );
+        assert_eq!(
+            parse_timestamp("2024-03-01T12:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-03-01T10:30:00+00:00"
+        );
+        assert!(parse_timestamp("").is_none());
+        assert!(parse_timestamp("01/03/2024").is_none());
+    }
+
+    #[test]
+    fn compares_key_columns_within_the_range() {
+        let range = RangeRef::parse("'Orders'!B1:D").unwrap();
+        let header = strings(&["sku", "id", "sku", "qty", "note"]);
+
+        let request = delete_duplicates(&range, &header, &["sku"], 7)
+            .unwrap()
+            .delete_duplicates
+            .unwrap();
+        let grid = request.range.unwrap();
+        assert_eq!(grid.sheet_id, Some(7));
+        assert_eq!(grid.start_row_index, Some(1));
+        assert_eq!(grid.end_row_index, None);
+        assert_eq!(grid.start_column_index, Some(1));
+        assert_eq!(grid.end_column_index, Some(4));
+        let columns = request.comparison_columns.unwrap();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0].start_index, Some(2));
+        assert_eq!(columns[0].end_index, Some(3));
+
+        let err = delete_duplicates(&range, &header, &["note"], 7).unwrap_err();
+        assert!(matches!(err, SheetsError::ColumnNotFound { column } if column == "note"));
+    }
+
+    #[test]
+    fn compares_every_column_without_keys() {
+        let range = RangeRef::parse("'Orders'!A3:C10").unwrap();
+        let header = strings(&["id", "sku", "qty"]);
+
+        let request = delete_duplicates(&range, &header, &[], 7)
+            .unwrap()
+            .delete_duplicates
+            .unwrap();
+        let grid = request.range.unwrap();
+        assert_eq!(grid.start_row_index, Some(3));
+        assert_eq!(grid.end_row_index, Some(10));
+        assert!(request.comparison_columns.is_none());
+    }
+
+    #[test]
+    fn groups_indices_into_runs() {
+        assert!(contiguous_runs(&[]).is_empty());
+        assert_eq!(
+            contiguous_runs(&[1, 2, 3, 5, 7, 8]),
+            vec![(1, 4), (5, 6), (7, 9)]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{SheetsBackend, SheetsError};
+use google_sheets4::{
+    api::{Spreadsheet, SpreadsheetProperties},
+    Sheets,
+};
+use hyper::{body, header, Body, Method, Request, StatusCode};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Scope needed to export files through Drive
+const DRIVE_READONLY_SCOPE: &str = "https://www.googleapis.com/auth/drive.readonly";
+
+/// Scope needed to move and share files through Drive
+const DRIVE_SCOPE: &str = "https://www.googleapis.com/auth/drive";
+
+const DRIVE_FILES_URL: &str = "https://www.googleapis.com/drive/v3/files";
+
+const XLSX_MIME_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+const PDF_MIME_TYPE: &str = "application/pdf";
+
+/// Redirects followed before giving up on an export
+const MAX_REDIRECTS: usize = 5;
+
+/// Access granted to a user by `share`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveRole {
+    Reader,
+    Commenter,
+    Writer,
+}
+
+impl DriveRole {
+    fn as_str(self) -> &'static str {
+        match self {
+            DriveRole::Reader => "reader",
+            DriveRole::Commenter => "commenter",
+            DriveRole::Writer => "writer",
+        }
+    }
+}
+
+/// A user to share a document with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub email: String,
+    pub role: DriveRole,
+}
+
+impl Share {
+    pub fn new(email: impl Into<String>, role: DriveRole) -> Self {
+        Self {
+            email: email.into(),
+            role,
+        }
+    }
+}
+
+/// Where `create_spreadsheet` puts a new document and who gets access to it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CreateOptions {
+    /// Drive folder to move the document into, instead of the service account's root
+    pub folder_id: Option<String>,
+    pub share_with: Vec<Share>,
+}
+
+/// Create an empty spreadsheet called `title`, move it to `options.folder_id` and share it
+/// with `options.share_with`, returning its document ID
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, options)))]
+pub async fn create_spreadsheet(
+    sheets: &Sheets,
+    title: &str,
+    options: &CreateOptions,
+) -> Result<String, SheetsError> {
+    let req = Spreadsheet {
+        properties: Some(SpreadsheetProperties {
+            title: Some(title.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let (_body, spreadsheet) = sheets
+        .spreadsheets()
+        .create(req)
+        .param("fields", "spreadsheetId")
+        .doit()
+        .await
+        .map_err(SheetsError::from_api_without_document)?;
+    let document_id =
+        spreadsheet
+            .spreadsheet_id
+            .ok_or_else(|| SheetsError::UnexpectedResponse {
+                reason: "created spreadsheet has no ID".to_string(),
+            })?;
+
+    if let Some(folder_id) = &options.folder_id {
+        move_to_folder(sheets, &document_id, folder_id).await?;
+    }
+    for share_with in &options.share_with {
+        share(sheets, &document_id, share_with).await?;
+    }
+
+    Ok(document_id)
+}
+
+/// Move document `document_id` into Drive folder `folder_id`, out of its current folders
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn move_to_folder(
+    sheets: &Sheets,
+    document_id: &str,
+    folder_id: &str,
+) -> Result<(), SheetsError> {
+    let url = format!("{}/{}?fields=parents", DRIVE_FILES_URL, document_id);
+    let file = drive_call(sheets, document_id, Method::GET, &url, None).await?;
+    let parents: Vec<&str> = file
+        .get("parents")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+
+    let url = format!(
+        "{}/{}?addParents={}&removeParents={}&fields=id",
+        DRIVE_FILES_URL,
+        document_id,
+        folder_id,
+        parents.join(",")
+    );
+    drive_call(sheets, document_id, Method::PATCH, &url, Some(json!({}))).await?;
+    Ok(())
+}
+
+/// Give `share.email` `share.role` access to document `document_id`, without emailing them
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn share(sheets: &Sheets, document_id: &str, share: &Share) -> Result<(), SheetsError> {
+    let url = format!(
+        "{}/{}/permissions?sendNotificationEmail=false&fields=id",
+        DRIVE_FILES_URL, document_id
+    );
+    let permission = json!({
+        "type": "user",
+        "role": share.role.as_str(),
+        "emailAddress": share.email,
+    });
+    drive_call(sheets, document_id, Method::POST, &url, Some(permission)).await?;
+    Ok(())
+}
+
+/// Download document `document_id` as an Excel file to `path`, either the whole spreadsheet
+/// through the Drive `files.export` endpoint or, given `tab_name`, just that tab.
+///
+/// The service account needs read access to the document in Drive.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, path)))]
+pub async fn export_xlsx(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: Option<&str>,
+    path: impl AsRef<Path>,
+) -> Result<(), SheetsError> {
+    let bytes = download(sheets, document_id, tab_name, "xlsx", XLSX_MIME_TYPE).await?;
+    tokio::fs::write(path, bytes).await?;
+    Ok(())
+}
+
+/// Render document `document_id`, or just tab `tab_name`, as a PDF with its formatting
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn export_pdf(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: Option<&str>,
+) -> Result<Vec<u8>, SheetsError> {
+    download(sheets, document_id, tab_name, "pdf", PDF_MIME_TYPE).await
+}
+
+/// Like `export_pdf`, writing the PDF to `path`
+pub async fn export_pdf_to_file(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: Option<&str>,
+    path: impl AsRef<Path>,
+) -> Result<(), SheetsError> {
+    let bytes = export_pdf(sheets, document_id, tab_name).await?;
+    tokio::fs::write(path, bytes).await?;
+    Ok(())
+}
+
+/// Export document `document_id`, or just tab `tab_name`, in `format`
+async fn download(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: Option<&str>,
+    format: &str,
+    mime_type: &str,
+) -> Result<Vec<u8>, SheetsError> {
+    let mut url = match tab_name {
+        None => format!(
+            "{}/{}/export?mimeType={}",
+            DRIVE_FILES_URL, document_id, mime_type
+        ),
+        Some(tab_name) => format!(
+            "https://docs.google.com/spreadsheets/d/{}/export?format={}&gid={}",
+            document_id,
+            format,
+            sheets.tab(document_id, tab_name).await?.sheet_id
+        ),
+    };
+    let token = sheets.auth.token(&[DRIVE_READONLY_SCOPE]).await?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let request = Request::get(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token.as_str()))
+            .body(Body::empty())
+            .map_err(|e| SheetsError::UnexpectedResponse {
+                reason: e.to_string(),
+            })?;
+        let response = sheets
+            .client
+            .request(request)
+            .await
+            .map_err(|e| SheetsError::Network(google_sheets4::Error::HttpError(e)))?;
+
+        if response.status().is_redirection() {
+            url = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| SheetsError::UnexpectedResponse {
+                    reason: "export redirect has no location".to_string(),
+                })?
+                .to_string();
+            continue;
+        }
+        if response.status() != StatusCode::OK {
+            return Err(SheetsError::from_api(
+                google_sheets4::Error::Failure(response),
+                document_id,
+            ));
+        }
+
+        let bytes = body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| SheetsError::Network(google_sheets4::Error::HttpError(e)))?;
+        return Ok(bytes.to_vec());
+    }
+
+    Err(SheetsError::UnexpectedResponse {
+        reason: "too many export redirects".to_string(),
+    })
+}
+
+/// Send a JSON request about document `document_id` to the Drive API
+async fn drive_call(
+    sheets: &Sheets,
+    document_id: &str,
+    method: Method,
+    url: &str,
+    payload: Option<Value>,
+) -> Result<Value, SheetsError> {
+    let token = sheets.auth.token(&[DRIVE_SCOPE]).await?;
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token.as_str()))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(match payload {
+            Some(payload) => Body::from(payload.to_string()),
+            None => Body::empty(),
+        })
+        .map_err(|e| SheetsError::UnexpectedResponse {
+            reason: e.to_string(),
+        })?;
+    let response = sheets
+        .client
+        .request(request)
+        .await
+        .map_err(|e| SheetsError::Network(google_sheets4::Error::HttpError(e)))?;
+    if !response.status().is_success() {
+        return Err(SheetsError::from_api(
+            google_sheets4::Error::Failure(response),
+            document_id,
+        ));
+    }
+
+    let bytes = body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| SheetsError::Network(google_sheets4::Error::HttpError(e)))?;
+    if bytes.is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_slice(&bytes).map_err(|e| SheetsError::UnexpectedResponse {
+        reason: e.to_string(),
+    })
+}
@@ -0,0 +1,128 @@
+use crate::{batch_update, quote_tab_name, SheetsBackend, SheetsError};
+use google_sheets4::{
+    api::{
+        BooleanCondition, ConditionValue, DataValidationRule, GridRange, Request,
+        SetDataValidationRequest,
+    },
+    Sheets,
+};
+
+/// Where a dropdown's choices come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Choices {
+    List(Vec<String>),
+    Range(String),
+}
+
+/// A dropdown on the data cells of a column, named by header, e.g.
+///
+/// `Dropdown::from_range("'Categories'!A2:A").add(&sheets, DOCUMENT_ID, "Products", "category")`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dropdown {
+    choices: Choices,
+    strict: bool,
+    input_message: Option<String>,
+}
+
+impl Dropdown {
+    /// Choose from `values`, e.g. the variants of an enum as they serialize
+    pub fn from_values(values: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self::new(Choices::List(values.into_iter().map(Into::into).collect()))
+    }
+
+    /// Choose from the values in `range`, such as another tab's column `'Categories'!A2:A` or
+    /// the name of a named range, so the choices follow a reference list kept elsewhere in
+    /// the document
+    pub fn from_range(range: impl Into<String>) -> Self {
+        Self::new(Choices::Range(range.into()))
+    }
+
+    fn new(choices: Choices) -> Self {
+        Self {
+            choices,
+            strict: true,
+            input_message: None,
+        }
+    }
+
+    /// Reject values that aren't among the choices, rather than just flagging them. On by
+    /// default.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Help text shown when a cell of the column is selected
+    pub fn input_message(mut self, message: impl Into<String>) -> Self {
+        self.input_message = Some(message.into());
+        self
+    }
+
+    /// Set the dropdown on every cell of column `column` of tab `tab_name` below its header
+    /// row, replacing any validation they had. Fails with `SheetsError::ColumnNotFound` if
+    /// the header has no such column.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, sheets)))]
+    pub async fn add(
+        &self,
+        sheets: &Sheets,
+        document_id: &str,
+        tab_name: &str,
+        column: &str,
+    ) -> Result<(), SheetsError> {
+        let header_range = format!("{}!1:1", quote_tab_name(tab_name));
+        let header = sheets
+            .read_values(document_id, &header_range)
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let index =
+            header
+                .iter()
+                .position(|h| h == column)
+                .ok_or_else(|| SheetsError::ColumnNotFound {
+                    column: column.to_string(),
+                })? as i32;
+        let sheet_id = sheets.tab(document_id, tab_name).await?.sheet_id;
+
+        let (condition_type, values) = match &self.choices {
+            Choices::List(values) => ("ONE_OF_LIST", values.clone()),
+            Choices::Range(range) => (
+                "ONE_OF_RANGE",
+                vec![format!("={}", range.trim_start_matches('='))],
+            ),
+        };
+        let rule = DataValidationRule {
+            condition: Some(BooleanCondition {
+                type_: Some(condition_type.to_string()),
+                values: Some(
+                    values
+                        .into_iter()
+                        .map(|value| ConditionValue {
+                            user_entered_value: Some(value),
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+            }),
+            input_message: self.input_message.clone(),
+            show_custom_ui: Some(true),
+            strict: Some(self.strict),
+        };
+        let request = Request {
+            set_data_validation: Some(SetDataValidationRequest {
+                range: Some(GridRange {
+                    sheet_id: Some(sheet_id),
+                    start_row_index: Some(1),
+                    end_row_index: None,
+                    start_column_index: Some(index),
+                    end_column_index: Some(index + 1),
+                }),
+                rule: Some(rule),
+            }),
+            ..Default::default()
+        };
+        batch_update(sheets, document_id, vec![request]).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{RangeRef, SheetsBackend, SheetsError, TabInfo, WriteReceipt};
+use async_trait::async_trait;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// A mutation that `DryRun` skipped instead of sending
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedMutation {
+    Write {
+        document_id: String,
+        range: String,
+        rows: Vec<Vec<String>>,
+    },
+    Append {
+        document_id: String,
+        range: String,
+        rows: Vec<Vec<String>>,
+    },
+    Clear {
+        document_id: String,
+        range: String,
+    },
+    DeleteRows {
+        document_id: String,
+        tab_name: String,
+        start: usize,
+        end: usize,
+    },
+    AddTab {
+        document_id: String,
+        tab_name: String,
+    },
+    DeleteTab {
+        document_id: String,
+        tab_name: String,
+    },
+    DuplicateTab {
+        document_id: String,
+        tab_name: String,
+        new_name: String,
+    },
+}
+
+impl fmt::Display for PlannedMutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedMutation::Write {
+                document_id,
+                range,
+                rows,
+            } => write!(
+                f,
+                "write {} rows to {} in {}",
+                rows.len(),
+                range,
+                document_id
+            ),
+            PlannedMutation::Append {
+                document_id,
+                range,
+                rows,
+            } => write!(
+                f,
+                "append {} rows to {} in {}",
+                rows.len(),
+                range,
+                document_id
+            ),
+            PlannedMutation::Clear { document_id, range } => {
+                write!(f, "clear {} in {}", range, document_id)
+            }
+            PlannedMutation::DeleteRows {
+                document_id,
+                tab_name,
+                start,
+                end,
+            } => write!(
+                f,
+                "delete rows {}-{} of {} in {}",
+                start + 1,
+                end,
+                tab_name,
+                document_id
+            ),
+            PlannedMutation::AddTab {
+                document_id,
+                tab_name,
+            } => write!(f, "add tab {} to {}", tab_name, document_id),
+            PlannedMutation::DeleteTab {
+                document_id,
+                tab_name,
+            } => write!(f, "delete tab {} from {}", tab_name, document_id),
+            PlannedMutation::DuplicateTab {
+                document_id,
+                tab_name,
+                new_name,
+            } => write!(
+                f,
+                "duplicate tab {} as {} in {}",
+                tab_name, new_name, document_id
+            ),
+        }
+    }
+}
+
+/// Backend wrapper that, while dry-run is enabled, passes reads through but short-circuits
+/// every write, clear and structural change, recording it instead, and logging it with the
+/// `tracing` feature.
+///
+/// Tabs "added" during a dry run don't exist, so later reads of them fail.
+pub struct DryRun<B> {
+    inner: B,
+    enabled: AtomicBool,
+    planned: Mutex<Vec<PlannedMutation>>,
+}
+
+impl<B: SheetsBackend> DryRun<B> {
+    /// Wrap `inner` with dry-run enabled
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            enabled: AtomicBool::new(true),
+            planned: Mutex::new(vec![]),
+        }
+    }
+
+    /// Enable or disable dry-run. When disabled, mutations are sent to the wrapped backend.
+    pub fn dry_run(self, enabled: bool) -> Self {
+        self.set_dry_run(enabled);
+        self
+    }
+
+    /// Enable or disable dry-run on a shared wrapper
+    pub fn set_dry_run(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Whether mutations are currently being skipped
+    pub fn is_dry_run(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Mutations skipped so far, in order
+    pub fn planned(&self) -> Vec<PlannedMutation> {
+        self.planned.lock().unwrap().clone()
+    }
+
+    /// Return and forget the mutations skipped so far
+    pub fn take_planned(&self) -> Vec<PlannedMutation> {
+        std::mem::take(&mut *self.planned.lock().unwrap())
+    }
+
+    /// The wrapped backend
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Record `mutation` if dry-run is enabled, returning whether it was skipped
+    fn skip(&self, mutation: impl FnOnce() -> PlannedMutation) -> bool {
+        if !self.is_dry_run() {
+            return false;
+        }
+        let mutation = mutation();
+        #[cfg(feature = "tracing")]
+        tracing::info!(%mutation, "dry run");
+        self.planned.lock().unwrap().push(mutation);
+        true
+    }
+}
+
+#[async_trait]
+impl<B: SheetsBackend> SheetsBackend for DryRun<B> {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.inner.read_values(document_id, range).await
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.inner.read_formulas(document_id, range).await
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        self.inner.read_ranges(document_id, ranges).await
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        if self.is_dry_run() {
+            self.skip(|| PlannedMutation::Write {
+                document_id: document_id.to_string(),
+                range: range.to_string(),
+                rows,
+            });
+            return Ok(());
+        }
+        self.inner.write_values(document_id, range, rows).await
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        if self.is_dry_run() {
+            self.skip(|| PlannedMutation::Write {
+                document_id: document_id.to_string(),
+                range: range.to_string(),
+                rows,
+            });
+            return Ok(());
+        }
+        self.inner.write_raw_values(document_id, range, rows).await
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        if self.is_dry_run() {
+            for (range, rows) in data {
+                self.skip(|| PlannedMutation::Write {
+                    document_id: document_id.to_string(),
+                    range,
+                    rows,
+                });
+            }
+            return Ok(());
+        }
+        self.inner.write_ranges(document_id, data).await
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        if self.is_dry_run() {
+            self.skip(|| PlannedMutation::Append {
+                document_id: document_id.to_string(),
+                range: range.to_string(),
+                rows,
+            });
+            // nothing was written
+            let tab_name = RangeRef::parse(range)?.tab_name;
+            return Ok(WriteReceipt::new(&tab_name, 0, 0, &[]));
+        }
+        self.inner.append_values(document_id, range, rows).await
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        if self.skip(|| PlannedMutation::Clear {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        }) {
+            return Ok(());
+        }
+        self.inner.clear_range(document_id, range).await
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        if self.skip(|| PlannedMutation::DeleteRows {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            start,
+            end,
+        }) {
+            return Ok(());
+        }
+        self.inner
+            .delete_rows(document_id, tab_name, start, end)
+            .await
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        self.inner.list_tabs(document_id).await
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        if self.skip(|| PlannedMutation::AddTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+        }) {
+            return Ok(TabInfo {
+                sheet_id: 0,
+                title: tab_name.to_string(),
+                row_count: 0,
+                column_count: 0,
+            });
+        }
+        self.inner.add_tab(document_id, tab_name).await
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        if self.skip(|| PlannedMutation::DeleteTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+        }) {
+            return Ok(());
+        }
+        self.inner.delete_tab(document_id, tab_name).await
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        if self.skip(|| PlannedMutation::DuplicateTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            new_name: new_name.to_string(),
+        }) {
+            return Ok(TabInfo {
+                sheet_id: 0,
+                title: new_name.to_string(),
+                row_count: 0,
+                column_count: 0,
+            });
+        }
+        self.inner
+            .duplicate_tab(document_id, tab_name, new_name)
+            .await
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{clear_tab, SheetsBackend, SheetsError};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Raw rows for records without a fixed set of fields, such as `HashMap`s, with a header of
+/// every key seen.
+///
+/// Columns in `column_order` come first, in that order, followed by any other keys in the
+/// order they are first seen. That order is insertion order for `IndexMap` with the
+/// `indexmap` feature enabled, and sorted order otherwise. Missing keys and nulls are empty
+/// cells; nested values are written as JSON.
+pub fn dynamic_rows<M: Serialize>(
+    records: &[M],
+    column_order: &[&str],
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let records = records
+        .iter()
+        .map(|record| match serde_json::to_value(record) {
+            Ok(Value::Object(map)) => Ok(map),
+            Ok(other) => Err(SheetsError::InvalidRecord {
+                reason: format!("expected a map or struct, got {}", other),
+            }),
+            Err(e) => Err(SheetsError::InvalidRecord {
+                reason: e.to_string(),
+            }),
+        })
+        .collect::<Result<Vec<Map<String, Value>>, SheetsError>>()?;
+
+    let mut header: Vec<String> = column_order.iter().map(|c| c.to_string()).collect();
+    for record in &records {
+        for key in record.keys() {
+            if !header.contains(key) {
+                header.push(key.clone());
+            }
+        }
+    }
+
+    let mut rows = Vec::with_capacity(records.len() + 1);
+    for mut record in records {
+        rows.push(
+            header
+                .iter()
+                .map(|column| record.remove(column).map(cell_text).unwrap_or_default())
+                .collect(),
+        );
+    }
+    rows.insert(0, header);
+    Ok(rows)
+}
+
+/// Write `records` to tab `tab_name` in document `document_id` with the columns laid out by
+/// `dynamic_rows`. The sheet will be cleared before writing.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, records), fields(rows = records.len()))
+)]
+pub async fn write_dynamic<M: Serialize, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    records: &[M],
+    column_order: &[&str],
+) -> Result<(), SheetsError> {
+    let rows = dynamic_rows(records, column_order)?;
+    clear_tab(sheets, document_id, tab_name).await?;
+    sheets.write_values(document_id, tab_name, rows).await
+}
+
+fn cell_text(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(true) => "TRUE".to_string(),
+        Value::Bool(false) => "FALSE".to_string(),
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{de::serde_error, quote_tab_name, serialize_rows, SheetsBackend, SheetsError};
+use csv::StringRecord;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// An event read back from an `EventLog`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<T> {
+    pub seq: u64,
+    pub timestamp: String,
+    pub event: T,
+}
+
+/// Append-only journal of `T` events in a single tab.
+///
+/// Each row is stamped with a `seq` and a `timestamp` in the first two columns. The timestamp
+/// is the server's time when it took the append, or the producer's clock for backends without
+/// a server, such as `MockSheets`.
+/// The sequence number is derived from the row the API appended to, so concurrent producers
+/// never hand out the same number. Rows are appended first and stamped by a second request,
+/// so `read_after` stops at the first row not yet stamped and returns it and the rows after it
+/// on a later call; a cursor never passes over a lower `seq` stamped late. A producer that
+/// fails between the two requests leaves its row unstamped, holding back readers until the
+/// row is stamped, with `seq` one less than its row number, or deleted.
+pub struct EventLog<T> {
+    document_id: String,
+    tab_name: String,
+    header_checked: AtomicBool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> EventLog<T> {
+    /// Journal events to tab `tab_name` in document `document_id`
+    pub fn new(document_id: impl Into<String>, tab_name: impl Into<String>) -> Self {
+        Self {
+            document_id: document_id.into(),
+            tab_name: tab_name.into(),
+            header_checked: AtomicBool::new(false),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Append `event`, returning its sequence number
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(document_id = %self.document_id, tab_name = %self.tab_name))
+    )]
+    pub async fn append<B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &B,
+        event: &T,
+    ) -> Result<u64, SheetsError> {
+        let mut rows = serialize_rows(std::slice::from_ref(event), true)?.into_iter();
+        let header = rows.next().unwrap_or_default();
+        let values = rows.next().unwrap_or_default();
+
+        if !self.header_checked.swap(true, Ordering::SeqCst) {
+            let header_range = format!("{}!1:1", quote_tab_name(&self.tab_name));
+            if sheets
+                .read_values(&self.document_id, &header_range)
+                .await?
+                .is_empty()
+            {
+                // written in place rather than appended, so two producers starting on an empty
+                // tab both write the same row 1 instead of appending a header each
+                let header = ["seq".to_string(), "timestamp".to_string()]
+                    .into_iter()
+                    .chain(header)
+                    .collect();
+                sheets
+                    .write_raw_values(&self.document_id, &header_range, vec![header])
+                    .await?;
+            }
+        }
+        let row = [String::new(), String::new()]
+            .into_iter()
+            .chain(values)
+            .collect();
+
+        let receipt = sheets
+            .append_values(&self.document_id, &self.tab_name, vec![row])
+            .await?;
+        if receipt.updated_rows == 0 {
+            return Err(SheetsError::UnexpectedResponse {
+                reason: "append response has no updated range".to_string(),
+            });
+        }
+        let row = receipt.first_row_index;
+        let seq = row as u64 - 1;
+        let timestamp = receipt
+            .server_time
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+
+        let range = format!("{}!A{}:B{}", quote_tab_name(&self.tab_name), row, row);
+        sheets
+            .write_raw_values(
+                &self.document_id,
+                &range,
+                vec![vec![seq.to_string(), timestamp]],
+            )
+            .await?;
+
+        Ok(seq)
+    }
+
+    /// Events with a sequence number greater than `seq`, in sequence order, up to the first
+    /// row not yet stamped
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, sheets), fields(document_id = %self.document_id, tab_name = %self.tab_name))
+    )]
+    pub async fn read_after<B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &B,
+        seq: u64,
+    ) -> Result<Vec<Event<T>>, SheetsError> {
+        let mut rows = sheets
+            .read_values(&self.document_id, &self.tab_name)
+            .await?
+            .into_iter();
+        let header: Vec<String> = match rows.next() {
+            Some(header) => header.into_iter().skip(2).collect(),
+            None => return Ok(vec![]),
+        };
+        let header_record = StringRecord::from(header.clone());
+
+        let mut events = vec![];
+        // sheet rows are one-based and the header is row 1
+        for (row_number, row) in (2..).zip(rows) {
+            let mut cells = row.into_iter();
+            let row_seq = match cells.next().and_then(|cell| cell.parse::<u64>().ok()) {
+                Some(row_seq) if row_seq > seq => row_seq,
+                Some(_) => continue,
+                // not stamped yet; later rows wait for it, so their order is kept
+                None => break,
+            };
+            let timestamp = cells.next().unwrap_or_default();
+            let record = StringRecord::from(cells.collect::<Vec<_>>());
+
+            let event = record
+                .deserialize(Some(&header_record))
+                .map_err(|e| serde_error(e, row_number, &header))?;
+            events.push(Event {
+                seq: row_seq,
+                timestamp,
+                event,
+            });
+        }
+
+        events.sort_by_key(|event| event.seq);
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockSheets;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Signup {
+        email: String,
+    }
+
+    fn signup(email: &str) -> Signup {
+        Signup {
+            email: email.to_string(),
+        }
+    }
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn numbers_events_by_row_and_reads_after_a_cursor() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "Events", vec![]);
+        let log = EventLog::new("doc", "Events");
+        assert_eq!(
+            log.append(&sheets, &signup("a@example.com")).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            log.append(&sheets, &signup("b@example.com")).await.unwrap(),
+            2
+        );
+
+        let rows = sheets.get_tab("doc", "Events").unwrap();
+        assert_eq!(rows[0], strings(&["seq", "timestamp", "email"]));
+        assert_eq!(rows.len(), 3);
+
+        let events = log.read_after(&sheets, 1).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seq, 2);
+        assert_eq!(events[0].event, signup("b@example.com"));
+        assert!(!events[0].timestamp.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_unstamped_row() {
+        let sheets = MockSheets::new();
+        sheets.set_tab(
+            "doc",
+            "Events",
+            vec![
+                strings(&["seq", "timestamp", "email"]),
+                strings(&["1", "2024-01-01T00:00:00+00:00", "a@example.com"]),
+                strings(&["", "", "b@example.com"]),
+                strings(&["3", "2024-01-01T00:00:02+00:00", "c@example.com"]),
+            ],
+        );
+        let log: EventLog<Signup> = EventLog::new("doc", "Events");
+
+        let events = log.read_after(&sheets, 0).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, signup("a@example.com"));
+    }
+}
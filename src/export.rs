@@ -0,0 +1,228 @@
+use crate::{
+    clear_tab, quote_tab_name, read_all, serialize_rows, trace, SheetsBackend, SheetsError,
+};
+use csv::{ReaderBuilder, WriterBuilder};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+/// What `import_csv` and `import_ndjson` do with rows already in the tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Clear the tab and write the header and data rows
+    Replace,
+    /// Append the file's data rows. Their header must match the tab's, if it has one.
+    Append,
+}
+
+/// How `import_csv` and `import_ndjson` read a file and write it to a tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    /// Field delimiter of a CSV file
+    pub delimiter: u8,
+    /// Rows sent per request
+    pub chunk_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            mode: ImportMode::Replace,
+            delimiter: b',',
+            chunk_size: 1000,
+        }
+    }
+}
+
+/// Write every row of tab `tab_name` in document `document_id`, header included, to a CSV
+/// file at `path`. Short rows are padded to the tab's widest row. Returns the number of data
+/// rows written.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, path), fields(rows = tracing::field::Empty))
+)]
+pub async fn export_csv<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    path: impl AsRef<Path>,
+) -> Result<usize, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    trace::record("rows", rows.len() as u64);
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut wtr = WriterBuilder::new().from_path(path)?;
+    for row in &rows {
+        let padding = std::iter::repeat("").take(width - row.len());
+        wtr.write_record(row.iter().map(String::as_str).chain(padding))?;
+    }
+    wtr.flush()?;
+
+    Ok(rows.len().saturating_sub(1))
+}
+
+/// Load the CSV file at `path`, whose first row is the header, into tab `tab_name` in
+/// document `document_id`, `options.chunk_size` rows per request. Returns the number of data
+/// rows imported.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, path), fields(rows = tracing::field::Empty))
+)]
+pub async fn import_csv<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    path: impl AsRef<Path>,
+    options: &ImportOptions,
+) -> Result<usize, SheetsError> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(options.delimiter)
+        .from_path(path)?;
+    let mut records = rdr
+        .records()
+        .map(|record| Ok::<Vec<String>, SheetsError>(record?.iter().map(str::to_string).collect()));
+
+    let header = match records.next() {
+        Some(header) => header?,
+        // an empty file still replaces the tab's rows, with none
+        None => {
+            if options.mode == ImportMode::Replace {
+                clear_tab(sheets, document_id, tab_name).await?;
+            }
+            return Ok(0);
+        }
+    };
+    prepare_import(sheets, document_id, tab_name, options.mode, header).await?;
+
+    let mut imported = 0;
+    loop {
+        let chunk = records
+            .by_ref()
+            .take(options.chunk_size.max(1))
+            .collect::<Result<Vec<_>, _>>()?;
+        if chunk.is_empty() {
+            break;
+        }
+        imported += chunk.len();
+        sheets.append_values(document_id, tab_name, chunk).await?;
+    }
+    trace::record("rows", imported as u64);
+
+    Ok(imported)
+}
+
+/// Write every row of tab `tab_name` in document `document_id` that deserializes into a `T`
+/// to `writer` as one JSON object per line. Returns the number of objects written.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, writer), fields(rows = tracing::field::Empty))
+)]
+pub async fn export_ndjson<T: DeserializeOwned + Serialize, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    mut writer: impl Write,
+) -> Result<usize, SheetsError> {
+    let objects: Vec<T> = read_all(sheets, document_id, tab_name).await?;
+    trace::record("rows", objects.len() as u64);
+
+    for obj in &objects {
+        serde_json::to_writer(&mut writer, obj).map_err(io::Error::from)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(objects.len())
+}
+
+/// Load newline-delimited JSON objects from `reader` into tab `tab_name` in document
+/// `document_id`, `options.chunk_size` rows per request, with a header from the fields of `T`.
+/// Blank lines are skipped; `options.delimiter` is unused. Returns the number of objects imported.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, reader), fields(rows = tracing::field::Empty))
+)]
+pub async fn import_ndjson<T: DeserializeOwned + Serialize, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    reader: impl BufRead,
+    options: &ImportOptions,
+) -> Result<usize, SheetsError> {
+    let mut objects = reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(i, line)| {
+            serde_json::from_str::<T>(&line?).map_err(|source| SheetsError::Json {
+                line: i + 1,
+                source,
+            })
+        });
+
+    let mut imported = 0;
+    loop {
+        let chunk = objects
+            .by_ref()
+            .take(options.chunk_size.max(1))
+            .collect::<Result<Vec<T>, _>>()?;
+        if chunk.is_empty() {
+            break;
+        }
+
+        let mut rows = serialize_rows(&chunk, imported == 0)?;
+        if imported == 0 {
+            let header = rows.remove(0);
+            prepare_import(sheets, document_id, tab_name, options.mode, header).await?;
+        }
+        imported += chunk.len();
+        sheets.append_values(document_id, tab_name, rows).await?;
+    }
+    // with no objects there is no header either, but the tab's rows are still replaced
+    if imported == 0 && options.mode == ImportMode::Replace {
+        clear_tab(sheets, document_id, tab_name).await?;
+    }
+    trace::record("rows", imported as u64);
+
+    Ok(imported)
+}
+
+/// Get tab `tab_name` ready for rows under `header`: in `Replace` mode clear it and write the
+/// header, in `Append` mode check the header against the tab's, writing it if the tab is empty
+async fn prepare_import<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    mode: ImportMode,
+    header: Vec<String>,
+) -> Result<(), SheetsError> {
+    let existing = match mode {
+        ImportMode::Replace => {
+            clear_tab(sheets, document_id, tab_name).await?;
+            None
+        }
+        ImportMode::Append => {
+            let header_range = format!("{}!1:1", quote_tab_name(tab_name));
+            let rows = sheets.read_values(document_id, &header_range).await?;
+            rows.into_iter().next()
+        }
+    };
+
+    match existing {
+        Some(existing) if existing != header => Err(SheetsError::HeaderMismatch {
+            expected: existing,
+            found: header,
+        }),
+        Some(_) => Ok(()),
+        None => {
+            sheets
+                .write_values(document_id, tab_name, vec![header])
+                .await
+        }
+    }
+}
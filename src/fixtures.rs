@@ -0,0 +1,735 @@
+use crate::{ApiErrorDetails, SheetsBackend, SheetsError, TabInfo, WriteReceipt};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// A backend call captured by `Recorder`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum RecordedCall {
+    ReadValues {
+        document_id: String,
+        range: String,
+    },
+    ReadFormulas {
+        document_id: String,
+        range: String,
+    },
+    ReadRanges {
+        document_id: String,
+        ranges: Vec<String>,
+    },
+    WriteValues {
+        document_id: String,
+        range: String,
+        rows: Vec<Vec<String>>,
+    },
+    WriteRawValues {
+        document_id: String,
+        range: String,
+        rows: Vec<Vec<String>>,
+    },
+    WriteRanges {
+        document_id: String,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    },
+    AppendValues {
+        document_id: String,
+        range: String,
+        rows: Vec<Vec<String>>,
+    },
+    ClearRange {
+        document_id: String,
+        range: String,
+    },
+    DeleteRows {
+        document_id: String,
+        tab_name: String,
+        start: usize,
+        end: usize,
+    },
+    ListTabs {
+        document_id: String,
+    },
+    AddTab {
+        document_id: String,
+        tab_name: String,
+    },
+    DeleteTab {
+        document_id: String,
+        tab_name: String,
+    },
+    DuplicateTab {
+        document_id: String,
+        tab_name: String,
+        new_name: String,
+    },
+}
+
+/// What a recorded call returned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedResult {
+    Values(Vec<Vec<String>>),
+    /// Values of several ranges, in the order they were asked for
+    Ranges(Vec<Vec<Vec<String>>>),
+    Tabs(Vec<TabInfo>),
+    Tab(TabInfo),
+    Appended(WriteReceipt),
+    Done,
+    Error(RecordedError),
+}
+
+/// A recorded error, kept as the variant it was so replay can return the same one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedError {
+    TabNotFound {
+        requested: String,
+        available: Vec<String>,
+        suggestion: Option<String>,
+    },
+    ColumnNotFound {
+        column: String,
+    },
+    RowIdNotFound {
+        id: String,
+    },
+    MalformedRange {
+        range: String,
+    },
+    PermissionDenied {
+        details: ApiErrorDetails,
+    },
+    NotFound {
+        document_id: String,
+        details: ApiErrorDetails,
+    },
+    ServerError {
+        details: ApiErrorDetails,
+    },
+    QuotaExceeded {
+        retry_after: Option<Duration>,
+        details: ApiErrorDetails,
+    },
+    InvalidRange {
+        details: ApiErrorDetails,
+    },
+    Network {
+        message: String,
+    },
+    /// Any other error, by its message only
+    Other {
+        message: String,
+    },
+}
+
+impl From<&SheetsError> for RecordedError {
+    fn from(error: &SheetsError) -> Self {
+        match error {
+            SheetsError::TabNotFound {
+                requested,
+                available,
+                suggestion,
+            } => RecordedError::TabNotFound {
+                requested: requested.clone(),
+                available: available.clone(),
+                suggestion: suggestion.clone(),
+            },
+            SheetsError::ColumnNotFound { column } => RecordedError::ColumnNotFound {
+                column: column.clone(),
+            },
+            SheetsError::RowIdNotFound { id } => RecordedError::RowIdNotFound { id: id.clone() },
+            SheetsError::MalformedRange { range } => RecordedError::MalformedRange {
+                range: range.clone(),
+            },
+            SheetsError::PermissionDenied { details } => RecordedError::PermissionDenied {
+                details: details.clone(),
+            },
+            SheetsError::NotFound {
+                document_id,
+                details,
+            } => RecordedError::NotFound {
+                document_id: document_id.clone(),
+                details: details.clone(),
+            },
+            SheetsError::ServerError { details } => RecordedError::ServerError {
+                details: details.clone(),
+            },
+            SheetsError::QuotaExceeded {
+                retry_after,
+                details,
+            } => RecordedError::QuotaExceeded {
+                retry_after: *retry_after,
+                details: details.clone(),
+            },
+            SheetsError::InvalidRange { details } => RecordedError::InvalidRange {
+                details: details.clone(),
+            },
+            SheetsError::Network(e) => RecordedError::Network {
+                message: e.to_string(),
+            },
+            e => RecordedError::Other {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<RecordedError> for SheetsError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::TabNotFound {
+                requested,
+                available,
+                suggestion,
+            } => SheetsError::TabNotFound {
+                requested,
+                available,
+                suggestion,
+            },
+            RecordedError::ColumnNotFound { column } => SheetsError::ColumnNotFound { column },
+            RecordedError::RowIdNotFound { id } => SheetsError::RowIdNotFound { id },
+            RecordedError::MalformedRange { range } => SheetsError::MalformedRange { range },
+            RecordedError::PermissionDenied { details } => {
+                SheetsError::PermissionDenied { details }
+            }
+            RecordedError::NotFound {
+                document_id,
+                details,
+            } => SheetsError::NotFound {
+                document_id,
+                details,
+            },
+            RecordedError::ServerError { details } => SheetsError::ServerError { details },
+            RecordedError::QuotaExceeded {
+                retry_after,
+                details,
+            } => SheetsError::QuotaExceeded {
+                retry_after,
+                details,
+            },
+            RecordedError::InvalidRange { details } => SheetsError::InvalidRange { details },
+            RecordedError::Network { message } => SheetsError::Network(google_sheets4::Error::Io(
+                std::io::Error::new(std::io::ErrorKind::Other, message),
+            )),
+            RecordedError::Other { message } => SheetsError::Fixture {
+                reason: format!("recorded error: {}", message),
+            },
+        }
+    }
+}
+
+/// A call and its result, as stored in a fixture file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub call: RecordedCall,
+    pub result: RecordedResult,
+}
+
+/// Backend wrapper that records every call made through it and its result, to be saved as a
+/// JSON fixture for `Replayer`. Call `save` to write the fixture and see whether that worked;
+/// dropping the recorder saves it too, but can only report failures through `tracing`.
+pub struct Recorder<B> {
+    inner: B,
+    path: PathBuf,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<B: SheetsBackend> Recorder<B> {
+    /// Record calls to `inner` into the fixture file at `path`
+    pub fn new(inner: B, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            exchanges: Mutex::new(vec![]),
+        }
+    }
+
+    /// Write the calls recorded so far to the fixture file
+    pub fn save(&self) -> Result<(), SheetsError> {
+        let json = serde_json::to_string_pretty(&*self.exchanges.lock().unwrap())?;
+        fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    fn record<T>(
+        &self,
+        call: RecordedCall,
+        result: &Result<T, SheetsError>,
+        to_recorded: impl FnOnce(&T) -> RecordedResult,
+    ) {
+        let result = match result {
+            Ok(value) => to_recorded(value),
+            Err(e) => RecordedResult::Error(e.into()),
+        };
+        self.exchanges
+            .lock()
+            .unwrap()
+            .push(Exchange { call, result });
+    }
+}
+
+impl<B> Drop for Recorder<B> {
+    fn drop(&mut self) {
+        let exchanges = self.exchanges.get_mut().unwrap();
+        let _result = serde_json::to_string_pretty(exchanges)
+            .map_err(SheetsError::from)
+            .and_then(|json| fs::write(&self.path, json).map_err(SheetsError::from));
+        #[cfg(feature = "tracing")]
+        if let Err(error) = _result {
+            tracing::warn!(%error, path = ?self.path, "saving fixture failed");
+        }
+    }
+}
+
+#[async_trait]
+impl<B: SheetsBackend> SheetsBackend for Recorder<B> {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let result = self.inner.read_values(document_id, range).await;
+        let call = RecordedCall::ReadValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        };
+        self.record(call, &result, |rows| RecordedResult::Values(rows.clone()));
+        result
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let result = self.inner.read_formulas(document_id, range).await;
+        let call = RecordedCall::ReadFormulas {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        };
+        self.record(call, &result, |rows| RecordedResult::Values(rows.clone()));
+        result
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        let result = self.inner.read_ranges(document_id, ranges).await;
+        let call = RecordedCall::ReadRanges {
+            document_id: document_id.to_string(),
+            ranges: ranges.to_vec(),
+        };
+        self.record(call, &result, |values| {
+            RecordedResult::Ranges(values.clone())
+        });
+        result
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let call = RecordedCall::WriteValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+            rows: rows.clone(),
+        };
+        let result = self.inner.write_values(document_id, range, rows).await;
+        self.record(call, &result, |_| RecordedResult::Done);
+        result
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let call = RecordedCall::WriteRawValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+            rows: rows.clone(),
+        };
+        let result = self.inner.write_raw_values(document_id, range, rows).await;
+        self.record(call, &result, |_| RecordedResult::Done);
+        result
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        let call = RecordedCall::WriteRanges {
+            document_id: document_id.to_string(),
+            data: data.clone(),
+        };
+        let result = self.inner.write_ranges(document_id, data).await;
+        self.record(call, &result, |_| RecordedResult::Done);
+        result
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let call = RecordedCall::AppendValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+            rows: rows.clone(),
+        };
+        let result = self.inner.append_values(document_id, range, rows).await;
+        self.record(call, &result, |receipt| {
+            RecordedResult::Appended(receipt.clone())
+        });
+        result
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        let result = self.inner.clear_range(document_id, range).await;
+        let call = RecordedCall::ClearRange {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        };
+        self.record(call, &result, |_| RecordedResult::Done);
+        result
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        let result = self
+            .inner
+            .delete_rows(document_id, tab_name, start, end)
+            .await;
+        let call = RecordedCall::DeleteRows {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            start,
+            end,
+        };
+        self.record(call, &result, |_| RecordedResult::Done);
+        result
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        let result = self.inner.list_tabs(document_id).await;
+        let call = RecordedCall::ListTabs {
+            document_id: document_id.to_string(),
+        };
+        self.record(call, &result, |tabs| RecordedResult::Tabs(tabs.clone()));
+        result
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let result = self.inner.add_tab(document_id, tab_name).await;
+        let call = RecordedCall::AddTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+        };
+        self.record(call, &result, |tab| RecordedResult::Tab(tab.clone()));
+        result
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        let result = self.inner.delete_tab(document_id, tab_name).await;
+        let call = RecordedCall::DeleteTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+        };
+        self.record(call, &result, |_| RecordedResult::Done);
+        result
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        let result = self
+            .inner
+            .duplicate_tab(document_id, tab_name, new_name)
+            .await;
+        let call = RecordedCall::DuplicateTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            new_name: new_name.to_string(),
+        };
+        self.record(call, &result, |tab| RecordedResult::Tab(tab.clone()));
+        result
+    }
+}
+
+/// Backend that serves the calls of a fixture written by `Recorder`, in order.
+///
+/// Each call must match the next recorded one exactly, otherwise it fails with
+/// `SheetsError::Fixture`. Recorded errors are returned as the variant they were recorded as,
+/// or as `SheetsError::Fixture` for variants a fixture doesn't keep.
+pub struct Replayer {
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl Replayer {
+    /// Load the fixture file at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SheetsError> {
+        let exchanges: Vec<Exchange> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Self::new(exchanges))
+    }
+
+    /// Serve `exchanges` in order
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        Self {
+            exchanges: Mutex::new(exchanges.into()),
+        }
+    }
+
+    /// Number of recorded calls not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().len()
+    }
+
+    fn replay(&self, call: RecordedCall) -> Result<RecordedResult, SheetsError> {
+        let exchange =
+            self.exchanges
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| SheetsError::Fixture {
+                    reason: format!("unexpected call {:?}, fixture exhausted", call),
+                })?;
+
+        if exchange.call != call {
+            return Err(SheetsError::Fixture {
+                reason: format!("expected call {:?}, got {:?}", exchange.call, call),
+            });
+        }
+
+        match exchange.result {
+            RecordedResult::Error(error) => Err(error.into()),
+            result => Ok(result),
+        }
+    }
+
+    fn replay_done(&self, call: RecordedCall) -> Result<(), SheetsError> {
+        match self.replay(call)? {
+            RecordedResult::Done => Ok(()),
+            other => Err(mismatched_result(other)),
+        }
+    }
+}
+
+fn mismatched_result(result: RecordedResult) -> SheetsError {
+    SheetsError::Fixture {
+        reason: format!("recorded result {:?} does not match the call", result),
+    }
+}
+
+#[async_trait]
+impl SheetsBackend for Replayer {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        match self.replay(RecordedCall::ReadValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        })? {
+            RecordedResult::Values(rows) => Ok(rows),
+            other => Err(mismatched_result(other)),
+        }
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        match self.replay(RecordedCall::ReadFormulas {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        })? {
+            RecordedResult::Values(rows) => Ok(rows),
+            other => Err(mismatched_result(other)),
+        }
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        match self.replay(RecordedCall::ReadRanges {
+            document_id: document_id.to_string(),
+            ranges: ranges.to_vec(),
+        })? {
+            RecordedResult::Ranges(values) => Ok(values),
+            other => Err(mismatched_result(other)),
+        }
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.replay_done(RecordedCall::WriteValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+            rows,
+        })
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.replay_done(RecordedCall::WriteRawValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+            rows,
+        })
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        self.replay_done(RecordedCall::WriteRanges {
+            document_id: document_id.to_string(),
+            data,
+        })
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        match self.replay(RecordedCall::AppendValues {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+            rows,
+        })? {
+            RecordedResult::Appended(receipt) => Ok(receipt),
+            other => Err(mismatched_result(other)),
+        }
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        self.replay_done(RecordedCall::ClearRange {
+            document_id: document_id.to_string(),
+            range: range.to_string(),
+        })
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        self.replay_done(RecordedCall::DeleteRows {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            start,
+            end,
+        })
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        match self.replay(RecordedCall::ListTabs {
+            document_id: document_id.to_string(),
+        })? {
+            RecordedResult::Tabs(tabs) => Ok(tabs),
+            other => Err(mismatched_result(other)),
+        }
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        match self.replay(RecordedCall::AddTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+        })? {
+            RecordedResult::Tab(tab) => Ok(tab),
+            other => Err(mismatched_result(other)),
+        }
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        self.replay_done(RecordedCall::DeleteTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+        })
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        match self.replay(RecordedCall::DuplicateTab {
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            new_name: new_name.to_string(),
+        })? {
+            RecordedResult::Tab(tab) => Ok(tab),
+            other => Err(mismatched_result(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replays_recorded_errors_as_their_variant() {
+        let error = SheetsError::tab_not_found("Ordrs", vec!["Orders".to_string()]);
+        let replayer = Replayer::new(vec![Exchange {
+            call: RecordedCall::ReadValues {
+                document_id: "doc".to_string(),
+                range: "Ordrs".to_string(),
+            },
+            result: RecordedResult::Error((&error).into()),
+        }]);
+
+        match replayer.read_values("doc", "Ordrs").await {
+            Err(SheetsError::TabNotFound {
+                requested,
+                suggestion,
+                ..
+            }) => {
+                assert_eq!(requested, "Ordrs");
+                assert_eq!(suggestion.as_deref(), Some("Orders"));
+            }
+            other => panic!("expected TabNotFound, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Lookup formulas over typed ranges, so generated formulas get their tab quoting, string
+//! escaping and column offsets right, e.g.
+//!
+//! ```ignore
+//! let prices = RangeRef::parse("'Price List'!A2:C")?;
+//! let price = formula::vlookup(CellRef::parse("B2")?, &prices, 2)?;
+//! assert_eq!(price.to_string(), "=VLOOKUP(B2, 'Price List'!A2:C, 3, FALSE)");
+//! ```
+//!
+//! A `Formula` serializes as its `=` text, so it can be a field of a written record.
+
+use crate::{
+    range::{column_letter, MAX_COLUMN},
+    CellRef, DocumentId, IntoCell, RangeRef, SheetsError,
+};
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// A formula, displayed and serialized with its leading `=`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Formula(String);
+
+impl Formula {
+    /// The formula without its leading `=`, for nesting in other formulas
+    pub fn expression(&self) -> &str {
+        &self.0
+    }
+
+    /// This formula, or `fallback` where it errors, e.g. a lookup key that isn't found
+    pub fn if_error(self, fallback: impl Into<Operand>) -> Formula {
+        Formula(format!("IFERROR({}, {})", self.0, fallback.into().0))
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "={}", self.0)
+    }
+}
+
+impl IntoCell for Formula {
+    fn into_cell(self) -> String {
+        self.to_string()
+    }
+}
+
+impl Serialize for Formula {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// An argument of a formula: a cell or range reference, a nested formula, or a literal
+/// string or number written so that it can't be read as anything else
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Operand(String);
+
+impl From<CellRef> for Operand {
+    /// A cell of the tab the formula is in
+    fn from(cell: CellRef) -> Self {
+        Operand(cell.to_string())
+    }
+}
+
+impl From<&RangeRef> for Operand {
+    fn from(range: &RangeRef) -> Self {
+        Operand(reference(range))
+    }
+}
+
+impl From<Formula> for Operand {
+    fn from(formula: Formula) -> Self {
+        Operand(formula.0)
+    }
+}
+
+impl From<&str> for Operand {
+    fn from(text: &str) -> Self {
+        Operand(string_literal(text))
+    }
+}
+
+impl From<String> for Operand {
+    fn from(text: String) -> Self {
+        Operand(string_literal(&text))
+    }
+}
+
+impl From<i32> for Operand {
+    fn from(number: i32) -> Self {
+        Operand(number.to_string())
+    }
+}
+
+impl From<i64> for Operand {
+    fn from(number: i64) -> Self {
+        Operand(number.to_string())
+    }
+}
+
+impl From<f64> for Operand {
+    fn from(number: f64) -> Self {
+        Operand(number.to_string())
+    }
+}
+
+/// `text` as a formula string literal, e.g. `say "hi"` => `"say ""hi"""`
+pub fn string_literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// The value in zero-based column `result_column` of the first row of `table` whose first
+/// column is exactly `key`. `result_column` counts from column `A` like `CellRef`, not from
+/// the table's first column as VLOOKUP does; it must be within `table`.
+pub fn vlookup(
+    key: impl Into<Operand>,
+    table: &RangeRef,
+    result_column: usize,
+) -> Result<Formula, SheetsError> {
+    let within = result_column >= table.start.column
+        && table.end_column.map_or(true, |end| result_column <= end);
+    if !within {
+        return Err(SheetsError::MalformedRange {
+            range: format!(
+                "{} does not contain column {}",
+                table,
+                column_letter(result_column)
+            ),
+        });
+    }
+    Ok(Formula(format!(
+        "VLOOKUP({}, {}, {}, FALSE)",
+        key.into().0,
+        reference(table),
+        result_column - table.start.column + 1
+    )))
+}
+
+/// The value in column `result` on the row where column `lookup` is exactly `key`. Both
+/// ranges must be single columns spanning the same rows, e.g. `'Products'!C2:C` and
+/// `'Products'!A2:A`; unlike `vlookup`, the key column may be right of the result.
+pub fn index_match(
+    result: &RangeRef,
+    lookup: &RangeRef,
+    key: impl Into<Operand>,
+) -> Result<Formula, SheetsError> {
+    for range in [result, lookup] {
+        if range.end_column != Some(range.start.column) {
+            return Err(SheetsError::MalformedRange {
+                range: format!("{} is not a single column", range),
+            });
+        }
+    }
+    if (result.start.row, result.end_row) != (lookup.start.row, lookup.end_row) {
+        return Err(SheetsError::MalformedRange {
+            range: format!("{} and {} span different rows", result, lookup),
+        });
+    }
+    Ok(Formula(format!(
+        "INDEX({}, MATCH({}, {}, 0))",
+        reference(result),
+        key.into().0,
+        reference(lookup)
+    )))
+}
+
+/// `range` of another document. The first use of a document must be allowed in the UI
+/// before any values show.
+pub fn importrange(document_id: &DocumentId, range: &RangeRef) -> Formula {
+    Formula(format!(
+        "IMPORTRANGE({}, {})",
+        string_literal(document_id.as_str()),
+        string_literal(&reference(range))
+    ))
+}
+
+/// `range` as formulas need it; a whole tab as its columns, since a bare tab name isn't a
+/// reference there
+fn reference(range: &RangeRef) -> String {
+    if range.start == CellRef::new(0, 0) && range.end_row.is_none() && range.end_column.is_none() {
+        let whole = RangeRef {
+            end_column: Some(MAX_COLUMN),
+            ..range.clone()
+        };
+        return whole.to_string();
+    }
+    range.to_string()
+}
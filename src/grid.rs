@@ -0,0 +1,104 @@
+use crate::{RangeRef, SheetsError};
+
+/// Zero-based, inclusive bounds of an A1 range; `None` ends are unbounded
+pub(crate) struct Span {
+    pub start_row: usize,
+    pub start_column: usize,
+    pub end_row: Option<usize>,
+    pub end_column: Option<usize>,
+}
+
+impl From<&RangeRef> for Span {
+    fn from(range: &RangeRef) -> Self {
+        Self {
+            start_row: range.start.row,
+            start_column: range.start.column,
+            end_row: range.end_row,
+            end_column: range.end_column,
+        }
+    }
+}
+
+impl Span {
+    pub fn contains_row(&self, row: usize) -> bool {
+        row >= self.start_row && self.end_row.map_or(true, |end| row <= end)
+    }
+
+    pub fn contains_column(&self, column: usize) -> bool {
+        column >= self.start_column && self.end_column.map_or(true, |end| column <= end)
+    }
+}
+
+/// Split an A1 range like `'My Tab'!B2:C3` into the unquoted tab name and its bounds,
+/// failing with `SheetsError::MalformedRange` as `RangeRef::parse` does
+pub(crate) fn tab_and_span(range: &str) -> Result<(String, Span), SheetsError> {
+    let range = RangeRef::parse(range)?;
+    let span = Span::from(&range);
+    Ok((range.tab_name, span))
+}
+
+/// Cells of `grid` within `span`, trimmed like the API does
+pub(crate) fn read_span(grid: &[Vec<String>], span: &Span) -> Vec<Vec<String>> {
+    let rows = grid
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| span.contains_row(*i))
+        .map(|(_, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(j, _)| span.contains_column(*j))
+                .map(|(_, cell)| cell.clone())
+                .collect()
+        })
+        .collect();
+
+    trim_grid(rows)
+}
+
+/// Empty the cells of `grid` within `span`
+pub(crate) fn clear_span(grid: &mut Vec<Vec<String>>, span: &Span) {
+    for (i, row) in grid.iter_mut().enumerate() {
+        if span.contains_row(i) {
+            for (j, cell) in row.iter_mut().enumerate() {
+                if span.contains_column(j) {
+                    cell.clear();
+                }
+            }
+        }
+    }
+    *grid = trim_grid(std::mem::take(grid));
+}
+
+/// Overwrite cells of `grid` starting at `(row, column)`, growing it as needed
+pub(crate) fn write_at(
+    grid: &mut Vec<Vec<String>>,
+    row: usize,
+    column: usize,
+    rows: Vec<Vec<String>>,
+) {
+    for (i, values) in rows.into_iter().enumerate() {
+        if grid.len() <= row + i {
+            grid.resize(row + i + 1, vec![]);
+        }
+        let target = &mut grid[row + i];
+        if target.len() < column + values.len() {
+            target.resize(column + values.len(), String::new());
+        }
+        for (j, value) in values.into_iter().enumerate() {
+            target[column + j] = value;
+        }
+    }
+}
+
+/// Drop trailing empty cells and rows, like the API does
+pub(crate) fn trim_grid(mut rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    for row in rows.iter_mut() {
+        while row.last().map_or(false, String::is_empty) {
+            row.pop();
+        }
+    }
+    while rows.last().map_or(false, Vec::is_empty) {
+        rows.pop();
+    }
+    rows
+}
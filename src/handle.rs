@@ -0,0 +1,385 @@
+use crate::{
+    append_row_with_options, append_rows, clear_tab, get_row_with_options, read_all_chunked,
+    read_all_with_options, read_indexed_with_options, read_page_with_options,
+    read_rows_at_with_options, replace_rows_with_options, retry::with_retries, set_row,
+    update_column_with_options, write_page_chunked, write_page_with_options, IntoCell, ReadOptions,
+    RetryPolicy, RowIndex, SheetsBackend, SheetsError, TabInfo, WriteOptions, WriteReceipt,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, future::Future};
+
+/// Options a `Worksheet` uses when a call doesn't give its own, so call sites for tabs that
+/// need, say, raw input or a comma decimal locale stay as short as the rest
+#[derive(Debug, Clone, Default)]
+pub struct TabDefaults {
+    /// Used by `write_page`, `replace_rows` and `append_row`
+    pub write: WriteOptions,
+    /// Used by `read_all`, `read_indexed`, `read_page`, `read_rows_at`, `get_row` and
+    /// `update_column`
+    pub read: ReadOptions,
+    /// Retries for every call made through the worksheet, on top of any the backend makes
+    pub retry: Option<RetryPolicy>,
+}
+
+impl TabDefaults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(mut self, write: WriteOptions) -> Self {
+        self.write = write;
+        self
+    }
+
+    pub fn read(mut self, read: ReadOptions) -> Self {
+        self.read = read;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+/// A document bound to a client, so its ID is given once rather than on every call
+pub struct Spreadsheet<'a, B: ?Sized> {
+    sheets: &'a B,
+    document_id: String,
+    defaults: HashMap<String, TabDefaults>,
+}
+
+impl<'a, B: SheetsBackend + ?Sized> Spreadsheet<'a, B> {
+    pub fn new(sheets: &'a B, document_id: impl Into<String>) -> Self {
+        Self {
+            sheets,
+            document_id: document_id.into(),
+            defaults: HashMap::new(),
+        }
+    }
+
+    pub fn document_id(&self) -> &str {
+        &self.document_id
+    }
+
+    /// Use `defaults` for worksheets of tab `tab_name` got from this document from now on
+    pub fn with_tab_defaults(mut self, tab_name: impl Into<String>, defaults: TabDefaults) -> Self {
+        self.defaults.insert(tab_name.into(), defaults);
+        self
+    }
+
+    /// Tab `tab_name`, without checking that it exists
+    pub fn worksheet(&self, tab_name: impl Into<String>) -> Worksheet<'a, B> {
+        let tab_name = tab_name.into();
+        Worksheet {
+            sheets: self.sheets,
+            document_id: self.document_id.clone(),
+            defaults: self.defaults.get(&tab_name).cloned().unwrap_or_default(),
+            tab_name,
+        }
+    }
+
+    /// Tab `tab_name`, failing with `SheetsError::TabNotFound` now rather than on first use if
+    /// the document has no such tab
+    pub async fn open_worksheet(&self, tab_name: &str) -> Result<Worksheet<'a, B>, SheetsError> {
+        self.sheets.tab(&self.document_id, tab_name).await?;
+        Ok(self.worksheet(tab_name))
+    }
+
+    /// All tabs of the document, in display order
+    pub async fn tabs(&self) -> Result<Vec<TabInfo>, SheetsError> {
+        self.sheets.list_tabs(&self.document_id).await
+    }
+
+    /// Create an empty tab called `tab_name`
+    pub async fn add_worksheet(&self, tab_name: &str) -> Result<Worksheet<'a, B>, SheetsError> {
+        self.sheets.add_tab(&self.document_id, tab_name).await?;
+        Ok(self.worksheet(tab_name))
+    }
+}
+
+/// A tab bound to a client and document, with the crate's operations as methods
+pub struct Worksheet<'a, B: ?Sized> {
+    sheets: &'a B,
+    document_id: String,
+    tab_name: String,
+    defaults: TabDefaults,
+}
+
+impl<'a, B: SheetsBackend + ?Sized> Worksheet<'a, B> {
+    pub fn document_id(&self) -> &str {
+        &self.document_id
+    }
+
+    pub fn tab_name(&self) -> &str {
+        &self.tab_name
+    }
+
+    /// The defaults calls on this worksheet use
+    pub fn defaults(&self) -> &TabDefaults {
+        &self.defaults
+    }
+
+    /// This worksheet with `defaults` instead of the ones it was got with
+    pub fn with_defaults(mut self, defaults: TabDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// `call`, retried as the defaults say; `idempotent` as for `RetryPolicy::delay`
+    async fn call<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        idempotent: bool,
+        call: F,
+    ) -> Result<T, SheetsError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, SheetsError>>,
+    {
+        match &self.defaults.retry {
+            Some(policy) => with_retries(policy, None, operation, idempotent, call).await,
+            None => call().await,
+        }
+    }
+
+    /// The tab's ID and grid size
+    pub async fn info(&self) -> Result<TabInfo, SheetsError> {
+        self.call("tab", true, || {
+            self.sheets.tab(&self.document_id, &self.tab_name)
+        })
+        .await
+    }
+
+    /// See `clear_tab`
+    pub async fn clear(&self) -> Result<(), SheetsError> {
+        self.call("clear_tab", true, || {
+            clear_tab(self.sheets, &self.document_id, &self.tab_name)
+        })
+        .await
+    }
+
+    /// See `write_page`; written with the default `WriteOptions`
+    pub async fn write_page(
+        &self,
+        objects: &[impl Serialize],
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.write_page_with_options(objects, &self.defaults.write)
+            .await
+    }
+
+    /// See `replace_rows`; written with the default `WriteOptions`
+    pub async fn replace_rows(
+        &self,
+        objects: &[impl Serialize],
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.call("replace_rows", true, || {
+            replace_rows_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                objects,
+                &self.defaults.write,
+            )
+        })
+        .await
+    }
+
+    /// See `write_page_with_options`
+    pub async fn write_page_with_options(
+        &self,
+        objects: &[impl Serialize],
+        options: &WriteOptions,
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.call("write_page", true, || {
+            write_page_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                objects,
+                options,
+            )
+        })
+        .await
+    }
+
+    /// See `write_page_chunked`
+    pub async fn write_page_chunked(
+        &self,
+        objects: &[impl Serialize],
+        chunk_size: usize,
+        parallelism: usize,
+    ) -> Result<(), SheetsError> {
+        self.call("write_page_chunked", true, || {
+            write_page_chunked(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                objects,
+                chunk_size,
+                parallelism,
+            )
+        })
+        .await
+    }
+
+    /// See `append_row`; serialized with the default `WriteOptions`
+    pub async fn append_row(&self, obj: impl Serialize) -> Result<WriteReceipt, SheetsError> {
+        self.call("append_row", false, || {
+            append_row_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                &obj,
+                &self.defaults.write,
+            )
+        })
+        .await
+    }
+
+    /// See `append_rows`
+    pub async fn append_rows(
+        &self,
+        objects: &[impl Serialize],
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.call("append_rows", false, || {
+            append_rows(self.sheets, &self.document_id, &self.tab_name, objects)
+        })
+        .await
+    }
+
+    /// See `get_row`; read with the default `ReadOptions`
+    pub async fn get_row<T: DeserializeOwned>(
+        &self,
+        row: impl Into<RowIndex>,
+    ) -> Result<Option<T>, SheetsError> {
+        let row = row.into();
+        self.call("get_row", true, || {
+            get_row_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                row,
+                &self.defaults.read,
+            )
+        })
+        .await
+    }
+
+    /// See `set_row`
+    pub async fn set_row(
+        &self,
+        row: impl Into<RowIndex>,
+        obj: &impl Serialize,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let row = row.into();
+        self.call("set_row", true, || {
+            set_row(self.sheets, &self.document_id, &self.tab_name, row, obj)
+        })
+        .await
+    }
+
+    /// See `update_column`; read with the default `ReadOptions`
+    pub async fn update_column<T: DeserializeOwned, C: IntoCell>(
+        &self,
+        field_name: &str,
+        value: impl Fn(&T) -> C,
+    ) -> Result<usize, SheetsError> {
+        self.call("update_column", true, || {
+            update_column_with_options::<T, _, C, _>(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                field_name,
+                &value,
+                &self.defaults.read,
+            )
+        })
+        .await
+    }
+
+    /// See `read_all`; read with the default `ReadOptions`
+    pub async fn read_all<T: DeserializeOwned + 'static>(&self) -> Result<Vec<T>, SheetsError> {
+        self.read_all_with_options(&self.defaults.read).await
+    }
+
+    /// See `read_all_with_options`
+    pub async fn read_all_with_options<T: DeserializeOwned + 'static>(
+        &self,
+        options: &ReadOptions,
+    ) -> Result<Vec<T>, SheetsError> {
+        self.call("read_all", true, || {
+            read_all_with_options(self.sheets, &self.document_id, &self.tab_name, options)
+        })
+        .await
+    }
+
+    /// See `read_indexed`; read with the default `ReadOptions`
+    pub async fn read_indexed<T: DeserializeOwned + 'static>(
+        &self,
+    ) -> Result<Vec<(RowIndex, T)>, SheetsError> {
+        self.call("read_indexed", true, || {
+            read_indexed_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                &self.defaults.read,
+            )
+        })
+        .await
+    }
+
+    /// See `read_rows_at`; read with the default `ReadOptions`
+    pub async fn read_rows_at<T: DeserializeOwned>(
+        &self,
+        rows: &[usize],
+    ) -> Result<Vec<Option<T>>, SheetsError> {
+        self.call("read_rows_at", true, || {
+            read_rows_at_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                rows,
+                &self.defaults.read,
+            )
+        })
+        .await
+    }
+
+    /// See `read_page`; read with the default `ReadOptions`
+    pub async fn read_page<T: DeserializeOwned>(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<T>, SheetsError> {
+        self.call("read_page", true, || {
+            read_page_with_options(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                offset,
+                limit,
+                &self.defaults.read,
+            )
+        })
+        .await
+    }
+
+    /// See `read_all_chunked`
+    pub async fn read_all_chunked<T: DeserializeOwned>(
+        &self,
+        chunk_size: usize,
+        parallelism: usize,
+    ) -> Result<Vec<T>, SheetsError> {
+        self.call("read_all_chunked", true, || {
+            read_all_chunked(
+                self.sheets,
+                &self.document_id,
+                &self.tab_name,
+                chunk_size,
+                parallelism,
+            )
+        })
+        .await
+    }
+}
@@ -0,0 +1,112 @@
+//! Field documentation shown as notes on header cells, so people editing a sheet see what
+//! each column means.
+//!
+//! There is no derive; list the descriptions with `impl_column_docs!`, keyed by the names the
+//! fields serialize to:
+//!
+//! ```ignore
+//! impl_column_docs!(Product {
+//!     "price" => "Price in USD, excl. tax",
+//!     "sku" => "Stock keeping unit, as printed on the label",
+//! });
+//! ```
+//!
+//! Serde doesn't see doc comments, so those can't be the fallback.
+
+use crate::{batch_update, quote_tab_name, write_page, SheetsBackend, SheetsError, WriteReceipt};
+use google_sheets4::{
+    api::{CellData, GridRange, Request, RowData, UpdateCellsRequest},
+    Sheets,
+};
+use serde::Serialize;
+
+/// Descriptions of a record type's columns
+pub trait ColumnDocs {
+    /// `(column, description)` pairs, by the names fields serialize to
+    fn column_docs() -> Vec<(&'static str, &'static str)>;
+}
+
+/// Implement `ColumnDocs` for a type from `"column" => "description"` pairs
+#[macro_export]
+macro_rules! impl_column_docs {
+    ($ty:ty { $($column:literal => $doc:literal),* $(,)? }) => {
+        impl $crate::header_notes::ColumnDocs for $ty {
+            fn column_docs() -> Vec<(&'static str, &'static str)> {
+                vec![$(($column, $doc)),*]
+            }
+        }
+    };
+}
+
+/// Set the notes on the header cells of tab `tab_name` to `T`'s column descriptions. Columns
+/// without a description keep their notes; described columns the header lacks are ignored.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn write_header_notes<T: ColumnDocs>(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<(), SheetsError> {
+    let header_range = format!("{}!1:1", quote_tab_name(tab_name));
+    let header = sheets
+        .read_values(document_id, &header_range)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let docs = T::column_docs();
+    let notes: Vec<(usize, &str)> = header
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| {
+            docs.iter()
+                .find(|(column, _)| column == name)
+                .map(|(_, doc)| (i, *doc))
+        })
+        .collect();
+    if notes.is_empty() {
+        return Ok(());
+    }
+
+    let sheet_id = sheets.tab(document_id, tab_name).await?.sheet_id;
+    let requests = notes
+        .into_iter()
+        .map(|(column, note)| Request {
+            update_cells: Some(UpdateCellsRequest {
+                range: Some(GridRange {
+                    sheet_id: Some(sheet_id),
+                    start_row_index: Some(0),
+                    end_row_index: Some(1),
+                    start_column_index: Some(column as i32),
+                    end_column_index: Some(column as i32 + 1),
+                }),
+                rows: Some(vec![RowData {
+                    values: Some(vec![CellData {
+                        note: Some(note.to_string()),
+                        ..Default::default()
+                    }]),
+                }]),
+                fields: Some("note".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .collect();
+    batch_update(sheets, document_id, requests).await?;
+    Ok(())
+}
+
+/// `write_page`, then `write_header_notes` for `T`
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn write_page_documented<T: Serialize + ColumnDocs>(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[T],
+) -> Result<WriteReceipt, SheetsError> {
+    let receipt = write_page(sheets, document_id, tab_name, objects).await?;
+    write_header_notes::<T>(sheets, document_id, tab_name).await?;
+    Ok(receipt)
+}
@@ -0,0 +1,137 @@
+use crate::SheetsError;
+use std::{fmt, str::FromStr};
+
+/// Longest tab name the Sheets UI accepts
+const MAX_SHEET_NAME_LEN: usize = 100;
+
+/// Characters not allowed in tab names, since Excel rejects them and `export_xlsx` would fail
+const FORBIDDEN_SHEET_NAME_CHARS: &[char] = &[':', '\\', '/', '?', '*', '[', ']'];
+
+/// A spreadsheet's document ID, parsed from a bare ID or a browser URL like
+/// `https://docs.google.com/spreadsheets/d/<id>/edit#gid=0`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DocumentId {
+    id: String,
+    gid: Option<i32>,
+}
+
+impl DocumentId {
+    /// The ID in `input`, with the tab's `gid` if `input` is a URL that has one
+    pub fn parse(input: &str) -> Result<Self, SheetsError> {
+        let input = input.trim();
+        let invalid = || SheetsError::InvalidDocumentId {
+            input: input.to_string(),
+        };
+
+        let (id, gid) = match input.split_once("/spreadsheets/d/") {
+            Some((_, rest)) => {
+                let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+                (&rest[..end], gid_from_url(&rest[end..]))
+            }
+            None if input.contains('/') => return Err(invalid()),
+            None => (input, None),
+        };
+
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(Self {
+            id: id.to_string(),
+            gid,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// Sheet ID of the tab the URL pointed at, comparable with `TabInfo::sheet_id`
+    pub fn gid(&self) -> Option<i32> {
+        self.gid
+    }
+}
+
+/// The `gid` parameter in the query or fragment of a URL, e.g. `/edit#gid=123`
+fn gid_from_url(rest: &str) -> Option<i32> {
+    rest.split(['?', '#', '&'])
+        .find_map(|param| param.strip_prefix("gid="))
+        .and_then(|gid| gid.parse().ok())
+}
+
+impl FromStr for DocumentId {
+    type Err = SheetsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+impl AsRef<str> for DocumentId {
+    fn as_ref(&self) -> &str {
+        &self.id
+    }
+}
+
+/// A tab name checked to be one the API will accept
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SheetName(String);
+
+impl SheetName {
+    /// `name`, if it is not blank, at most 100 characters and free of control characters and
+    /// `: \ / ? * [ ]`
+    pub fn new(name: impl Into<String>) -> Result<Self, SheetsError> {
+        let name = name.into();
+        let invalid = |reason: &str| SheetsError::InvalidSheetName {
+            name: name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if name.trim().is_empty() {
+            return Err(invalid("name is blank"));
+        }
+        if name.chars().count() > MAX_SHEET_NAME_LEN {
+            return Err(invalid("name is longer than 100 characters"));
+        }
+        if let Some(c) = name
+            .chars()
+            .find(|c| c.is_control() || FORBIDDEN_SHEET_NAME_CHARS.contains(c))
+        {
+            return Err(invalid(&format!("name contains {:?}", c)));
+        }
+        Ok(Self(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for SheetName {
+    type Err = SheetsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl fmt::Display for SheetName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for SheetName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
@@ -0,0 +1,131 @@
+//! Image cells, written as `=IMAGE(...)` formulas, e.g. thumbnails in a product catalog.
+//! The formula only takes effect with the default `ValueInput::UserEntered`.
+//!
+//! A sheet displays nothing for an image cell, so read tables holding them with
+//! `ReadOptions::formulas` to get the formula back. Cells holding a bare `http(s)` URL are
+//! read as images too.
+
+use crate::SheetsError;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+/// How an image is sized within its cell, the `mode` argument of `IMAGE`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageMode {
+    /// Scaled to fit the cell, keeping its aspect ratio
+    #[default]
+    Fit,
+    /// Stretched to fill the cell
+    Stretch,
+    /// At its original size, cropped by the cell
+    Original,
+    /// At this size in pixels
+    Custom { height: u32, width: u32 },
+}
+
+/// A cell showing the image at `url`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageCell {
+    pub url: String,
+    pub mode: ImageMode,
+}
+
+impl ImageCell {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            mode: ImageMode::Fit,
+        }
+    }
+
+    pub fn mode(mut self, mode: ImageMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The `=IMAGE(...)` formula showing this image. Fails with `SheetsError::InvalidRecord`
+    /// if the URL isn't `http` or `https` or holds control characters; quotes are escaped, so
+    /// a URL can't end the string and add formula text of its own.
+    pub fn formula(&self) -> Result<String, SheetsError> {
+        let invalid = |reason: &str| SheetsError::InvalidRecord {
+            reason: format!("image URL {:?} {}", self.url, reason),
+        };
+        if !is_web_url(&self.url) {
+            return Err(invalid("is not http or https"));
+        }
+        if self.url.chars().any(char::is_control) {
+            return Err(invalid("has control characters"));
+        }
+        let url = self.url.replace('"', "\"\"");
+        Ok(match self.mode {
+            ImageMode::Fit => format!("=IMAGE(\"{}\")", url),
+            ImageMode::Stretch => format!("=IMAGE(\"{}\", 2)", url),
+            ImageMode::Original => format!("=IMAGE(\"{}\", 3)", url),
+            ImageMode::Custom { height, width } => {
+                format!("=IMAGE(\"{}\", 4, {}, {})", url, height, width)
+            }
+        })
+    }
+
+    /// The image in a cell holding an `=IMAGE(...)` formula with a literal URL, or a bare
+    /// `http(s)` URL
+    pub fn parse(cell: &str) -> Option<Self> {
+        let cell = cell.trim();
+        if is_web_url(cell) {
+            return Some(Self::new(cell));
+        }
+        let rest = cell.strip_prefix('=')?.trim_start();
+        if !rest.get(..5)?.eq_ignore_ascii_case("IMAGE") {
+            return None;
+        }
+        let rest = rest[5..].trim_start().strip_prefix('(')?.trim_start();
+        let rest = rest.strip_prefix('"')?;
+
+        let mut url = String::new();
+        let mut chars = rest.char_indices().peekable();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') if chars.peek().map(|(_, c)| *c) != Some('"') => break i + 1,
+                (_, '"') => {
+                    chars.next();
+                    url.push('"');
+                }
+                (_, c) => url.push(c),
+            }
+        };
+        let args = rest[end..].trim().strip_suffix(')')?.trim();
+        let args: Vec<u32> = match args.strip_prefix(',') {
+            Some(args) => args
+                .split(',')
+                .map(|arg| arg.trim().parse().ok())
+                .collect::<Option<_>>(),
+            None if args.is_empty() => Some(vec![]),
+            None => None,
+        }?;
+        let mode = match args[..] {
+            [] | [1] => ImageMode::Fit,
+            [2] => ImageMode::Stretch,
+            [3] => ImageMode::Original,
+            [4, height, width] => ImageMode::Custom { height, width },
+            _ => return None,
+        };
+        Some(Self { url, mode })
+    }
+}
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+impl Serialize for ImageCell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.formula().map_err(ser::Error::custom)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageCell {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let cell = String::deserialize(deserializer)?;
+        Self::parse(&cell)
+            .ok_or_else(|| de::Error::custom(format!("{:?} is not an image or image URL", cell)))
+    }
+}
@@ -1,18 +1,156 @@
-use csv::{ReaderBuilder, StringRecord, Writer, WriterBuilder};
-use google_sheets4::{
-    api::{ClearValuesRequest, ValueRange},
-    Sheets,
-};
+use csv::Writer;
+use futures::{stream, StreamExt};
+use google_sheets4::api::{BatchUpdateSpreadsheetRequest, BatchUpdateSpreadsheetResponse, Request};
 use serde::de::DeserializeOwned;
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use thiserror::Error;
-use yup_oauth2::{ServiceAccountAuthenticator, ServiceAccountKey};
+pub use tokio_util::sync::CancellationToken;
+use yup_oauth2::ServiceAccountAuthenticator;
+
+// the client types in this crate's API, so depending on it alone is enough to build a client
+pub use google_sheets4::{self, Sheets};
+pub use hyper;
+pub use hyper_rustls;
+pub use yup_oauth2::{self, ServiceAccountKey};
+
+mod api_error;
+pub mod appender;
+pub mod audit;
+pub mod backend;
+pub mod cache;
+pub mod charts;
+#[cfg(any(feature = "arrow", feature = "polars", feature = "sqlite"))]
+mod columns;
+pub mod computed;
+pub mod config;
+pub mod csv_dir;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+mod de;
+pub mod delete;
+pub mod drive;
+pub mod dropdown;
+pub mod dry_run;
+pub mod dynamic;
+pub mod event_log;
+pub mod export;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod formula;
+mod grid;
+pub mod handle;
+pub mod header_notes;
+pub mod ids;
+pub mod image;
+pub mod live_config;
+pub mod lock;
+pub mod matrix;
+pub mod middleware;
+pub mod mock;
+#[cfg(feature = "msgraph")]
+pub mod msgraph;
+pub mod offline;
+pub mod options;
+pub mod partition;
+pub mod pivot;
+pub mod prelude;
+pub mod range;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
+pub mod retry;
+pub mod rich;
+pub mod row_ids;
+pub mod rows;
+pub mod schema;
+mod ser;
+pub mod sort;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod telemetry;
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod trace;
+pub mod upsert;
+pub mod validate;
+pub mod with;
+
+pub use api_error::{ApiErrorDetails, ErrorKind};
+pub use appender::BufferedAppender;
+pub use audit::{AuditEntry, Audited};
+pub use backend::{SheetsBackend, TabInfo, WriteReceipt};
+pub use cache::ReadCache;
+pub use charts::{Chart, ChartType};
+pub use computed::ComputedColumns;
+pub use config::SheetsConfig;
+pub use csv_dir::CsvDirectory;
+#[cfg(feature = "polars")]
+pub use dataframe::{read_dataframe, write_dataframe};
+pub use delete::{dedupe, delete_rows_where, prune_rows, DeleteMode, PruneMode};
+pub use drive::{
+    create_spreadsheet, export_pdf, export_pdf_to_file, export_xlsx, move_to_folder, share,
+    CreateOptions, DriveRole, Share,
+};
+pub use dropdown::Dropdown;
+pub use dry_run::{DryRun, PlannedMutation};
+pub use dynamic::{dynamic_rows, write_dynamic};
+pub use event_log::{Event, EventLog};
+pub use export::{export_csv, export_ndjson, import_csv, import_ndjson, ImportMode, ImportOptions};
+pub use formula::{Formula, Operand};
+pub use handle::{Spreadsheet, TabDefaults, Worksheet};
+pub use header_notes::{write_header_notes, write_page_documented, ColumnDocs};
+pub use ids::{DocumentId, SheetName};
+pub use image::{ImageCell, ImageMode};
+pub use live_config::{read_key_values, ConfigHandle};
+pub use lock::SheetLock;
+pub use matrix::{read_f64_matrix, read_matrix, write_matrix, IntoCell};
+pub use middleware::{ApiRequest, Intercepted, Middleware};
+pub use mock::MockSheets;
+#[cfg(feature = "msgraph")]
+pub use msgraph::{GraphToken, GraphWorkbook};
+pub use offline::OfflineQueue;
+pub use options::{
+    CsvOptions, DecimalSeparator, HeaderCase, HeaderKey, HeaderMatching, Provenance, ReadOptions,
+    RowPredicate, RowValidator, ValueInput, WriteOptions,
+};
+pub use partition::{Partition, PartitionedTable};
+pub use pivot::{Aggregation, PivotTable};
+use range::{column_letter, quote_tab_name};
+pub use range::{CellRef, RangeRef};
+#[cfg(feature = "arrow")]
+pub use record_batch::{read_record_batch, write_record_batch};
+pub use retry::{RetryOn, RetryPolicy, RetryStrategy, Retrying};
+pub use rich::{read_cells_rich, Color, RichCell};
+pub use row_ids::{delete_by_id, update_by_id, write_with_ids};
+pub use rows::{read_rows, write_rows, FromRow, ToRow};
+pub use schema::{check_schema, record_schema, Schema};
+pub use sort::{read_all_sorted, sort_tab};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{mirror_to_sqlite, sync_from_sqlite};
+pub use telemetry::{Metered, MetricsRecorder, RequestEvent};
+pub use template::instantiate_template;
+pub use upsert::{append_if_changed, upsert_row, MergeStrategy};
+pub use validate::{validate_rows, ValidationIssue, ValidationReport};
 
+/// Errors from this crate. `kind` sorts them into broad categories.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum SheetsError {
     #[error("SERVICE_ACCOUNT_JSON not defined")]
     EnvVarNotFound(#[from] std::env::VarError),
 
+    #[error("Missing configuration: {name} is not set")]
+    MissingConfig { name: &'static str },
+
+    #[error("Cannot read service account file {path:?}: {source}")]
+    ServiceAccountFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[error("Invalid service account JSON")]
     InvalidServiceAccountJSON(#[from] serde_json::Error),
 
@@ -22,6 +160,13 @@ pub enum SheetsError {
     #[error(transparent)]
     SheetsError(#[from] google_sheets4::Error),
 
+    #[error(transparent)]
+    Auth(#[from] yup_oauth2::Error),
+
+    /// The request did not get a response, e.g. a connection or TLS failure
+    #[error("Network error: {0}")]
+    Network(#[source] google_sheets4::Error),
+
     #[error(transparent)]
     CSVError(#[from] csv::Error),
 
@@ -30,6 +175,140 @@ pub enum SheetsError {
 
     #[error("Internal error")]
     InternalWriterError(#[from] csv::IntoInnerError<Writer<Vec<u8>>>),
+
+    #[error("Tab not found: {requested}{}", .suggestion.as_ref().map(|s| format!(", did you mean {:?}?", s)).unwrap_or_default())]
+    TabNotFound {
+        requested: String,
+        /// Tabs the document does have, if known
+        available: Vec<String>,
+        /// The available tab closest to `requested`, if any is close
+        suggestion: Option<String>,
+    },
+
+    #[error("Not a document ID or spreadsheet URL: {input}")]
+    InvalidDocumentId { input: String },
+
+    #[error("Invalid tab name {name:?}: {reason}")]
+    InvalidSheetName { name: String, reason: String },
+
+    #[error("Header {name:?} appears more than once, in columns {}", .columns.join(", "))]
+    DuplicateHeader { name: String, columns: Vec<String> },
+
+    #[error("Columns not in the record type: {}", .columns.join(", "))]
+    UnknownColumns { columns: Vec<String> },
+
+    #[error("Column not found: {column}")]
+    ColumnNotFound { column: String },
+
+    #[error("Unexpected API response: {reason}")]
+    UnexpectedResponse { reason: String },
+
+    #[error("Fixture replay failed: {reason}")]
+    Fixture { reason: String },
+
+    #[error("No row with ID {id}")]
+    RowIdNotFound { id: String },
+
+    #[error("Cancelled after {rows_done} rows")]
+    Cancelled { rows_done: usize },
+
+    #[error("Permission denied: {details}")]
+    PermissionDenied { details: ApiErrorDetails },
+
+    #[error("Document not found: {document_id}: {details}")]
+    NotFound {
+        document_id: String,
+        details: ApiErrorDetails,
+    },
+
+    /// A 5xx response from the API, which is usually worth retrying
+    #[error("Server error: {details}")]
+    ServerError { details: ApiErrorDetails },
+
+    #[error("Quota exceeded: {details}")]
+    QuotaExceeded {
+        /// How long the API asked to wait before retrying, if it said
+        retry_after: Option<Duration>,
+        details: ApiErrorDetails,
+    },
+
+    #[error("Malformed A1 range: {range}")]
+    MalformedRange { range: String },
+
+    #[error("Invalid range: {details}")]
+    InvalidRange { details: ApiErrorDetails },
+
+    #[error("Invalid JSON on line {line}: {source}")]
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    /// Sheet row `row` (one-based) could not be deserialized; `column` is the header of the
+    /// offending cell, if known
+    #[error("Row {row}{}: {message}", .column.as_ref().map(|c| format!(", column {}", c)).unwrap_or_default())]
+    Serde {
+        row: usize,
+        column: Option<String>,
+        message: String,
+    },
+
+    #[error("Invalid record: {reason}")]
+    InvalidRecord { reason: String },
+
+    #[error("Header mismatch: expected {expected:?}, found {found:?}")]
+    HeaderMismatch {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+
+    /// Some chunks of a `write_page_chunked` failed; `written` are the ranges that were saved
+    #[error("{} of {} chunks failed to write", .failed.len(), .written.len() + .failed.len())]
+    ChunksFailed {
+        written: Vec<String>,
+        failed: Vec<(String, SheetsError)>,
+    },
+
+    /// The record type read from `tab` differs from the one last recorded for it in `_schema`
+    #[error("Schema drift in {tab}: {}", .changes.join(", "))]
+    SchemaDrift { tab: String, changes: Vec<String> },
+
+    #[error("Tab has more than {max_rows} rows")]
+    TooManyRows { max_rows: usize },
+
+    #[error("Tab has more than {max_cells} cells")]
+    TooManyCells { max_cells: usize },
+
+    #[error("Lock held by {holder} until {expires_at}")]
+    LockHeld { holder: String, expires_at: String },
+
+    /// The lease's cell was cleared or overwritten with something other than a claim
+    #[error("Lock in {cell} was lost")]
+    LockLost { cell: String },
+
+    #[error("Buffered appender stopped")]
+    AppenderStopped,
+
+    /// A write split across several requests failed part way; the first `rows_written` rows
+    /// were saved and the request starting at `range` failed with `source`
+    #[error("Write failed at {range} after {rows_written} rows: {source}")]
+    PartialWrite {
+        range: String,
+        rows_written: usize,
+        source: Box<SheetsError>,
+    },
+
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Arrow(#[from] arrow::error::ArrowError),
+
+    #[cfg(feature = "polars")]
+    #[error(transparent)]
+    DataFrame(#[from] polars::error::PolarsError),
+
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
 }
 
 /// Builds a `ServiceAccountKey` from JSON in environment variable `SERVICE_ACCOUNT_JSON`
@@ -39,8 +318,30 @@ pub fn service_account_from_env() -> Result<ServiceAccountKey, SheetsError> {
     Ok(key)
 }
 
+/// Builds a `ServiceAccountKey` from the JSON file at `path`, e.g. a mounted Kubernetes secret
+pub fn service_account_from_file(path: impl AsRef<Path>) -> Result<ServiceAccountKey, SheetsError> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path).map_err(|source| SheetsError::ServiceAccountFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    service_account_from_reader(std::io::BufReader::new(file))
+}
+
+/// Builds a `ServiceAccountKey` from JSON read from `reader`
+pub fn service_account_from_reader(
+    reader: impl std::io::Read,
+) -> Result<ServiceAccountKey, SheetsError> {
+    let key = serde_json::from_reader(reader)?;
+    Ok(key)
+}
+
 /// Given a `ServiceAccountKey`, builds a `google_sheets4::Sheets` client, with
-/// access token cache at `token_cache_path` (if specified)
+/// access token cache at `token_cache_path` (if specified).
+///
+/// Requests and responses are not gzip-compressed: `google_sheets4` sends them through a
+/// plain `hyper` client, which neither compresses bodies nor decodes compressed ones.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
 pub async fn get_sheets<P: Into<PathBuf>>(
     service_account: ServiceAccountKey,
     token_cache_path: Option<P>,
@@ -61,146 +362,1211 @@ pub async fn get_sheets<P: Into<PathBuf>>(
     Ok(sheets)
 }
 
+/// One-based number of a row in its tab, as shown in the Sheets UI
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct RowIndex(pub usize);
+
+impl RowIndex {
+    /// The whole row in tab `tab_name`, e.g. `'Tab'!17:17`
+    pub fn range(&self, tab_name: &str) -> String {
+        format!("{}!{}:{}", quote_tab_name(tab_name), self.0, self.0)
+    }
+}
+
+impl From<usize> for RowIndex {
+    fn from(row: usize) -> Self {
+        Self(row)
+    }
+}
+
+impl std::fmt::Display for RowIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Rows transferred so far by a long-running operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub rows_done: usize,
+    pub rows_total: usize,
+}
+
 /// Clear all data from the sheet called `tab_name` in document `document_id`
-pub async fn clear_tab(
-    sheets: &mut Sheets,
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn clear_tab<B: SheetsBackend + ?Sized>(
+    sheets: &B,
     document_id: &str,
     tab_name: &str,
 ) -> Result<(), SheetsError> {
-    sheets
-        .spreadsheets()
-        .values_clear(ClearValuesRequest::default(), document_id, tab_name)
-        .doit()
-        .await?;
-    Ok(())
+    sheets.clear_range(document_id, tab_name).await
+}
+
+/// Replace the data rows of tab `tab_name` in document `document_id` with `objects`, leaving
+/// the header row alone so its formatting, frozen panes, filters and column widths survive.
+///
+/// Each field is written under the header cell that names it, failing with
+/// `SheetsError::HeaderMismatch` if the header lacks one. A tab without a header is written
+/// like `write_page`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn replace_rows<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+) -> Result<WriteReceipt, SheetsError> {
+    replace_rows_with_options(
+        sheets,
+        document_id,
+        tab_name,
+        objects,
+        &WriteOptions::default(),
+    )
+    .await
+}
+
+/// Like `replace_rows`, serializing and writing cells as `options` say. The header row is
+/// always row 1 and is never cleared, so `clear`, `include_header`, `header_rows` and
+/// `skip_rows` only matter if the tab has no header and is written like `write_page`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects, options), fields(rows = objects.len()))
+)]
+pub async fn replace_rows_with_options<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+    options: &WriteOptions,
+) -> Result<WriteReceipt, SheetsError> {
+    let header = first_row(sheets, document_id, &RowIndex(1).range(tab_name)).await?;
+    if header.is_empty() {
+        return write_page_with_options(sheets, document_id, tab_name, objects, options).await;
+    }
+
+    let rows = align_to_header(&header, serialize_with(objects, true, options)?)?;
+
+    let data = RangeRef {
+        tab_name: tab_name.to_string(),
+        start: CellRef::new(1, 0),
+        end_row: None,
+        end_column: None,
+    };
+    sheets.clear_range(document_id, &data.to_string()).await?;
+    let receipt = WriteReceipt::new(tab_name, 1, 0, &rows);
+    if !rows.is_empty() {
+        let start = format!("{}!A2", quote_tab_name(tab_name));
+        match options.value_input {
+            ValueInput::UserEntered => sheets.write_values(document_id, &start, rows).await?,
+            ValueInput::Raw => sheets.write_raw_values(document_id, &start, rows).await?,
+        }
+    }
+    Ok(receipt)
+}
+
+/// The object on sheet row `row` of tab `tab_name`, deserialized against the header row, or
+/// `None` if the row is empty
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, row)))]
+pub async fn get_row<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    row: impl Into<RowIndex>,
+) -> Result<Option<T>, SheetsError> {
+    let options = ReadOptions::default().include_deleted(true);
+    get_row_with_options(sheets, document_id, tab_name, row, &options).await
+}
+
+/// Like `get_row`, deserializing with `options`; `None` too if the row is soft-deleted and
+/// `options` don't include deleted rows. The header is always row 1, so `header_rows`,
+/// `skip_rows` and the options for where the table ends don't apply.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, row, options)))]
+pub async fn get_row_with_options<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    row: impl Into<RowIndex>,
+    options: &ReadOptions,
+) -> Result<Option<T>, SheetsError> {
+    let row = data_row(tab_name, row.into())?;
+    let (header, cells) = futures::try_join!(
+        first_row(sheets, document_id, &RowIndex(1).range(tab_name)),
+        first_row(sheets, document_id, &row.range(tab_name)),
+    )?;
+    if cells.iter().all(|cell| cell.is_empty()) {
+        return Ok(None);
+    }
+
+    let mut decoder = de::RowDecoder::new::<T>(header, options)?;
+    decoder.try_decode(&cells, row.0)
+}
+
+/// The objects on sheet rows `rows` of tab `tab_name`, in the order given, `None` for empty
+/// rows. Runs of adjacent rows are fetched as one range, all in a single request, so rows
+/// found by `read_indexed` or a lookup can be reread without reading the whole tab.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn read_rows_at<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    rows: &[usize],
+) -> Result<Vec<Option<T>>, SheetsError> {
+    let options = ReadOptions::default().include_deleted(true);
+    read_rows_at_with_options(sheets, document_id, tab_name, rows, &options).await
+}
+
+/// Like `read_rows_at`, deserializing with `options` as `get_row_with_options` does
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, options)))]
+pub async fn read_rows_at_with_options<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    rows: &[usize],
+    options: &ReadOptions,
+) -> Result<Vec<Option<T>>, SheetsError> {
+    for &row in rows {
+        data_row(tab_name, RowIndex(row))?;
+    }
+    let mut sorted = rows.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    // first and last row of each run of consecutive rows
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for row in sorted {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == row => *end = row,
+            _ => runs.push((row, row)),
+        }
+    }
+
+    let tab = quote_tab_name(tab_name);
+    let ranges: Vec<String> = std::iter::once(RowIndex(1).range(tab_name))
+        .chain(
+            runs.iter()
+                .map(|(start, end)| format!("{}!{}:{}", tab, start, end)),
+        )
+        .collect();
+    let mut values = sheets.read_ranges(document_id, &ranges).await?.into_iter();
+    let header = values
+        .next()
+        .and_then(|rows| rows.into_iter().next())
+        .unwrap_or_default();
+    let blocks: Vec<Vec<Vec<String>>> = values.collect();
+    let mut decoder = de::RowDecoder::new::<T>(header, options)?;
+
+    rows.iter()
+        .map(|&row| {
+            let run = runs
+                .iter()
+                .position(|(start, end)| (*start..=*end).contains(&row))
+                .expect("every row is in a run");
+            let cells = blocks
+                .get(run)
+                .and_then(|block| block.get(row - runs[run].0))
+                .cloned()
+                .unwrap_or_default();
+            if cells.iter().all(|cell| cell.is_empty()) {
+                return Ok(None);
+            }
+            decoder.try_decode(&cells, row)
+        })
+        .collect()
+}
+
+/// Overwrite sheet row `row` of tab `tab_name` with `obj`, each field under its own header.
+/// Fails with `SheetsError::HeaderMismatch` if a field has no column.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, row, obj)))]
+pub async fn set_row<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    row: impl Into<RowIndex>,
+    obj: &impl serde::Serialize,
+) -> Result<WriteReceipt, SheetsError> {
+    let row = data_row(tab_name, row.into())?;
+    let header = first_row(sheets, document_id, &RowIndex(1).range(tab_name)).await?;
+    let rows = align_to_header(&header, serialize_rows(std::slice::from_ref(obj), true)?)?;
+
+    let receipt = WriteReceipt::new(tab_name, row.0 - 1, 0, &rows);
+    let start = format!("{}!A{}", quote_tab_name(tab_name), row);
+    sheets.write_values(document_id, &start, rows).await?;
+    Ok(receipt)
+}
+
+/// Overwrite the column headed `field_name` of tab `tab_name` with `value` of the record on
+/// each data row, leaving the other columns as they are, e.g. to refresh a computed score
+/// while people edit the rest. Blank and soft-deleted rows are left alone, and a row that
+/// doesn't deserialize fails the update before anything is written. Returns the number of
+/// cells written.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, value), fields(rows = tracing::field::Empty))
+)]
+pub async fn update_column<T, F, C, B>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    field_name: &str,
+    value: F,
+) -> Result<usize, SheetsError>
+where
+    T: DeserializeOwned,
+    F: Fn(&T) -> C,
+    C: IntoCell,
+    B: SheetsBackend + ?Sized,
+{
+    update_column_with_options(
+        sheets,
+        document_id,
+        tab_name,
+        field_name,
+        value,
+        &ReadOptions::default(),
+    )
+    .await
+}
+
+/// Like `update_column`, reading the tab with `options`, so rows they skip are left alone and
+/// `field_name` is matched to a column the way reads match fields, aliases included
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, value, options), fields(rows = tracing::field::Empty))
+)]
+pub async fn update_column_with_options<T, F, C, B>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    field_name: &str,
+    value: F,
+    options: &ReadOptions,
+) -> Result<usize, SheetsError>
+where
+    T: DeserializeOwned,
+    F: Fn(&T) -> C,
+    C: IntoCell,
+    B: SheetsBackend + ?Sized,
+{
+    let rows = read_table(sheets, document_id, tab_name, options).await?;
+    let (header, first_row, rows) = split_table::<T>(rows, options).unwrap_or_default();
+    let header_column = header.iter().position(|h| h == field_name);
+    let mut decoder = de::RowDecoder::new::<T>(header, options)?;
+    // the field, as matched the way reads match it, or else the header text itself
+    let column = decoder
+        .column(field_name)
+        .or(header_column)
+        .ok_or_else(|| SheetsError::ColumnNotFound {
+            column: field_name.to_string(),
+        })?;
+
+    // runs of adjacent rows with a record, as their first sheet row and new cells
+    let mut runs: Vec<(usize, Vec<Vec<String>>)> = vec![];
+    for (sheet_row, row) in (first_row..).zip(&rows) {
+        let record: T = match decoder.try_decode(row, sheet_row)? {
+            Some(record) => record,
+            None => continue,
+        };
+        let cell = vec![value(&record).into_cell()];
+        match runs.last_mut() {
+            Some((start, cells)) if *start + cells.len() == sheet_row => cells.push(cell),
+            _ => runs.push((sheet_row, vec![cell])),
+        }
+    }
+    let written = runs.iter().map(|(_, cells)| cells.len()).sum();
+    trace::record("rows", written as u64);
+    if written == 0 {
+        return Ok(0);
+    }
+
+    let letter = column_letter(column);
+    let data = runs
+        .into_iter()
+        .map(|(start, cells)| {
+            let end = start + cells.len() - 1;
+            let range = format!(
+                "{}!{}{}:{}{}",
+                quote_tab_name(tab_name),
+                letter,
+                start,
+                letter,
+                end
+            );
+            (range, cells)
+        })
+        .collect();
+    sheets.write_ranges(document_id, data).await?;
+    Ok(written)
+}
+
+/// `row`, if it is below the header row
+fn data_row(tab_name: &str, row: RowIndex) -> Result<RowIndex, SheetsError> {
+    if row.0 < 2 {
+        return Err(SheetsError::MalformedRange {
+            range: row.range(tab_name),
+        });
+    }
+    Ok(row)
+}
+
+/// The first row of `range`, empty if there is none
+async fn first_row<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    range: &str,
+) -> Result<Vec<String>, SheetsError> {
+    Ok(sheets
+        .read_values(document_id, range)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default())
+}
+
+/// Rows for `objects`, field names first if `include_header`, with the cell settings of
+/// `options`: trimming, decimal separator, header case and provenance columns
+fn serialize_with(
+    objects: &[impl serde::Serialize],
+    include_header: bool,
+    options: &WriteOptions,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let mut rows = ser::to_rows(
+        objects,
+        include_header,
+        options.decimal_separator,
+        options.value_input == ValueInput::UserEntered,
+    )?;
+    let has_header = rows.len() > objects.len();
+    trim_rows(&mut rows, options.csv.trim, has_header);
+    if has_header && !matches!(options.header_case, HeaderCase::AsIs) {
+        if let Some(header) = rows.first_mut() {
+            for cell in header.iter_mut() {
+                *cell = options.header_case.apply(cell);
+            }
+        }
+    }
+    if let Some(provenance) = &options.provenance {
+        add_provenance(&mut rows, provenance, has_header);
+    }
+    Ok(rows)
+}
+
+/// Serialized `rows`, field names first, rearranged so each field lands under its column of
+/// `header`
+fn align_to_header(
+    header: &[String],
+    rows: Vec<Vec<String>>,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let mut rows = rows.into_iter();
+    let fields = rows.next().unwrap_or_default();
+    let positions = fields
+        .iter()
+        .map(|field| header.iter().position(|h| h == field))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(|| SheetsError::HeaderMismatch {
+            expected: header.to_vec(),
+            found: fields.clone(),
+        })?;
+    Ok(rows
+        .map(|row| {
+            let mut cells = vec![String::new(); header.len()];
+            for (cell, &column) in row.into_iter().zip(&positions) {
+                cells[column] = cell;
+            }
+            cells
+        })
+        .collect())
 }
 
 /// Serialize a list of objects and write to the tab `tab_name` in document `document_id`.
-/// The sheet will be cleared before writing.
-pub async fn write_page(
-    sheets: &mut Sheets,
+/// The sheet will be cleared before writing. The receipt covers the header row too.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn write_page<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+) -> Result<WriteReceipt, SheetsError> {
+    write_page_with_options(
+        sheets,
+        document_id,
+        tab_name,
+        objects,
+        &WriteOptions::default(),
+    )
+    .await
+}
+
+/// Like `write_page`, serializing and writing as configured by `options`
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects, options), fields(rows = objects.len()))
+)]
+pub async fn write_page_with_options<B: SheetsBackend + ?Sized>(
+    sheets: &B,
     document_id: &str,
     tab_name: &str,
     objects: &[impl serde::Serialize],
+    options: &WriteOptions,
+) -> Result<WriteReceipt, SheetsError> {
+    let rows = serialize_with(objects, options.include_header, options)?;
+    // rows above the field names, e.g. a title banner or group headings, are kept
+    let kept_rows = options.skip_rows + options.header_rows.saturating_sub(1);
+    let range = if kept_rows == 0 {
+        tab_name.to_string()
+    } else {
+        format!("{}!A{}", quote_tab_name(tab_name), kept_rows + 1)
+    };
+    if options.clear {
+        if kept_rows == 0 {
+            clear_tab(sheets, document_id, tab_name).await?;
+        } else {
+            let below = RangeRef {
+                tab_name: tab_name.to_string(),
+                start: CellRef::new(kept_rows, 0),
+                end_row: None,
+                end_column: None,
+            };
+            sheets.clear_range(document_id, &below.to_string()).await?;
+        }
+    }
+
+    let receipt = WriteReceipt::new(tab_name, kept_rows, 0, &rows);
+    match options.value_input {
+        ValueInput::UserEntered => sheets.write_values(document_id, &range, rows).await?,
+        ValueInput::Raw => sheets.write_raw_values(document_id, &range, rows).await?,
+    }
+    Ok(receipt)
+}
+
+/// Append `provenance`'s columns to `rows`, right of the widest row so they line up, with
+/// their names on the header row if there is one
+fn add_provenance(rows: &mut [Vec<String>], provenance: &Provenance, has_header: bool) {
+    let columns = provenance.columns(&chrono::Utc::now().to_rfc3339());
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for (i, row) in rows.iter_mut().enumerate() {
+        row.resize(width, String::new());
+        if i == 0 && has_header {
+            row.extend(columns.iter().map(|(name, _)| name.to_string()));
+        } else {
+            row.extend(columns.iter().map(|(_, value)| value.clone()));
+        }
+    }
+}
+
+/// Like `write_page`, but writes `chunk_size` rows per request and calls `progress` after
+/// each one, starting with zero rows done.
+///
+/// Once `cancel` is cancelled, stops before the next chunk with `SheetsError::Cancelled`,
+/// leaving the rows written so far in the tab.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects, progress, cancel), fields(rows = objects.len()))
+)]
+pub async fn write_page_with_progress<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+    chunk_size: usize,
+    progress: impl Fn(Progress) + Send + Sync,
+    cancel: &CancellationToken,
 ) -> Result<(), SheetsError> {
+    let chunk_size = chunk_size.max(1);
+    let rows_total = objects.len();
+    let mut rows = serialize_rows(objects, true)?.into_iter();
+
     clear_tab(sheets, document_id, tab_name).await?;
+    progress(Progress {
+        rows_done: 0,
+        rows_total,
+    });
+
+    let mut written = 0;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(SheetsError::Cancelled {
+                rows_done: written.saturating_sub(1),
+            });
+        }
+
+        let chunk: Vec<Vec<String>> = rows.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
 
-    let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        let range = format!("{}!A{}", quote_tab_name(tab_name), written + 1);
+        written += chunk.len();
+        sheets.write_values(document_id, &range, chunk).await?;
 
-    for obj in objects {
-        wtr.serialize(&obj)?;
+        progress(Progress {
+            // the header is not a data row
+            rows_done: written - 1,
+            rows_total,
+        });
     }
 
-    let data = String::from_utf8(wtr.into_inner()?)?;
+    Ok(())
+}
 
-    let mut rdr = ReaderBuilder::new()
-        .has_headers(false)
-        .from_reader(data.as_bytes());
-    let records = rdr
-        .records()
-        .collect::<Result<Vec<StringRecord>, csv::Error>>()?;
+/// Like `write_page`, but writes `chunk_size` rows per request with up to `parallelism`
+/// requests in flight.
+///
+/// Chunks cover disjoint rows of the cleared tab, so the result is the same as writing them
+/// in order. Every chunk is attempted; if any fail, `SheetsError::ChunksFailed` lists the
+/// ranges written and the error for each range that wasn't.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn write_page_chunked<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+    chunk_size: usize,
+    parallelism: usize,
+) -> Result<(), SheetsError> {
+    let chunk_size = chunk_size.max(1);
+    let rows = serialize_rows(objects, true)?;
+    clear_tab(sheets, document_id, tab_name).await?;
 
-    let req = ValueRange {
-        major_dimension: None,
-        range: Some(tab_name.to_string()),
-        values: Some(
-            records
-                .into_iter()
-                .map(|s| s.iter().map(|s| s.to_string()).collect())
-                .collect(),
-        ),
-    };
+    let chunks = rows.chunks(chunk_size).enumerate().map(|(i, chunk)| {
+        let range = format!("{}!A{}", quote_tab_name(tab_name), i * chunk_size + 1);
+        async move {
+            let result = sheets
+                .write_values(document_id, &range, chunk.to_vec())
+                .await;
+            (range, result)
+        }
+    });
+    let outcomes: Vec<_> = stream::iter(chunks)
+        .buffered(parallelism.max(1))
+        .collect()
+        .await;
 
-    sheets
-        .spreadsheets()
-        .values_update(req, document_id, tab_name)
-        .value_input_option("USER_ENTERED")
-        .include_values_in_response(false)
-        .doit()
-        .await?;
+    let mut written = vec![];
+    let mut failed = vec![];
+    for (range, result) in outcomes {
+        match result {
+            Ok(()) => written.push(range),
+            Err(e) => failed.push((range, e)),
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(SheetsError::ChunksFailed { written, failed })
+    }
+}
 
-    Ok(())
+/// Append a single object `obj` to tab `tab_name` in document `document_id`, returning the
+/// row it landed in
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, obj)))]
+pub async fn append_row<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    obj: impl serde::Serialize,
+) -> Result<WriteReceipt, SheetsError> {
+    append_row_with_options(sheets, document_id, tab_name, obj, &WriteOptions::default()).await
 }
 
-/// Append a single object `obj` to tab `tab_name` in document `document_id`
-pub async fn append_row(
-    sheets: &mut Sheets,
+/// Like `append_row`, serializing the cells as `options` say, provenance columns included.
+/// Appended cells are always parsed as if typed in, so `value_input` only decides whether
+/// 128-bit integers are kept as text, and the header and clearing options don't apply.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, obj, options)))]
+pub async fn append_row_with_options<B: SheetsBackend + ?Sized>(
+    sheets: &B,
     document_id: &str,
     tab_name: &str,
     obj: impl serde::Serialize,
-) -> Result<(), SheetsError> {
-    let mut wtr = WriterBuilder::new().from_writer(vec![]);
+    options: &WriteOptions,
+) -> Result<WriteReceipt, SheetsError> {
+    sheets
+        .append_values(
+            document_id,
+            tab_name,
+            serialize_with(&[obj], false, options)?,
+        )
+        .await
+}
+
+/// Append `objects` to tab `tab_name` in document `document_id` in one request, returning the
+/// rows they landed in
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn append_rows<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+) -> Result<WriteReceipt, SheetsError> {
+    sheets
+        .append_values(document_id, tab_name, serialize_rows(objects, false)?)
+        .await
+}
 
-    wtr.serialize(&obj)?;
+/// Read all rows from tab `tab_name` in document `document_id` and deserialize them, failing
+/// with `SheetsError::Serde` for the first row that doesn't deserialize
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_all<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Vec<T>, SheetsError> {
+    let options = ReadOptions::default();
+    let rows = read_table(sheets, document_id, tab_name, &options).await?;
+    decode_rows(rows, &options)
+}
 
-    let data = String::from_utf8(wtr.into_inner()?)?;
+/// Like `read_all`, deserializing with `options`
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, options), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_all_with_options<T: DeserializeOwned + 'static, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    options: &ReadOptions,
+) -> Result<Vec<T>, SheetsError> {
+    Ok(
+        read_indexed_with_options(sheets, document_id, tab_name, options)
+            .await?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect(),
+    )
+}
 
-    let mut rdr = ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(data.as_bytes());
+/// Like `read_all`, but pairs every record with the row it was read from, so it can be
+/// updated or deleted later without matching it by content
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_indexed<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Vec<(RowIndex, T)>, SheetsError> {
+    let options = ReadOptions::default();
+    let rows = read_table(sheets, document_id, tab_name, &options).await?;
+    decode_indexed(rows, &options)
+}
 
-    let records = rdr
-        .records()
-        .collect::<Result<Vec<StringRecord>, csv::Error>>()?;
+/// Like `read_indexed`, deserializing with `options`
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, options), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_indexed_with_options<T: DeserializeOwned + 'static, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    options: &ReadOptions,
+) -> Result<Vec<(RowIndex, T)>, SheetsError> {
+    let rows = read_table(sheets, document_id, tab_name, options).await?;
+    let records = decode_indexed(rows, options)?;
+    // validators need `T: 'static` to find their record type, which `decode_indexed` doesn't
+    for (row, record) in &records {
+        for validator in &options.validators {
+            validator.check(record, row.0)?;
+        }
+    }
+    Ok(records)
+}
 
-    let req = ValueRange {
-        major_dimension: None,
-        range: Some(tab_name.to_string()),
-        values: Some(
-            records
-                .into_iter()
-                .map(|s| s.iter().map(|s| s.to_string()).collect())
-                .collect(),
-        ),
+/// Read `limit` data rows of tab `tab_name` starting `offset` rows below the header, for
+/// paginating a tab without downloading all of it. Only the header row and the page's rows
+/// are requested. Offsets count rows, so blank rows in the page yield no record.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_page<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<T>, SheetsError> {
+    read_page_with_options(
+        sheets,
+        document_id,
+        tab_name,
+        offset,
+        limit,
+        &ReadOptions::default(),
+    )
+    .await
+}
+
+/// Like `read_page`, deserializing with `options`. The header is always row 1, so
+/// `header_rows`, `skip_rows` and the options for where the table ends don't apply.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, options), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_page_with_options<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    offset: usize,
+    limit: usize,
+    options: &ReadOptions,
+) -> Result<Vec<T>, SheetsError> {
+    if limit == 0 {
+        return Ok(vec![]);
+    }
+    let tab = quote_tab_name(tab_name);
+    let header_range = format!("{}!1:1", tab);
+    let page_range = format!("{}!{}:{}", tab, offset + 2, offset + limit + 1);
+    let (header, rows) = futures::try_join!(
+        sheets.read_values(document_id, &header_range),
+        sheets.read_values(document_id, &page_range),
+    )?;
+    let header = match header.into_iter().next() {
+        Some(header) => header,
+        None => return Ok(vec![]),
     };
+    trace::record("rows", rows.len() as u64);
+    let mut decoder = de::RowDecoder::new::<T>(header, options)?;
+    decoder.decode_all(rows, offset + 2)
+}
 
-    sheets
-        .spreadsheets()
-        .values_append(req, document_id, tab_name)
-        .value_input_option("USER_ENTERED")
-        .include_values_in_response(false)
-        .doit()
-        .await?;
+/// Raw rows of the table `options` describe in tab `tab_name`, headers first
+async fn read_table<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    options: &ReadOptions,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let header_rows = options.header_row_count();
+    let read = |range: String| async move {
+        if options.formulas {
+            sheets.read_formulas(document_id, &range).await
+        } else {
+            sheets.read_values(document_id, &range).await
+        }
+    };
+    let rows = match options.max_rows {
+        // the headers, the allowed rows and one more to tell if there are too many
+        Some(max_rows) => {
+            let range = format!(
+                "{}!{}:{}",
+                quote_tab_name(tab_name),
+                options.skip_rows + 1,
+                options.skip_rows + max_rows + header_rows + 1
+            );
+            let rows = read(range).await?;
+            if rows.len() > max_rows + header_rows {
+                return Err(SheetsError::TooManyRows { max_rows });
+            }
+            rows
+        }
+        None if options.skip_rows > 0 => {
+            let range = RangeRef {
+                tab_name: tab_name.to_string(),
+                start: CellRef::new(options.skip_rows, 0),
+                end_row: None,
+                end_column: None,
+            };
+            read(range.to_string()).await?
+        }
+        None => read(tab_name.to_string()).await?,
+    };
+    if let Some(max_cells) = options.max_cells {
+        if rows.iter().map(Vec::len).sum::<usize>() > max_cells {
+            return Err(SheetsError::TooManyCells { max_cells });
+        }
+    }
+    trace::record("rows", rows.len() as u64);
+    Ok(rows)
+}
 
-    Ok(())
+/// Like `read_all`, but also returns rows soft-deleted by `delete_rows_where`
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_all_including_deleted<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Vec<T>, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    trace::record("rows", rows.len() as u64);
+    deserialize_records(rows)
+}
+
+/// Like `read_all`, but fetches the tab in ranges of `chunk_size` rows with up to
+/// `parallelism` requests in flight. Chunks are stitched back together in sheet order.
+pub async fn read_all_chunked<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    chunk_size: usize,
+    parallelism: usize,
+) -> Result<Vec<T>, SheetsError> {
+    read_all_chunked_with_progress(
+        sheets,
+        document_id,
+        tab_name,
+        chunk_size,
+        parallelism,
+        |_| {},
+        &CancellationToken::new(),
+    )
+    .await
 }
 
-/// Append a single object `obj` to tab `tab_name` in document `document_id`
-pub async fn read_all<T: DeserializeOwned>(
-    sheets: &mut Sheets,
+/// Like `read_all_chunked`, calling `progress` as each chunk arrives. Totals count the rows
+/// of the tab's grid, which may include empty rows below the data.
+///
+/// Once `cancel` is cancelled, no further chunks are requested and the read fails with
+/// `SheetsError::Cancelled`, counting the rows received so far.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, progress, cancel), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_all_chunked_with_progress<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
     document_id: &str,
     tab_name: &str,
+    chunk_size: usize,
+    parallelism: usize,
+    progress: impl Fn(Progress) + Send + Sync,
+    cancel: &CancellationToken,
 ) -> Result<Vec<T>, SheetsError> {
-    let (_body, value_range) = sheets
+    let rows_total = sheets.tab(document_id, tab_name).await?.row_count;
+
+    progress(Progress {
+        rows_done: 0,
+        rows_total,
+    });
+
+    // each chunk is deserialized as it arrives, so only one chunk of raw rows is held at once
+    let mut rows_done = 0;
+    let mut decoder: Option<de::RowDecoder> = None;
+    let mut records = vec![];
+    let chunks = stream::iter(chunk_ranges(rows_total, chunk_size).into_iter().map(
+        |(start, end)| async move {
+            if cancel.is_cancelled() {
+                return Err(SheetsError::Cancelled { rows_done: 0 });
+            }
+            let range = format!("{}!{}:{}", quote_tab_name(tab_name), start, end);
+            let mut rows = sheets.read_values(document_id, &range).await?;
+            // the API drops trailing empty rows, pad so later chunks keep their position
+            rows.resize(end - start + 1, vec![]);
+            Ok::<_, SheetsError>((start, rows))
+        },
+    ))
+    .buffered(parallelism.max(1));
+    futures::pin_mut!(chunks);
+
+    while let Some(chunk) = chunks.next().await {
+        let (mut first_row, rows) = match chunk {
+            Err(SheetsError::Cancelled { .. }) => return Err(SheetsError::Cancelled { rows_done }),
+            chunk => chunk?,
+        };
+        rows_done += rows.len();
+        progress(Progress {
+            rows_done,
+            rows_total,
+        });
+
+        let mut rows = rows.into_iter();
+        if decoder.is_none() {
+            match rows.next() {
+                Some(header) => {
+                    decoder = Some(de::RowDecoder::new::<T>(header, &ReadOptions::default())?);
+                    first_row += 1;
+                }
+                None => continue,
+            }
+        }
+        if let Some(decoder) = &mut decoder {
+            records.extend(decoder.decode_all(rows, first_row)?);
+        }
+    }
+    trace::record("rows", records.len() as u64);
+
+    Ok(records)
+}
+
+/// Locale of document `document_id`, such as `en_US` or `de_DE`, which decides how the sheet
+/// parses and displays numbers and dates; see `DecimalSeparator::for_locale`
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn spreadsheet_locale(sheets: &Sheets, document_id: &str) -> Result<String, SheetsError> {
+    let (_, spreadsheet) = sheets
         .spreadsheets()
-        .values_get(document_id, tab_name)
+        .get(document_id)
+        .param("fields", "properties.locale")
         .doit()
-        .await?;
+        .await
+        .map_err(|e| SheetsError::from_api(e, document_id))?;
+    Ok(spreadsheet
+        .properties
+        .and_then(|properties| properties.locale)
+        .unwrap_or_else(|| "en_US".to_string()))
+}
 
-    let rows = value_range.values.unwrap();
-    let mut wtr = WriterBuilder::new().from_writer(vec![]);
+/// Send `requests` to the `batchUpdate` endpoint of document `document_id`. Only the
+/// properties of added sheets are returned in the replies.
+async fn batch_update(
+    sheets: &Sheets,
+    document_id: &str,
+    requests: Vec<Request>,
+) -> Result<BatchUpdateSpreadsheetResponse, SheetsError> {
+    let req = BatchUpdateSpreadsheetRequest {
+        requests: Some(requests),
+        ..Default::default()
+    };
+    let (_body, response) = sheets
+        .spreadsheets()
+        .batch_update(req, document_id)
+        .param(
+            "fields",
+            "replies(addSheet.properties,duplicateSheet.properties,addChart.chart.chartId,deleteDuplicates.duplicatesRemovedCount)",
+        )
+        .doit()
+        .await
+        .map_err(|e| SheetsError::from_api(e, document_id))?;
+    Ok(response)
+}
+
+/// Split rows `1..=row_count` into inclusive `(start, end)` ranges of `chunk_size` rows
+fn chunk_ranges(row_count: usize, chunk_size: usize) -> Vec<(usize, usize)> {
+    let chunk_size = chunk_size.max(1);
+    (1..=row_count)
+        .step_by(chunk_size)
+        .map(|start| (start, (start + chunk_size - 1).min(row_count)))
+        .collect()
+}
+
+/// Serialize `objects` into raw rows, starting with a header row if `include_header`.
+///
+/// These are exactly the cells `write_page` and `append_row` send, so the output can be
+/// snapshot-tested without a network connection.
+pub fn serialize_rows<S: serde::Serialize>(
+    objects: &[S],
+    include_header: bool,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    ser::to_rows(objects, include_header, DecimalSeparator::Period, true)
+}
 
-    for row in rows {
-        if let Err(e) = wtr.write_record(&row) {
-            println!("error writing row- {:?}", e);
+/// Like `serialize_rows`, with the cells whitespace is trimmed from by `options.trim`. Cells
+/// are never put through CSV text, so commas, quotes and newlines in them are kept as they are.
+pub fn serialize_rows_with_options<S: serde::Serialize>(
+    objects: &[S],
+    include_header: bool,
+    options: &CsvOptions,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let mut rows = serialize_rows(objects, include_header)?;
+    let has_header = rows.len() > objects.len();
+    trim_rows(&mut rows, options.trim, has_header);
+    Ok(rows)
+}
+
+/// Trim whitespace from the header row, the other rows or both, as `trim` says
+fn trim_rows(rows: &mut [Vec<String>], trim: csv::Trim, has_header: bool) {
+    for (i, row) in rows.iter_mut().enumerate() {
+        let header = i == 0 && has_header;
+        let trimmed = match trim {
+            csv::Trim::Headers => header,
+            csv::Trim::Fields => !header,
+            csv::Trim::All => true,
+            _ => false,
         };
+        if trimmed {
+            for cell in row.iter_mut() {
+                if cell.trim().len() != cell.len() {
+                    *cell = cell.trim().to_string();
+                }
+            }
+        }
     }
+}
 
-    let data = String::from_utf8(wtr.into_inner()?)?;
+/// Deserialize raw rows, the first of which is the header, skipping soft-deleted rows
+fn deserialize_rows<T: DeserializeOwned>(rows: Vec<Vec<String>>) -> Result<Vec<T>, SheetsError> {
+    decode_rows(rows, &ReadOptions::default())
+}
+
+/// Deserialize raw rows, the first of which is the header
+fn deserialize_records<T: DeserializeOwned>(rows: Vec<Vec<String>>) -> Result<Vec<T>, SheetsError> {
+    decode_rows(rows, &ReadOptions::default().include_deleted(true))
+}
 
-    let mut rdr = ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(data.as_bytes());
+/// Deserialize raw rows, the first of which is the header, one row at a time
+fn decode_rows<T: DeserializeOwned>(
+    rows: Vec<Vec<String>>,
+    options: &ReadOptions,
+) -> Result<Vec<T>, SheetsError> {
+    Ok(decode_indexed(rows, options)?
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect())
+}
 
+/// Like `decode_rows`, pairing records with their rows' positions in the tab
+fn decode_indexed<T: DeserializeOwned>(
+    rows: Vec<Vec<String>>,
+    options: &ReadOptions,
+) -> Result<Vec<(RowIndex, T)>, SheetsError> {
+    let (header, first_row, rows) = match split_table::<T>(rows, options) {
+        Some(table) => table,
+        None => return Ok(vec![]),
+    };
+    let mut decoder = de::RowDecoder::new::<T>(header, options)?;
     let mut records = vec![];
-    for result in rdr.deserialize() {
-        match result {
-            Ok(r) => records.push(r),
-            Err(e) => {
-                println!("error deserializing row: {:?}", e);
-            }
+    for (row_number, row) in (first_row..).zip(&rows) {
+        if let Some(record) = decoder.try_decode(row, row_number)? {
+            records.push((RowIndex(row_number), record));
         }
     }
-
     Ok(records)
 }
+
+/// The header of the table in `rows` as read by `read_table`, the sheet row of its first
+/// data row and the data rows `options` keep, or `None` if it has no header
+fn split_table<T: DeserializeOwned>(
+    rows: Vec<Vec<String>>,
+    options: &ReadOptions,
+) -> Option<(Vec<String>, usize, Vec<Vec<String>>)> {
+    let mut rows = rows.into_iter();
+    let header = if options.headerless {
+        de::struct_fields::<T>()
+            .iter()
+            .map(|field| field.to_string())
+            .collect()
+    } else {
+        de::header_row(&mut rows, options)?
+    };
+
+    let mut rows: Vec<Vec<String>> = rows.collect();
+    rows.truncate(rows.len().saturating_sub(options.footer_rows));
+    if let Some(stop_when) = &options.stop_when {
+        if let Some(end) = rows.iter().position(|row| stop_when.matches(&header, row)) {
+            rows.truncate(end);
+        }
+    }
+    if options.stop_at_empty_row {
+        if let Some(end) = rows.iter().position(|row| de::is_blank(row)) {
+            rows.truncate(end);
+        }
+    }
+    let first_row = options.skip_rows + options.header_row_count() + 1;
+    Some((header, first_row, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Score {
+        id: u32,
+        #[allow(dead_code)]
+        score: String,
+    }
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn updates_the_column_below_skipped_rows() {
+        let sheets = MockSheets::new();
+        sheets.set_tab(
+            "doc",
+            "Scores",
+            vec![
+                strings(&["Weekly scores"]),
+                strings(&[]),
+                strings(&["id", "Score"]),
+                strings(&["1", "old"]),
+                strings(&["2", "old"]),
+            ],
+        );
+        let options = ReadOptions::default()
+            .skip_rows(2)
+            .header_matching(HeaderMatching::Normalized);
+
+        let written = update_column_with_options(
+            &sheets,
+            "doc",
+            "Scores",
+            "score",
+            |record: &Score| format!("{}0", record.id),
+            &options,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(
+            sheets.get_tab("doc", "Scores").unwrap(),
+            vec![
+                strings(&["Weekly scores"]),
+                strings(&[]),
+                strings(&["id", "Score"]),
+                strings(&["1", "10"]),
+                strings(&["2", "20"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn updates_a_column_by_its_header_text() {
+        let sheets = MockSheets::new();
+        sheets.set_tab(
+            "doc",
+            "Scores",
+            vec![strings(&["id", "Points"]), strings(&["1", "old"])],
+        );
+        let options = ReadOptions::default().header_alias("Points", "score");
+
+        for column in ["score", "Points"] {
+            update_column_with_options(
+                &sheets,
+                "doc",
+                "Scores",
+                column,
+                |record: &Score| record.id.to_string(),
+                &options,
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(
+            sheets.get_tab("doc", "Scores").unwrap()[1],
+            strings(&["1", "1"])
+        );
+
+        let missing = update_column_with_options(
+            &sheets,
+            "doc",
+            "Scores",
+            "rank",
+            |record: &Score| record.id.to_string(),
+            &options,
+        )
+        .await;
+        assert!(matches!(missing, Err(SheetsError::ColumnNotFound { .. })));
+    }
+}
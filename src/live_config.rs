@@ -0,0 +1,140 @@
+use crate::{rows::serde_from_row, SheetsBackend, SheetsError};
+use serde::de::DeserializeOwned;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{watch, Notify},
+    task::JoinHandle,
+};
+
+/// Deserialize a `T` from a tab of key-value pairs: each row holds a field name in column A
+/// and its value in column B. Rows with an empty key are skipped, and missing fields can be
+/// given `#[serde(default)]`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn read_key_values<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<T, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    let mut keys = vec![];
+    let mut values = vec![];
+    let mut key_rows = vec![];
+    for (i, mut row) in rows.into_iter().enumerate() {
+        row.resize(2, String::new());
+        let value = row.pop().unwrap_or_default();
+        let key = row.pop().unwrap_or_default();
+        if !key.trim().is_empty() {
+            keys.push(key.trim().to_string());
+            values.push(value);
+            key_rows.push(i + 1);
+        }
+    }
+
+    serde_from_row(&keys, &values).map_err(|error| match error {
+        // report the row the bad value is on
+        SheetsError::Serde {
+            column: Some(key),
+            message,
+            ..
+        } => SheetsError::Serde {
+            row: keys
+                .iter()
+                .position(|k| *k == key)
+                .map_or(0, |i| key_rows[i]),
+            column: Some(key),
+            message,
+        },
+        error => error,
+    })
+}
+
+/// The latest `T` read from a key-value tab with `read_key_values`, refreshed in the
+/// background every `interval` and whenever `notify` is called.
+///
+/// A refresh that fails or doesn't deserialize keeps the previous config. Subscribers are
+/// woken only when the config actually changes. Dropping the handle stops the refreshes.
+pub struct ConfigHandle<T> {
+    current: watch::Receiver<Arc<T>>,
+    wake: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl<T: DeserializeOwned + PartialEq + Send + Sync + 'static> ConfigHandle<T> {
+    /// Read the config from tab `tab_name` in document `document_id`, failing if the first
+    /// read does. Must be called within a Tokio runtime.
+    pub async fn new<B: SheetsBackend + ?Sized + 'static>(
+        sheets: Arc<B>,
+        document_id: impl Into<String>,
+        tab_name: impl Into<String>,
+        interval: Duration,
+    ) -> Result<Self, SheetsError> {
+        let document_id = document_id.into();
+        let tab_name = tab_name.into();
+        let initial = read_key_values(&*sheets, &document_id, &tab_name).await?;
+        let (sender, current) = watch::channel(Arc::new(initial));
+        let wake = Arc::new(Notify::new());
+        let task = tokio::spawn(run(
+            sheets,
+            document_id,
+            tab_name,
+            interval,
+            sender,
+            wake.clone(),
+        ));
+        Ok(Self {
+            current,
+            wake,
+            task,
+        })
+    }
+
+    /// The latest config
+    pub fn get(&self) -> Arc<T> {
+        self.current.borrow().clone()
+    }
+
+    /// A receiver that sees every change, e.g. with `changed().await`
+    pub fn subscribe(&self) -> watch::Receiver<Arc<T>> {
+        self.current.clone()
+    }
+
+    /// Refresh now rather than waiting for the interval, such as when a change notification
+    /// arrives for the document
+    pub fn notify(&self) {
+        self.wake.notify_one();
+    }
+}
+
+impl<T> Drop for ConfigHandle<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn run<T: DeserializeOwned + PartialEq, B: SheetsBackend + ?Sized>(
+    sheets: Arc<B>,
+    document_id: String,
+    tab_name: String,
+    interval: Duration,
+    sender: watch::Sender<Arc<T>>,
+    wake: Arc<Notify>,
+) {
+    loop {
+        let _ = tokio::time::timeout(interval, wake.notified()).await;
+        let config = match read_key_values::<T, B>(&*sheets, &document_id, &tab_name).await {
+            Ok(config) => config,
+            Err(_error) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_error, "config refresh failed, keeping previous");
+                continue;
+            }
+        };
+        sender.send_if_modified(|current| {
+            if **current == config {
+                return false;
+            }
+            *current = Arc::new(config);
+            true
+        });
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{SheetsBackend, SheetsError};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// How long to wait between writing a claim and reading it back, so a competing write that
+/// raced ours has landed
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// A best-effort lease on a single cell, for workers sharing a sheet that must not write at
+/// the same time, such as overlapping cron jobs.
+///
+/// The cell holds the holder's ID and when the lease expires, e.g.
+/// `3f2c… until 2026-10-14T12:00:00+00:00`. A lease is taken by writing a claim to a free or
+/// expired cell and reading it back after a short pause, so of two workers racing, only the
+/// one whose write landed last goes ahead. Sheets has no compare-and-swap, so this narrows
+/// rather than closes the window, and a holder that outlives its `ttl` without `renew`ing
+/// loses the lease.
+pub struct SheetLock<'a, B: ?Sized> {
+    sheets: &'a B,
+    document_id: String,
+    cell: String,
+    holder: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl<'a, B: SheetsBackend + ?Sized> SheetLock<'a, B> {
+    /// Take the lease in `cell`, e.g. `Locks!A1`, for `ttl`, failing with
+    /// `SheetsError::LockHeld` if another worker holds it
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+    pub async fn acquire(
+        sheets: &'a B,
+        document_id: &str,
+        cell: &str,
+        ttl: Duration,
+    ) -> Result<SheetLock<'a, B>, SheetsError> {
+        let mut lock = SheetLock {
+            sheets,
+            document_id: document_id.to_string(),
+            cell: cell.to_string(),
+            holder: uuid::Uuid::new_v4().to_string(),
+            expires_at: Utc::now(),
+        };
+        if let Some((holder, expires_at)) = lock.read().await? {
+            if expires_at > Utc::now() {
+                return Err(SheetsError::LockHeld {
+                    holder,
+                    expires_at: expires_at.to_rfc3339(),
+                });
+            }
+        }
+        lock.claim(ttl).await?;
+        Ok(lock)
+    }
+
+    /// The ID written to the cell while this lease is held
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// When the lease lapses unless renewed
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+
+    /// Extend the lease to `ttl` from now, failing with `SheetsError::LockHeld` or
+    /// `SheetsError::LockLost` if it was lost
+    pub async fn renew(&mut self, ttl: Duration) -> Result<(), SheetsError> {
+        self.check_held().await?;
+        self.claim(ttl).await
+    }
+
+    /// Give up the lease, clearing the cell if this lease still holds it
+    pub async fn release(self) -> Result<(), SheetsError> {
+        if self.check_held().await.is_ok() {
+            self.sheets
+                .clear_range(&self.document_id, &self.cell)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Write a claim expiring `ttl` from now, then read it back to check no one else's
+    /// replaced it
+    async fn claim(&mut self, ttl: Duration) -> Result<(), SheetsError> {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::weeks(5200));
+        let expires_at = Utc::now() + ttl;
+        let claim = format!("{} until {}", self.holder, expires_at.to_rfc3339());
+        self.sheets
+            .write_raw_values(&self.document_id, &self.cell, vec![vec![claim]])
+            .await?;
+        tokio::time::sleep(SETTLE).await;
+        self.expires_at = expires_at;
+        self.check_held().await
+    }
+
+    async fn check_held(&self) -> Result<(), SheetsError> {
+        match self.read().await? {
+            Some((holder, _)) if holder == self.holder => Ok(()),
+            Some((holder, expires_at)) => Err(SheetsError::LockHeld {
+                holder,
+                expires_at: expires_at.to_rfc3339(),
+            }),
+            None => Err(SheetsError::LockLost {
+                cell: self.cell.clone(),
+            }),
+        }
+    }
+
+    /// The holder and expiry in the cell, if it holds a claim
+    async fn read(&self) -> Result<Option<(String, DateTime<Utc>)>, SheetsError> {
+        let rows = self
+            .sheets
+            .read_values(&self.document_id, &self.cell)
+            .await?;
+        let cell = rows
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next())
+            .unwrap_or_default();
+        Ok(cell.split_once(" until ").and_then(|(holder, expires_at)| {
+            let expires_at = DateTime::parse_from_rfc3339(expires_at).ok()?;
+            Some((holder.to_string(), expires_at.with_timezone(&Utc)))
+        }))
+    }
+}
@@ -0,0 +1,94 @@
+//! Reading and writing plain matrices of cells, without serde, for data that is already
+//! tabular such as `Vec<Vec<String>>` or `Vec<Vec<f64>>`.
+
+use crate::{clear_tab, SheetsBackend, SheetsError};
+
+/// A value written as the text of one cell
+pub trait IntoCell {
+    fn into_cell(self) -> String;
+}
+
+impl IntoCell for String {
+    fn into_cell(self) -> String {
+        self
+    }
+}
+
+impl IntoCell for &str {
+    fn into_cell(self) -> String {
+        self.to_string()
+    }
+}
+
+impl IntoCell for f64 {
+    /// Formatted like serialized fields, e.g. `1.0`. `NaN` is an empty cell.
+    fn into_cell(self) -> String {
+        if self.is_nan() {
+            String::new()
+        } else {
+            ryu::Buffer::new().format(self).to_string()
+        }
+    }
+}
+
+impl IntoCell for i64 {
+    fn into_cell(self) -> String {
+        self.to_string()
+    }
+}
+
+/// Write `rows` as they are to tab `tab_name` in document `document_id`. The sheet will be
+/// cleared before writing. Rows of `String` are sent without copying.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, rows), fields(rows = rows.len()))
+)]
+pub async fn write_matrix<C: IntoCell, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    rows: Vec<Vec<C>>,
+) -> Result<(), SheetsError> {
+    let rows = rows
+        .into_iter()
+        .map(|row| row.into_iter().map(IntoCell::into_cell).collect())
+        .collect();
+    clear_tab(sheets, document_id, tab_name).await?;
+    sheets.write_values(document_id, tab_name, rows).await
+}
+
+/// Every cell of tab `tab_name` in document `document_id`, including the header
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn read_matrix<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    sheets.read_values(document_id, tab_name).await
+}
+
+/// Every cell of tab `tab_name` in document `document_id` as a number. Empty cells are `NaN`;
+/// any other non-numeric cell is an error, so there must be no header.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn read_f64_matrix<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Vec<Vec<f64>>, SheetsError> {
+    sheets
+        .read_values(document_id, tab_name)
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .map(|cell| match cell.trim() {
+                    "" => Ok(f64::NAN),
+                    cell => cell.parse().map_err(|_| SheetsError::InvalidRecord {
+                        reason: format!("row {}: {:?} is not a number", i + 1, cell),
+                    }),
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,260 @@
+use crate::{SheetsBackend, SheetsError, TabInfo, WriteReceipt};
+use async_trait::async_trait;
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+/// A backend call about to be made, as seen by `Middleware`
+#[derive(Debug, Clone, Copy)]
+pub struct ApiRequest<'a> {
+    /// Backend operation, e.g. `read_values`
+    pub operation: &'static str,
+    pub document_id: &'a str,
+    /// Range or tab name the call targets, if any
+    pub target: Option<&'a str>,
+    /// Rows of values sent with the call
+    pub rows: usize,
+}
+
+/// Hooks run around every call made through `Intercepted`.
+///
+/// Both hooks are async, so they can also inject latency. Headers and request signing need
+/// access to the HTTP request itself; do those in the `hyper` connector passed to `Sheets::new`.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the call is sent. Returning an error aborts the call with that error.
+    async fn before(&self, _request: &ApiRequest<'_>) -> Result<(), SheetsError> {
+        Ok(())
+    }
+
+    /// Called after the call completes, or after `before` aborted it
+    async fn after(
+        &self,
+        _request: &ApiRequest<'_>,
+        _latency: Duration,
+        _error: Option<&SheetsError>,
+    ) {
+    }
+}
+
+/// Backend wrapper running a stack of `Middleware` around every call.
+///
+/// `before` hooks run in the order the middleware was added, `after` hooks in reverse.
+pub struct Intercepted<B> {
+    inner: B,
+    middleware: Vec<Box<dyn Middleware>>,
+}
+
+impl<B: SheetsBackend> Intercepted<B> {
+    /// Wrap `inner` with no middleware
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            middleware: vec![],
+        }
+    }
+
+    /// Add `middleware` to the end of the stack
+    pub fn with(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// The wrapped backend
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    async fn call<T, F>(&self, request: ApiRequest<'_>, call: F) -> Result<T, SheetsError>
+    where
+        F: Future<Output = Result<T, SheetsError>> + Send,
+    {
+        let started = Instant::now();
+        let mut entered = 0;
+        let mut result = Ok(());
+        for middleware in &self.middleware {
+            entered += 1;
+            result = middleware.before(&request).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        let result = match result {
+            Ok(()) => call.await,
+            Err(e) => Err(e),
+        };
+
+        let latency = started.elapsed();
+        for middleware in self.middleware[..entered].iter().rev() {
+            middleware
+                .after(&request, latency, result.as_ref().err())
+                .await;
+        }
+        result
+    }
+}
+
+fn request<'a>(
+    operation: &'static str,
+    document_id: &'a str,
+    target: Option<&'a str>,
+    rows: usize,
+) -> ApiRequest<'a> {
+    ApiRequest {
+        operation,
+        document_id,
+        target,
+        rows,
+    }
+}
+
+#[async_trait]
+impl<B: SheetsBackend> SheetsBackend for Intercepted<B> {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.call(
+            request("read_values", document_id, Some(range), 0),
+            self.inner.read_values(document_id, range),
+        )
+        .await
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.call(
+            request("read_values", document_id, Some(range), 0),
+            self.inner.read_formulas(document_id, range),
+        )
+        .await
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        self.call(
+            request("read_ranges", document_id, None, 0),
+            self.inner.read_ranges(document_id, ranges),
+        )
+        .await
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.call(
+            request("write_values", document_id, Some(range), rows.len()),
+            self.inner.write_values(document_id, range, rows),
+        )
+        .await
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.call(
+            request("write_values", document_id, Some(range), rows.len()),
+            self.inner.write_raw_values(document_id, range, rows),
+        )
+        .await
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        let rows = data.iter().map(|(_, rows)| rows.len()).sum();
+        self.call(
+            request("write_ranges", document_id, None, rows),
+            self.inner.write_ranges(document_id, data),
+        )
+        .await
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.call(
+            request("append_values", document_id, Some(range), rows.len()),
+            self.inner.append_values(document_id, range, rows),
+        )
+        .await
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        self.call(
+            request("clear_range", document_id, Some(range), 0),
+            self.inner.clear_range(document_id, range),
+        )
+        .await
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        self.call(
+            request("delete_rows", document_id, Some(tab_name), 0),
+            self.inner.delete_rows(document_id, tab_name, start, end),
+        )
+        .await
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        self.call(
+            request("list_tabs", document_id, None, 0),
+            self.inner.list_tabs(document_id),
+        )
+        .await
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        self.call(
+            request("add_tab", document_id, Some(tab_name), 0),
+            self.inner.add_tab(document_id, tab_name),
+        )
+        .await
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        self.call(
+            request("delete_tab", document_id, Some(tab_name), 0),
+            self.inner.delete_tab(document_id, tab_name),
+        )
+        .await
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        self.call(
+            request("duplicate_tab", document_id, Some(tab_name), 0),
+            self.inner.duplicate_tab(document_id, tab_name, new_name),
+        )
+        .await
+    }
+}
@@ -0,0 +1,436 @@
+use crate::{
+    grid::{clear_span, read_span, tab_and_span, trim_grid, write_at},
+    ApiErrorDetails, SheetsBackend, SheetsError, TabInfo, WriteReceipt,
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+type ErrorFactory = Arc<dyn Fn() -> SheetsError + Send + Sync>;
+
+/// In-memory `SheetsBackend` for unit tests.
+///
+/// Tabs are stored as `HashMap<(document_id, tab_name), Vec<Vec<String>>>` and are created on
+/// first write. Latency, quota exhaustion and arbitrary errors can be injected to exercise
+/// application error handling without credentials or network.
+#[derive(Default)]
+pub struct MockSheets {
+    state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    tabs: HashMap<(String, String), Vec<Vec<String>>>,
+    /// `(document_id, tab_name, sheet_id)` in creation order
+    tab_order: Vec<(String, String, i32)>,
+    next_sheet_id: i32,
+    latency: Duration,
+    quota: Option<usize>,
+    calls: usize,
+    failures: Vec<(usize, ErrorFactory)>,
+}
+
+impl MockSheets {
+    /// An empty mock with no latency, quota or injected errors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the contents of `tab_name` in `document_id` with `rows`, creating it if needed
+    pub fn set_tab(&self, document_id: &str, tab_name: &str, rows: Vec<Vec<String>>) {
+        let mut state = self.state.lock().unwrap();
+        state.ensure_tab(document_id, tab_name);
+        state
+            .tabs
+            .insert((document_id.to_string(), tab_name.to_string()), rows);
+    }
+
+    /// Current contents of `tab_name` in `document_id`, if it exists
+    pub fn get_tab(&self, document_id: &str, tab_name: &str) -> Option<Vec<Vec<String>>> {
+        self.state
+            .lock()
+            .unwrap()
+            .tabs
+            .get(&(document_id.to_string(), tab_name.to_string()))
+            .cloned()
+    }
+
+    /// Delay every call by `latency`
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Allow `calls` more calls before every call fails with a quota error, `None` for no limit
+    pub fn set_quota(&self, calls: Option<usize>) {
+        self.state.lock().unwrap().quota = calls;
+    }
+
+    /// Fail the next `count` calls with the error built by `error`
+    pub fn fail_next(&self, count: usize, error: impl Fn() -> SheetsError + Send + Sync + 'static) {
+        if count == 0 {
+            return;
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .push((count, Arc::new(error)));
+    }
+
+    /// Number of calls made so far, including failed ones
+    pub fn calls(&self) -> usize {
+        self.state.lock().unwrap().calls
+    }
+
+    /// The error returned when the API cannot be reached
+    pub fn unreachable_error() -> SheetsError {
+        SheetsError::Network(google_sheets4::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "mock backend unreachable",
+        )))
+    }
+
+    /// The error returned when the read or write quota is exhausted
+    pub fn quota_error() -> SheetsError {
+        SheetsError::QuotaExceeded {
+            retry_after: None,
+            details: ApiErrorDetails {
+                status: 429,
+                reason: Some("RESOURCE_EXHAUSTED".to_string()),
+                message: "Quota exceeded (mock)".to_string(),
+            },
+        }
+    }
+
+    /// The error returned for adding a tab whose name is taken, which has no typed variant
+    pub fn duplicate_tab_error(tab_name: &str) -> SheetsError {
+        SheetsError::duplicate_tab(tab_name)
+    }
+
+    /// Count the call, sleep for the configured latency and return any injected error
+    async fn begin_call(&self) -> Result<(), SheetsError> {
+        let (latency, injected) = {
+            let mut state = self.state.lock().unwrap();
+            state.calls += 1;
+
+            let injected = match state.quota.as_mut() {
+                Some(0) => Some(Self::quota_error()),
+                Some(remaining) => {
+                    *remaining -= 1;
+                    None
+                }
+                None => None,
+            };
+            let injected = injected.or_else(|| {
+                let (count, error) = state.failures.first_mut()?;
+                let error = error();
+                *count -= 1;
+                if *count == 0 {
+                    state.failures.remove(0);
+                }
+                Some(error)
+            });
+
+            (state.latency, injected)
+        };
+
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
+        match injected {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl MockState {
+    fn ensure_tab(&mut self, document_id: &str, tab_name: &str) -> &mut Vec<Vec<String>> {
+        let key = (document_id.to_string(), tab_name.to_string());
+        if !self.tabs.contains_key(&key) {
+            self.next_sheet_id += 1;
+            self.tab_order
+                .push((key.0.clone(), key.1.clone(), self.next_sheet_id));
+        }
+        self.tabs.entry(key).or_default()
+    }
+
+    fn tab_mut(
+        &mut self,
+        document_id: &str,
+        tab_name: &str,
+    ) -> Result<&mut Vec<Vec<String>>, SheetsError> {
+        let available = self
+            .tab_order
+            .iter()
+            .filter(|(doc, _, _)| doc == document_id)
+            .map(|(_, tab, _)| tab.clone())
+            .collect();
+        self.tabs
+            .get_mut(&(document_id.to_string(), tab_name.to_string()))
+            .ok_or_else(|| SheetsError::tab_not_found(tab_name, available))
+    }
+}
+
+#[async_trait]
+impl SheetsBackend for MockSheets {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.begin_call().await?;
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut state = self.state.lock().unwrap();
+        let grid = state.tab_mut(document_id, &tab_name)?;
+        Ok(read_span(grid, &span))
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.begin_call().await?;
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut state = self.state.lock().unwrap();
+        let grid = state.ensure_tab(document_id, &tab_name);
+        write_at(grid, span.start_row, span.start_column, rows);
+        Ok(())
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.begin_call().await?;
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut state = self.state.lock().unwrap();
+        let grid = state.ensure_tab(document_id, &tab_name);
+        let next_row = trim_grid(grid.clone()).len().max(span.start_row);
+        let receipt = WriteReceipt::new(&tab_name, next_row, span.start_column, &rows);
+        write_at(grid, next_row, span.start_column, rows);
+        Ok(receipt)
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        self.begin_call().await?;
+        let (tab_name, span) = tab_and_span(range)?;
+        let mut state = self.state.lock().unwrap();
+        let grid = state.tab_mut(document_id, &tab_name)?;
+        clear_span(grid, &span);
+        Ok(())
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        self.begin_call().await?;
+        let mut state = self.state.lock().unwrap();
+        let grid = state.tab_mut(document_id, tab_name)?;
+        let end = end.min(grid.len());
+        if start < end {
+            grid.drain(start..end);
+        }
+        Ok(())
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        self.begin_call().await?;
+        let state = self.state.lock().unwrap();
+
+        Ok(state
+            .tab_order
+            .iter()
+            .filter(|(document, _, _)| document == document_id)
+            .map(|(document, title, sheet_id)| {
+                let grid = &state.tabs[&(document.clone(), title.clone())];
+                TabInfo {
+                    sheet_id: *sheet_id,
+                    title: title.clone(),
+                    row_count: grid.len(),
+                    column_count: grid.iter().map(Vec::len).max().unwrap_or(0),
+                }
+            })
+            .collect())
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        self.begin_call().await?;
+        let mut state = self.state.lock().unwrap();
+        let key = (document_id.to_string(), tab_name.to_string());
+        if state.tabs.contains_key(&key) {
+            return Err(Self::duplicate_tab_error(tab_name));
+        }
+        state.ensure_tab(document_id, tab_name);
+        let sheet_id = state.next_sheet_id;
+
+        Ok(TabInfo {
+            sheet_id,
+            title: tab_name.to_string(),
+            row_count: 0,
+            column_count: 0,
+        })
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        self.begin_call().await?;
+        let mut state = self.state.lock().unwrap();
+        state.tab_mut(document_id, tab_name)?;
+        state
+            .tabs
+            .remove(&(document_id.to_string(), tab_name.to_string()));
+        state
+            .tab_order
+            .retain(|(document, title, _)| !(document == document_id && title == tab_name));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn appends_below_the_last_row() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "Tab", vec![strings(&["id"]), strings(&["1"])]);
+
+        let receipt = sheets
+            .append_values("doc", "Tab", vec![strings(&["2"]), strings(&["3"])])
+            .await
+            .unwrap();
+        assert_eq!(receipt.first_row_index, 3);
+        assert_eq!(receipt.updated_rows, 2);
+        assert_eq!(receipt.updated_range, "'Tab'!A3:A4");
+        assert_eq!(
+            sheets.read_values("doc", "Tab").await.unwrap(),
+            vec![
+                strings(&["id"]),
+                strings(&["1"]),
+                strings(&["2"]),
+                strings(&["3"])
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn clears_cells_and_trims_what_is_left() {
+        let sheets = MockSheets::new();
+        sheets.set_tab(
+            "doc",
+            "Tab",
+            vec![strings(&["a", "b"]), strings(&["c", "d"])],
+        );
+
+        sheets.clear_range("doc", "Tab!B1:B2").await.unwrap();
+        assert_eq!(
+            sheets.read_values("doc", "Tab").await.unwrap(),
+            vec![strings(&["a"]), strings(&["c"])]
+        );
+        sheets.clear_range("doc", "Tab").await.unwrap();
+        assert!(sheets.read_values("doc", "Tab").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn deletes_rows_and_shifts_later_ones_up() {
+        let sheets = MockSheets::new();
+        let rows: Vec<Vec<String>> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|c| strings(&[*c]))
+            .collect();
+        sheets.set_tab("doc", "Tab", rows);
+
+        sheets.delete_rows("doc", "Tab", 1, 3).await.unwrap();
+        assert_eq!(
+            sheets.get_tab("doc", "Tab").unwrap(),
+            vec![strings(&["a"]), strings(&["d"])]
+        );
+        // past the end is ignored, as in the API
+        sheets.delete_rows("doc", "Tab", 5, 9).await.unwrap();
+        assert_eq!(sheets.get_tab("doc", "Tab").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_duplicate_tab() {
+        let sheets = MockSheets::new();
+        let first = sheets.add_tab("doc", "Tab").await.unwrap();
+        let second = sheets.add_tab("doc", "Other").await.unwrap();
+        assert_ne!(first.sheet_id, second.sheet_id);
+
+        match sheets.add_tab("doc", "Tab").await {
+            Err(SheetsError::SheetsError(google_sheets4::Error::BadRequest(body))) => {
+                assert_eq!(body["error"]["status"], "INVALID_ARGUMENT");
+            }
+            other => panic!("expected a bad request, got {:?}", other),
+        }
+        // the same name in another document is a different tab
+        sheets.add_tab("other-doc", "Tab").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_missing_tabs_with_a_suggestion() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "Orders", vec![strings(&["id"])]);
+
+        match sheets.read_values("doc", "Ordrs").await {
+            Err(SheetsError::TabNotFound {
+                requested,
+                available,
+                suggestion,
+            }) => {
+                assert_eq!(requested, "Ordrs");
+                assert_eq!(available, vec!["Orders".to_string()]);
+                assert_eq!(suggestion.as_deref(), Some("Orders"));
+            }
+            other => panic!("expected TabNotFound, got {:?}", other),
+        }
+        assert!(matches!(
+            sheets.delete_rows("doc", "Missing", 0, 1).await,
+            Err(SheetsError::TabNotFound { .. })
+        ));
+        assert!(matches!(
+            sheets.delete_tab("doc", "Missing").await,
+            Err(SheetsError::TabNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn injects_failures_and_counts_calls() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "Tab", vec![]);
+        sheets.fail_next(0, MockSheets::unreachable_error);
+        sheets.fail_next(2, MockSheets::unreachable_error);
+
+        for _ in 0..2 {
+            assert!(matches!(
+                sheets.read_values("doc", "Tab").await,
+                Err(SheetsError::Network(_))
+            ));
+        }
+        sheets.read_values("doc", "Tab").await.unwrap();
+
+        sheets.set_quota(Some(1));
+        sheets.read_values("doc", "Tab").await.unwrap();
+        assert!(matches!(
+            sheets.read_values("doc", "Tab").await,
+            Err(SheetsError::QuotaExceeded { .. })
+        ));
+        assert_eq!(sheets.calls(), 5);
+    }
+}
@@ -0,0 +1,474 @@
+use crate::{
+    api_error::{classify, parse_retry_after},
+    column_letter,
+    grid::{read_span, tab_and_span, trim_grid, write_at, Span},
+    ApiErrorDetails, SheetsBackend, SheetsError, TabInfo, WriteReceipt,
+};
+use async_trait::async_trait;
+use hyper::{body, client::HttpConnector, header, Body, Client, Method, Request};
+use hyper_rustls::HttpsConnector;
+use serde_json::{json, Value};
+
+/// Drive items of the signed-in user, the default for `GraphWorkbook`
+pub const DEFAULT_BASE_URL: &str = "https://graph.microsoft.com/v1.0/me/drive/items";
+
+/// Excel limits every worksheet to this many rows
+const MAX_ROWS: usize = 1_048_576;
+
+/// Excel limits every worksheet to this many columns
+const MAX_COLUMNS: usize = 16_384;
+
+/// Supplies Microsoft Graph access tokens to `GraphWorkbook`
+#[async_trait]
+pub trait GraphToken: Send + Sync {
+    /// A token with `Files.ReadWrite` access to the workbooks
+    async fn access_token(&self) -> Result<String, SheetsError>;
+}
+
+#[async_trait]
+impl GraphToken for String {
+    async fn access_token(&self) -> Result<String, SheetsError> {
+        Ok(self.clone())
+    }
+}
+
+/// `SheetsBackend` for Excel Online workbooks, through the Microsoft Graph workbook API.
+///
+/// Document IDs are drive item IDs of `.xlsx` files and tabs are worksheets. Cells are read
+/// as their displayed text, like Google Sheets' formatted values. Graph worksheet IDs are not
+/// numeric, so `TabInfo::sheet_id` is a hash of the worksheet's ID: it stays the same when
+/// the worksheet is renamed or moved, but is not a number Excel shows anywhere.
+pub struct GraphWorkbook {
+    client: Client<HttpsConnector<HttpConnector>>,
+    base_url: String,
+    token: Box<dyn GraphToken>,
+}
+
+impl GraphWorkbook {
+    /// Access workbooks in the signed-in user's OneDrive with tokens from `token`
+    pub fn new(token: impl GraphToken + 'static) -> Self {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .https_only()
+            .enable_http1()
+            .enable_http2()
+            .build();
+        Self {
+            client: Client::builder().build(connector),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            token: Box::new(token),
+        }
+    }
+
+    /// Resolve document IDs against another drive, e.g.
+    /// `https://graph.microsoft.com/v1.0/drives/{drive-id}/items`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Send a request to `path` under the workbook of document `document_id`. `tab_name` is
+    /// the worksheet the request targets, so a 404 for it can be reported as `TabNotFound`.
+    async fn request(
+        &self,
+        method: Method,
+        document_id: &str,
+        tab_name: Option<&str>,
+        path: &str,
+        payload: Option<Value>,
+    ) -> Result<Value, SheetsError> {
+        let error = match self.send(method, document_id, path, payload).await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        match (tab_name, &error) {
+            (Some(tab_name), SheetsError::NotFound { details, .. })
+                if details
+                    .reason
+                    .as_deref()
+                    .map_or(false, |code| code.eq_ignore_ascii_case("ItemNotFound")) =>
+            {
+                Err(self.check_tab(document_id, tab_name, error).await)
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// `error`, a 404 from a request on worksheet `tab_name`, replaced by
+    /// `SheetsError::TabNotFound` if the workbook is there but has no such worksheet. Graph
+    /// answers a missing workbook and a missing worksheet alike, so the worksheets are listed
+    /// to tell them apart.
+    async fn check_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        error: SheetsError,
+    ) -> SheetsError {
+        let worksheets = match self
+            .send(Method::GET, document_id, "worksheets", None)
+            .await
+        {
+            Ok(worksheets) => worksheets,
+            // most likely the workbook itself is missing
+            Err(lookup) => return lookup,
+        };
+        let titles: Vec<String> = worksheets
+            .get("value")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|worksheet| tab_info(worksheet).title)
+            .collect();
+        // worksheet names are case-insensitive in Excel
+        let tab_name_lower = tab_name.to_lowercase();
+        if titles
+            .iter()
+            .any(|title| title.to_lowercase() == tab_name_lower)
+        {
+            error
+        } else {
+            SheetsError::tab_not_found(tab_name, titles)
+        }
+    }
+
+    /// Send a request to `path` under the workbook of document `document_id`, classifying
+    /// errors without regard to the worksheet
+    async fn send(
+        &self,
+        method: Method,
+        document_id: &str,
+        path: &str,
+        payload: Option<Value>,
+    ) -> Result<Value, SheetsError> {
+        let url = format!(
+            "{}/{}/workbook/{}",
+            self.base_url,
+            encode(document_id),
+            path
+        );
+        let token = self.token.access_token().await?;
+        let request = Request::builder()
+            .method(method)
+            .uri(url)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(match payload {
+                Some(payload) => Body::from(payload.to_string()),
+                None => Body::empty(),
+            })
+            .map_err(unexpected)?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| SheetsError::Network(google_sheets4::Error::HttpError(e)))?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| parse_retry_after(value.to_str().ok()?));
+        let bytes = body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| SheetsError::Network(google_sheets4::Error::HttpError(e)))?;
+        let value = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).map_err(unexpected)?
+        };
+
+        if status.is_success() {
+            return Ok(value);
+        }
+
+        // Graph errors look like {"error": {"code": "ItemNotFound", "message": ..}}
+        let error = value.get("error");
+        let details = ApiErrorDetails {
+            status: status.as_u16(),
+            reason: error
+                .and_then(|e| e.get("code")?.as_str())
+                .map(str::to_string),
+            message: error
+                .and_then(|e| e.get("message")?.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        };
+        Err(
+            classify(details, retry_after, document_id).unwrap_or_else(|details| {
+                SheetsError::UnexpectedResponse {
+                    reason: details.to_string(),
+                }
+            }),
+        )
+    }
+
+    /// All cell text of worksheet `tab_name`, positioned from `A1`
+    async fn used_grid(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let path = format!(
+            "worksheets/{}/usedRange(valuesOnly=true)?$select=address,text",
+            encode(tab_name)
+        );
+        let used = self
+            .request(Method::GET, document_id, Some(tab_name), &path, None)
+            .await?;
+
+        let text = used.get("text").cloned().unwrap_or_else(|| json!([]));
+        let text: Vec<Vec<String>> = serde_json::from_value(text).map_err(unexpected)?;
+        let address = used
+            .get("address")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let (_, origin) = tab_and_span(address).map_err(unexpected)?;
+        let mut grid = vec![];
+        write_at(&mut grid, origin.start_row, origin.start_column, text);
+        Ok(trim_grid(grid))
+    }
+
+    /// Write `rows` to worksheet `tab_name` starting at zero-based `(row, column)`
+    async fn write_cells(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        row: usize,
+        column: usize,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if rows.is_empty() || width == 0 {
+            return Ok(());
+        }
+        let span = Span {
+            start_row: row,
+            start_column: column,
+            end_row: Some(row + rows.len() - 1),
+            end_column: Some(column + width - 1),
+        };
+        // Graph wants a rectangle; null leaves a cell unchanged, like a short row in Sheets
+        let values: Vec<Vec<Value>> = rows
+            .into_iter()
+            .map(|row| {
+                let mut values: Vec<Value> = row.into_iter().map(Value::String).collect();
+                values.resize(width, Value::Null);
+                values
+            })
+            .collect();
+
+        self.request(
+            Method::PATCH,
+            document_id,
+            Some(tab_name),
+            &range_path(tab_name, &span),
+            Some(json!({ "values": values })),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SheetsBackend for GraphWorkbook {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let (tab_name, span) = tab_and_span(range)?;
+        let grid = self.used_grid(document_id, &tab_name).await?;
+        Ok(read_span(&grid, &span))
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let (tab_name, span) = tab_and_span(range)?;
+        self.write_cells(
+            document_id,
+            &tab_name,
+            span.start_row,
+            span.start_column,
+            rows,
+        )
+        .await
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let (tab_name, span) = tab_and_span(range)?;
+        let next_row = self
+            .used_grid(document_id, &tab_name)
+            .await?
+            .len()
+            .max(span.start_row);
+        let receipt = WriteReceipt::new(&tab_name, next_row, span.start_column, &rows);
+        self.write_cells(document_id, &tab_name, next_row, span.start_column, rows)
+            .await?;
+        Ok(receipt)
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        let (tab_name, span) = tab_and_span(range)?;
+        self.request(
+            Method::POST,
+            document_id,
+            Some(&tab_name),
+            &format!("{}/clear", range_path(&tab_name, &span)),
+            Some(json!({ "applyTo": "Contents" })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        if start >= end {
+            return Ok(());
+        }
+        let span = Span {
+            start_row: start,
+            start_column: 0,
+            end_row: Some(end - 1),
+            end_column: None,
+        };
+        self.request(
+            Method::POST,
+            document_id,
+            Some(tab_name),
+            &format!("{}/delete", range_path(tab_name, &span)),
+            Some(json!({ "shift": "Up" })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        let worksheets = self
+            .request(Method::GET, document_id, None, "worksheets", None)
+            .await?;
+        let mut worksheets: Vec<&Value> = worksheets
+            .get("value")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .collect();
+        worksheets.sort_by_key(|worksheet| worksheet.get("position").and_then(Value::as_i64));
+        Ok(worksheets.into_iter().map(tab_info).collect())
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let worksheet = self
+            .request(
+                Method::POST,
+                document_id,
+                None,
+                "worksheets/add",
+                Some(json!({ "name": tab_name })),
+            )
+            .await?;
+        Ok(tab_info(&worksheet))
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        self.request(
+            Method::DELETE,
+            document_id,
+            Some(tab_name),
+            &format!("worksheets/{}", encode(tab_name)),
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+fn tab_info(worksheet: &Value) -> TabInfo {
+    TabInfo {
+        sheet_id: sheet_id(
+            worksheet
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        ),
+        title: worksheet
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        row_count: MAX_ROWS,
+        column_count: MAX_COLUMNS,
+    }
+}
+
+/// Sheet ID for the worksheet with Graph ID `worksheet_id`, a non-negative FNV-1a hash, which
+/// unlike `DefaultHasher` is the same in every build
+fn sheet_id(worksheet_id: &str) -> i32 {
+    let hash = worksheet_id.bytes().fold(0x811c_9dc5_u32, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    });
+    (hash & 0x7fff_ffff) as i32
+}
+
+/// Path of the range covering `span` in worksheet `tab_name`
+fn range_path(tab_name: &str, span: &Span) -> String {
+    format!(
+        "worksheets/{}/range(address='{}')",
+        encode(tab_name),
+        encode(&address(span))
+    )
+}
+
+/// Worksheet-relative address of `span`, e.g. `B2:D5`, `A:C` or `3:7`
+fn address(span: &Span) -> String {
+    let end_row = span.end_row.unwrap_or(MAX_ROWS - 1);
+    let end_column = span.end_column.unwrap_or(MAX_COLUMNS - 1);
+    if span.start_row == 0 && span.end_row.is_none() {
+        format!(
+            "{}:{}",
+            column_letter(span.start_column),
+            column_letter(end_column)
+        )
+    } else if span.start_column == 0 && span.end_column.is_none() {
+        format!("{}:{}", span.start_row + 1, end_row + 1)
+    } else {
+        format!(
+            "{}{}:{}{}",
+            column_letter(span.start_column),
+            span.start_row + 1,
+            column_letter(end_column),
+            end_row + 1
+        )
+    }
+}
+
+/// Percent-encode `s` for use in a URL path
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn unexpected(error: impl std::fmt::Display) -> SheetsError {
+    SheetsError::UnexpectedResponse {
+        reason: error.to_string(),
+    }
+}
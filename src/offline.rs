@@ -0,0 +1,278 @@
+use crate::{serialize_rows, trace, SheetsBackend, SheetsError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+/// An append waiting in the journal of an `OfflineQueue`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedAppend {
+    pub dedup_key: String,
+    pub document_id: String,
+    pub tab_name: String,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Local journal of appends that could not reach the API.
+///
+/// The journal is a JSON-lines file at `path`. Keys of entries replayed by the current flush
+/// are tracked in `<path>.sent`, so a flush interrupted after sending an entry but before
+/// rewriting the journal won't send it twice.
+pub struct OfflineQueue {
+    path: PathBuf,
+}
+
+impl OfflineQueue {
+    /// Journal failed appends to the file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Append `obj` to tab `tab_name` in document `document_id`, journaling it instead if the
+    /// API is unreachable. If earlier appends are journaled, `obj` is journaled after them and
+    /// the journal flushed, keeping rows in order; should the flush fail otherwise than by the
+    /// API being unreachable, the error is returned and `obj` stays journaled for the next one.
+    /// A `dedup_key` already in the journal is ignored. Returns `true` if the row was sent now.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, sheets, obj)))]
+    pub async fn append_row<B: SheetsBackend + ?Sized>(
+        &self,
+        sheets: &B,
+        document_id: &str,
+        tab_name: &str,
+        dedup_key: &str,
+        obj: impl Serialize,
+    ) -> Result<bool, SheetsError> {
+        let entry = QueuedAppend {
+            dedup_key: dedup_key.to_string(),
+            document_id: document_id.to_string(),
+            tab_name: tab_name.to_string(),
+            rows: serialize_rows(&[obj], false)?,
+        };
+
+        if !self.pending()?.is_empty() {
+            // journaled before flushing, so it is sent after the earlier entries, and kept for
+            // the next flush if this one fails for any reason
+            self.enqueue(entry)?;
+            return match self.flush(sheets).await {
+                Ok(_) => Ok(true),
+                Err(e) if is_unreachable(&e) => Ok(false),
+                Err(e) => Err(e),
+            };
+        }
+
+        match sheets
+            .append_values(document_id, tab_name, entry.rows.clone())
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_unreachable(&e) => {
+                self.enqueue(entry)?;
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Appends currently waiting in the journal, oldest first
+    pub fn pending(&self) -> Result<Vec<QueuedAppend>, SheetsError> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                entries.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Replay journaled appends in order, removing each from the journal once sent.
+    /// Stops at the first failure, leaving it and later entries queued. Returns the number sent.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = ?self.path, sent = tracing::field::Empty))
+    )]
+    pub async fn flush<B: SheetsBackend + ?Sized>(&self, sheets: &B) -> Result<usize, SheetsError> {
+        let mut remaining = self.pending()?;
+        let mut sent = self.sent_keys()?;
+        let mut count = 0;
+
+        while let Some(entry) = remaining.first().cloned() {
+            if !sent.contains(&entry.dedup_key) {
+                sheets
+                    .append_values(&entry.document_id, &entry.tab_name, entry.rows)
+                    .await?;
+                self.mark_sent(&entry.dedup_key)?;
+                sent.insert(entry.dedup_key);
+                count += 1;
+            }
+            remaining.remove(0);
+            self.rewrite(&remaining)?;
+        }
+        trace::record("sent", count as u64);
+
+        match fs::remove_file(self.sent_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        Ok(count)
+    }
+
+    fn enqueue(&self, entry: QueuedAppend) -> Result<(), SheetsError> {
+        if self
+            .pending()?
+            .iter()
+            .any(|queued| queued.dedup_key == entry.dedup_key)
+        {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    fn rewrite(&self, entries: &[QueuedAppend]) -> Result<(), SheetsError> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        for entry in entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn sent_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".sent");
+        path.into()
+    }
+
+    fn sent_keys(&self) -> Result<HashSet<String>, SheetsError> {
+        match fs::read_to_string(self.sent_path()) {
+            Ok(contents) => Ok(contents.lines().map(str::to_string).collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashSet::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn mark_sent(&self, dedup_key: &str) -> Result<(), SheetsError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.sent_path())?;
+        writeln!(file, "{}", dedup_key)?;
+        Ok(())
+    }
+}
+
+/// Whether `error` means the API could not be reached at all, as opposed to rejecting the request
+fn is_unreachable(error: &SheetsError) -> bool {
+    matches!(
+        error,
+        SheetsError::Network(_) | SheetsError::SheetsError(google_sheets4::Error::MissingToken(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockSheets;
+
+    #[derive(Serialize)]
+    struct Reading {
+        sensor: &'static str,
+        value: u32,
+    }
+
+    fn reading(value: u32) -> Reading {
+        Reading {
+            sensor: "t1",
+            value,
+        }
+    }
+
+    fn queue() -> OfflineQueue {
+        let path = std::env::temp_dir().join(format!("offline-{}.jsonl", uuid::Uuid::new_v4()));
+        OfflineQueue::new(path)
+    }
+
+    fn keys(queue: &OfflineQueue) -> Vec<String> {
+        queue
+            .pending()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.dedup_key)
+            .collect()
+    }
+
+    fn values(sheets: &MockSheets) -> Vec<String> {
+        sheets
+            .get_tab("doc", "Readings")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| row[1].clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn journals_rows_while_unreachable_and_sends_them_in_order() {
+        let sheets = MockSheets::new();
+        let queue = queue();
+
+        sheets.fail_next(1, MockSheets::unreachable_error);
+        let sent = queue
+            .append_row(&sheets, "doc", "Readings", "a", reading(1))
+            .await
+            .unwrap();
+        assert!(!sent);
+        assert_eq!(keys(&queue), vec!["a"]);
+
+        let sent = queue
+            .append_row(&sheets, "doc", "Readings", "b", reading(2))
+            .await
+            .unwrap();
+        assert!(sent);
+        assert!(keys(&queue).is_empty());
+        assert_eq!(values(&sheets), vec!["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_row_when_flushing_fails() {
+        let sheets = MockSheets::new();
+        let queue = queue();
+
+        sheets.fail_next(1, MockSheets::unreachable_error);
+        queue
+            .append_row(&sheets, "doc", "Readings", "a", reading(1))
+            .await
+            .unwrap();
+
+        sheets.fail_next(1, MockSheets::quota_error);
+        let error = queue
+            .append_row(&sheets, "doc", "Readings", "b", reading(2))
+            .await;
+        assert!(matches!(error, Err(SheetsError::QuotaExceeded { .. })));
+        assert_eq!(keys(&queue), vec!["a", "b"]);
+
+        queue
+            .append_row(&sheets, "doc", "Readings", "c", reading(3))
+            .await
+            .unwrap();
+        assert!(keys(&queue).is_empty());
+        assert_eq!(values(&sheets), vec!["1", "2", "3"]);
+    }
+}
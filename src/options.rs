@@ -0,0 +1,602 @@
+use crate::SheetsError;
+use csv::Trim;
+use std::{any::Any, collections::HashMap, fmt, sync::Arc};
+
+/// Cell-level settings shared by reads and writes. Rows go straight between cells and
+/// fields without CSV text, so there is no delimiter, quoting or line terminator to set.
+#[derive(Debug, Clone, Copy)]
+pub struct CsvOptions {
+    /// Allow data rows with a different number of cells than the header; when reading,
+    /// missing cells are left to serde defaults rather than skipping the row
+    pub flexible: bool,
+    /// Whitespace trimmed from cells, of the header row, the other rows or both
+    pub trim: Trim,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            flexible: false,
+            trim: Trim::None,
+        }
+    }
+}
+
+/// How header cells are matched to struct fields when reading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderMatching {
+    /// Headers must equal the field names
+    #[default]
+    Exact,
+    /// Ignore case, whitespace and punctuation, so `Number Of Foos` matches `number_of_foos`
+    Normalized,
+}
+
+/// Which header text a column's field is matched by, when there are several header rows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HeaderKey {
+    /// The bottom header row
+    #[default]
+    Bottom,
+    /// Every header row's text joined by the separator, top to bottom, e.g. `group.field`.
+    /// Blank cells in the upper rows continue the text to their left, as merged cells read.
+    Joined(String),
+}
+
+/// Test of a data row against the tab's header, e.g. for where a table ends
+#[derive(Clone)]
+pub struct RowPredicate(Arc<dyn Fn(&[String], &[String]) -> bool + Send + Sync>);
+
+impl RowPredicate {
+    /// A predicate calling `f(header, row)`
+    pub fn new(f: impl Fn(&[String], &[String]) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn matches(&self, header: &[String], row: &[String]) -> bool {
+        (self.0)(header, row)
+    }
+}
+
+impl fmt::Debug for RowPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RowPredicate")
+    }
+}
+
+type Check<T> = Arc<dyn Fn(&T) -> Result<(), String> + Send + Sync>;
+
+/// A domain rule checked against every record read, added with `ReadOptions::validate`
+#[derive(Clone)]
+pub struct RowValidator {
+    /// A `Check<T>`
+    check: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+}
+
+impl RowValidator {
+    /// `Ok` if `record` passes; fails with `SheetsError::Serde` for sheet row `row` if it
+    /// doesn't, or with `SheetsError::InvalidRecord` if the validator is for another type
+    pub(crate) fn check<T: 'static>(&self, record: &T, row: usize) -> Result<(), SheetsError> {
+        let check =
+            self.check
+                .downcast_ref::<Check<T>>()
+                .ok_or_else(|| SheetsError::InvalidRecord {
+                    reason: format!(
+                        "validator for {} used to read {}",
+                        self.type_name,
+                        std::any::type_name::<T>()
+                    ),
+                })?;
+        check(record).map_err(|message| SheetsError::Serde {
+            row,
+            column: None,
+            message,
+        })
+    }
+}
+
+impl fmt::Debug for RowValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RowValidator<{}>", self.type_name)
+    }
+}
+
+/// How `read_all_with_options` turns rows into structs
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// Trimming of header and data cells, and whether rows may be ragged
+    pub csv: CsvOptions,
+    /// Fail with `SheetsError::TooManyRows` rather than read more data rows than this. Only
+    /// this many rows are requested, so a far bigger tab is never downloaded.
+    pub max_rows: Option<usize>,
+    /// Fail with `SheetsError::TooManyCells` if the rows read hold more cells than this
+    pub max_cells: Option<usize>,
+    /// Also return rows soft-deleted by `delete_rows_where`
+    pub include_deleted: bool,
+    /// Treat blank cells as missing fields, so `#[serde(default)]` and
+    /// `#[serde(default = "path")]` apply to cells humans left empty. Blank `String` fields
+    /// without a default then fail to deserialize rather than being `""`.
+    pub blank_as_missing: bool,
+    pub header_matching: HeaderMatching,
+    /// Field names for headers that don't match them, keyed by header. Keys are matched like
+    /// fields, so normalized when `header_matching` is `Normalized`.
+    pub header_aliases: HashMap<String, String>,
+    /// Rows of headers above the data, for templates with group headings over the fields
+    pub header_rows: usize,
+    pub header_key: HeaderKey,
+    /// Rows above the table to ignore, e.g. a title banner and the blank row under it
+    pub skip_rows: usize,
+    /// Rows at the bottom of the tab to ignore, e.g. a TOTAL row
+    pub footer_rows: usize,
+    /// Where the table ends: the first data row matching this, and every row after it, are
+    /// ignored. Applied after `footer_rows` are dropped.
+    pub stop_when: Option<RowPredicate>,
+    /// The tab has no header row: every row is data, read into fields by position
+    pub headerless: bool,
+    /// Fail with `SheetsError::UnknownColumns` if the header has columns `T` has no field for
+    pub deny_unknown_columns: bool,
+    /// Read cells holding formulas as the formula rather than its result, for fields such as
+    /// `ImageCell` whose cells display nothing
+    pub formulas: bool,
+    /// Separator of the numbers in the sheet. With `Comma`, cells such as `0,5` or
+    /// `1.234,5` are read as `0.5` and `1234.5`, text fields included.
+    pub decimal_separator: DecimalSeparator,
+    /// Rules every record must pass, or the read fails naming the first row that doesn't
+    pub validators: Vec<RowValidator>,
+    /// Trim whitespace from headers and cells, non-breaking and zero-width spaces included,
+    /// and make non-breaking spaces inside them plain spaces, so pasted values still parse
+    pub trim_whitespace: bool,
+    /// Cells meaning "no value", e.g. `-` or `N/A`, read as blank cells: `None` for `Option`
+    /// fields, and the default with `blank_as_missing`. Compared after trimming.
+    pub null_values: Vec<String>,
+    /// Skip data rows whose cells are all blank, rather than giving them to serde, where they
+    /// fail or become records of defaults. On by default.
+    pub skip_empty_rows: bool,
+    /// End the table at the first data row whose cells are all blank, ignoring the rows after
+    /// it, e.g. notes kept below the data. Applied after `footer_rows` are dropped.
+    pub stop_at_empty_row: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            csv: CsvOptions::default(),
+            max_rows: None,
+            max_cells: None,
+            include_deleted: false,
+            blank_as_missing: false,
+            header_matching: HeaderMatching::Exact,
+            header_aliases: HashMap::new(),
+            header_rows: 1,
+            header_key: HeaderKey::Bottom,
+            skip_rows: 0,
+            footer_rows: 0,
+            stop_when: None,
+            headerless: false,
+            deny_unknown_columns: false,
+            formulas: false,
+            decimal_separator: DecimalSeparator::Period,
+            validators: vec![],
+            trim_whitespace: false,
+            null_values: vec![],
+            skip_empty_rows: true,
+            stop_at_empty_row: false,
+        }
+    }
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn csv(mut self, csv: CsvOptions) -> Self {
+        self.csv = csv;
+        self
+    }
+
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    pub fn max_cells(mut self, max_cells: usize) -> Self {
+        self.max_cells = Some(max_cells);
+        self
+    }
+
+    pub fn include_deleted(mut self, include_deleted: bool) -> Self {
+        self.include_deleted = include_deleted;
+        self
+    }
+
+    pub fn blank_as_missing(mut self, blank_as_missing: bool) -> Self {
+        self.blank_as_missing = blank_as_missing;
+        self
+    }
+
+    /// Another name for `blank_as_missing`: blank and whitespace-only cells are `None` for
+    /// `Option` fields and the serde default for `#[serde(default)]` ones
+    pub fn treat_blank_as_none(self, treat_blank_as_none: bool) -> Self {
+        self.blank_as_missing(treat_blank_as_none)
+    }
+
+    pub fn trim_whitespace(mut self, trim_whitespace: bool) -> Self {
+        self.trim_whitespace = trim_whitespace;
+        self
+    }
+
+    /// Read cells equal to any of `values` as blank, e.g. `["-", "N/A"]`
+    pub fn null_values(mut self, values: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.null_values = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn header_matching(mut self, header_matching: HeaderMatching) -> Self {
+        self.header_matching = header_matching;
+        self
+    }
+
+    /// Read the column headed `header` into field `field`
+    pub fn header_alias(mut self, header: impl Into<String>, field: impl Into<String>) -> Self {
+        self.header_aliases.insert(header.into(), field.into());
+        self
+    }
+
+    /// Take the first `header_rows` rows as headers, at least one
+    pub fn header_rows(mut self, header_rows: usize) -> Self {
+        self.header_rows = header_rows.max(1);
+        self
+    }
+
+    pub fn header_key(mut self, header_key: HeaderKey) -> Self {
+        self.header_key = header_key;
+        self
+    }
+
+    pub fn skip_rows(mut self, skip_rows: usize) -> Self {
+        self.skip_rows = skip_rows;
+        self
+    }
+
+    pub fn footer_rows(mut self, footer_rows: usize) -> Self {
+        self.footer_rows = footer_rows;
+        self
+    }
+
+    /// End the table at the first data row for which `f(header, row)` is true
+    pub fn stop_when(
+        mut self,
+        f: impl Fn(&[String], &[String]) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.stop_when = Some(RowPredicate::new(f));
+        self
+    }
+
+    /// Check every record with `f`, e.g. `|order: &Order| ...` for quantities being positive,
+    /// failing the read with `SheetsError::Serde` for the first row `f` rejects, with `f`'s
+    /// message. A row that doesn't deserialize fails the read before `f` sees it.
+    pub fn validate<T: 'static>(
+        mut self,
+        f: impl Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        let check: Check<T> = Arc::new(f);
+        self.validators.push(RowValidator {
+            check: Arc::new(check),
+            type_name: std::any::type_name::<T>(),
+        });
+        self
+    }
+
+    /// Rows of headers above the data, none if `headerless`
+    pub(crate) fn header_row_count(&self) -> usize {
+        if self.headerless {
+            0
+        } else {
+            self.header_rows.max(1)
+        }
+    }
+
+    /// Read every row as data, the first column into the first field and so on
+    pub fn headerless(mut self, headerless: bool) -> Self {
+        self.headerless = headerless;
+        self
+    }
+
+    /// Reject headers with columns the record type doesn't have, like serde's
+    /// `deny_unknown_fields`, to catch columns added by hand
+    pub fn deny_unknown_columns(mut self, deny_unknown_columns: bool) -> Self {
+        self.deny_unknown_columns = deny_unknown_columns;
+        self
+    }
+
+    pub fn formulas(mut self, formulas: bool) -> Self {
+        self.formulas = formulas;
+        self
+    }
+
+    pub fn decimal_separator(mut self, decimal_separator: DecimalSeparator) -> Self {
+        self.decimal_separator = decimal_separator;
+        self
+    }
+
+    pub fn skip_empty_rows(mut self, skip_empty_rows: bool) -> Self {
+        self.skip_empty_rows = skip_empty_rows;
+        self
+    }
+
+    pub fn stop_at_empty_row(mut self, stop_at_empty_row: bool) -> Self {
+        self.stop_at_empty_row = stop_at_empty_row;
+        self
+    }
+
+    /// End the table at the first data row whose `column` is blank, e.g. its key column
+    pub fn stop_at_blank(self, column: impl Into<String>) -> Self {
+        let column = column.into();
+        self.stop_when(move |header, row| {
+            let cell = header
+                .iter()
+                .position(|h| *h == column)
+                .and_then(|i| row.get(i));
+            cell.map_or(true, |cell| cell.trim().is_empty())
+        })
+    }
+}
+
+/// How the API interprets written cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueInput {
+    /// Parse cells as if typed into the UI, so `1.5` is a number and `=A1` a formula
+    #[default]
+    UserEntered,
+    /// Store cells exactly as given, as text
+    Raw,
+}
+
+/// The decimal separator of a spreadsheet's locale, which numbers must be written with to be
+/// parsed as numbers rather than text or dates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimalSeparator {
+    /// `0.5`
+    #[default]
+    Period,
+    /// `0,5`, as in most of continental Europe and South America
+    Comma,
+}
+
+impl DecimalSeparator {
+    /// The separator of `locale`, such as `de_DE` or `en_US` as `spreadsheet_locale` returns
+    /// it
+    pub fn for_locale(locale: &str) -> Self {
+        const COMMA_LANGUAGES: &[&str] = &[
+            "az", "be", "bg", "ca", "cs", "da", "de", "el", "es", "et", "eu", "fi", "fr", "gl",
+            "hr", "hu", "id", "is", "it", "ka", "kk", "lt", "lv", "mk", "nb", "nl", "nn", "no",
+            "pl", "pt", "ro", "ru", "sk", "sl", "sq", "sr", "sv", "tr", "uk", "uz", "vi",
+        ];
+        // countries that use a period though their language mostly doesn't
+        const PERIOD_LOCALES: &[&str] = &["de_CH", "de_LI", "it_CH", "es_MX", "es_US"];
+
+        let locale = locale.replace('-', "_");
+        let language = locale.split('_').next().unwrap_or_default();
+        if COMMA_LANGUAGES.contains(&language) && !PERIOD_LOCALES.contains(&locale.as_str()) {
+            DecimalSeparator::Comma
+        } else {
+            DecimalSeparator::Period
+        }
+    }
+}
+
+/// Column `write_page_with_options` stamps with the time of the write, given `Provenance`
+pub const UPDATED_AT_COLUMN: &str = "_updated_at";
+/// Column of `Provenance::updated_by`
+pub const UPDATED_BY_COLUMN: &str = "_updated_by";
+/// Column of `Provenance::run_id`
+pub const SOURCE_RUN_ID_COLUMN: &str = "_source_run_id";
+
+/// Managed columns added to the right of every row written, recording when and by what, for
+/// sheets edited by people and programs alike. Reads ignore them unless the record type has
+/// fields of the same names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// Fills `_updated_by`, e.g. the program's name; the column is left out if `None`
+    pub updated_by: Option<String>,
+    /// Fills `_source_run_id`, e.g. a CI job ID; the column is left out if `None`
+    pub run_id: Option<String>,
+}
+
+impl Provenance {
+    /// Only `_updated_at`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn updated_by(mut self, updated_by: impl Into<String>) -> Self {
+        self.updated_by = Some(updated_by.into());
+        self
+    }
+
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Names and values of the managed columns for a write at `now`
+    pub(crate) fn columns(&self, now: &str) -> Vec<(&'static str, String)> {
+        let mut columns = vec![(UPDATED_AT_COLUMN, now.to_string())];
+        if let Some(updated_by) = &self.updated_by {
+            columns.push((UPDATED_BY_COLUMN, updated_by.clone()));
+        }
+        if let Some(run_id) = &self.run_id {
+            columns.push((SOURCE_RUN_ID_COLUMN, run_id.clone()));
+        }
+        columns
+    }
+}
+
+/// How `write_page_with_options` turns structs into rows and writes them
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Trimming of the cells written; `flexible` has no effect on writes
+    pub csv: CsvOptions,
+    /// Clear the tab before writing
+    pub clear: bool,
+    /// Write a header row of field names above the data
+    pub include_header: bool,
+    pub value_input: ValueInput,
+    /// How field names are rendered in the header row
+    pub header_case: HeaderCase,
+    /// Rows of headers the tab has, the bottom one of field names. The rows above it, e.g.
+    /// group headings, are left as they are.
+    pub header_rows: usize,
+    /// Rows above the table to leave as they are, e.g. a title banner
+    pub skip_rows: usize,
+    /// Separator floats are written with, to match the spreadsheet's locale
+    pub decimal_separator: DecimalSeparator,
+    pub provenance: Option<Provenance>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            csv: CsvOptions::default(),
+            clear: true,
+            include_header: true,
+            value_input: ValueInput::UserEntered,
+            header_case: HeaderCase::AsIs,
+            header_rows: 1,
+            skip_rows: 0,
+            decimal_separator: DecimalSeparator::Period,
+            provenance: None,
+        }
+    }
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn csv(mut self, csv: CsvOptions) -> Self {
+        self.csv = csv;
+        self
+    }
+
+    pub fn clear(mut self, clear: bool) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    pub fn include_header(mut self, include_header: bool) -> Self {
+        self.include_header = include_header;
+        self
+    }
+
+    pub fn value_input(mut self, value_input: ValueInput) -> Self {
+        self.value_input = value_input;
+        self
+    }
+
+    pub fn header_case(mut self, header_case: HeaderCase) -> Self {
+        self.header_case = header_case;
+        self
+    }
+
+    /// Keep the first `header_rows - 1` rows, writing from the row after them
+    pub fn header_rows(mut self, header_rows: usize) -> Self {
+        self.header_rows = header_rows.max(1);
+        self
+    }
+
+    /// Write only data rows, with fields in declaration order, for `ReadOptions::headerless`
+    pub fn headerless(self) -> Self {
+        self.include_header(false)
+    }
+
+    /// Start the table `skip_rows` rows down, like `ReadOptions::skip_rows`
+    pub fn skip_rows(mut self, skip_rows: usize) -> Self {
+        self.skip_rows = skip_rows;
+        self
+    }
+
+    pub fn decimal_separator(mut self, decimal_separator: DecimalSeparator) -> Self {
+        self.decimal_separator = decimal_separator;
+        self
+    }
+
+    /// Add `provenance`'s managed columns to every row
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+}
+
+/// How `WriteOptions` renders field names as headers. All but `Custom` read back into the
+/// same fields with `HeaderMatching::Normalized`.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum HeaderCase {
+    /// The field names as serialized, e.g. `number_of_foos`
+    #[default]
+    AsIs,
+    /// `Number Of Foos`
+    Title,
+    /// `Number of foos`
+    Sentence,
+    /// `NUMBER_OF_FOOS`
+    Screaming,
+    Custom(fn(&str) -> String),
+}
+
+impl HeaderCase {
+    /// `field` rendered in this case
+    pub fn apply(&self, field: &str) -> String {
+        let words = words(field);
+        match self {
+            HeaderCase::AsIs => field.to_string(),
+            HeaderCase::Title => words
+                .iter()
+                .map(|word| capitalize(word))
+                .collect::<Vec<_>>()
+                .join(" "),
+            HeaderCase::Sentence => {
+                let sentence = words.join(" ").to_lowercase();
+                capitalize(&sentence)
+            }
+            HeaderCase::Screaming => words.join("_").to_uppercase(),
+            HeaderCase::Custom(f) => f(field),
+        }
+    }
+}
+
+/// Words of a `snake_case`, `kebab-case` or `camelCase` identifier
+fn words(field: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in field.chars() {
+        let boundary = !c.is_alphanumeric() || (c.is_uppercase() && prev_lower);
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.push(c);
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// `word` with its first letter uppercased and the rest lowercased
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{append_rows, read_all, write_page, SheetsBackend, SheetsError, WriteReceipt};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeMap;
+
+/// How much time each tab of a `PartitionedTable` covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    /// `<prefix>_2024_06_15`
+    Daily,
+    /// `<prefix>_2024_06`
+    Monthly,
+}
+
+impl Partition {
+    /// The suffix of the tab covering `date`
+    fn suffix(&self, date: NaiveDate) -> String {
+        match self {
+            Partition::Daily => date.format("%Y_%m_%d").to_string(),
+            Partition::Monthly => date.format("%Y_%m").to_string(),
+        }
+    }
+
+    /// The first day of the partition after the one covering `date`
+    fn next(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Partition::Daily => date + Duration::days(1),
+            Partition::Monthly => {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+            }
+        }
+    }
+}
+
+/// Rows of `T` spread over one tab per day or month, such as `Data_2024_06`, chosen by each
+/// row's timestamp. Appends create missing tabs with a header row; reads of a time range only
+/// touch the tabs that cover it.
+pub struct PartitionedTable<'a, T, B: ?Sized> {
+    sheets: &'a B,
+    document_id: String,
+    prefix: String,
+    partition: Partition,
+    timestamp: Box<dyn Fn(&T) -> DateTime<Utc> + Send + Sync>,
+}
+
+impl<'a, T, B: SheetsBackend + ?Sized> PartitionedTable<'a, T, B> {
+    /// Tabs named `<prefix>_<date>` in document `document_id`, with rows placed by
+    /// `timestamp`
+    pub fn new(
+        sheets: &'a B,
+        document_id: impl Into<String>,
+        prefix: impl Into<String>,
+        partition: Partition,
+        timestamp: impl Fn(&T) -> DateTime<Utc> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            sheets,
+            document_id: document_id.into(),
+            prefix: prefix.into(),
+            partition,
+            timestamp: Box::new(timestamp),
+        }
+    }
+
+    /// The tab holding rows timestamped `at`
+    pub fn tab_name(&self, at: DateTime<Utc>) -> String {
+        format!("{}_{}", self.prefix, self.partition.suffix(at.date_naive()))
+    }
+
+    /// Append `objects` to the tabs covering their timestamps, creating any that don't exist.
+    /// Returns a receipt per tab written, in tab order.
+    pub async fn append(&self, objects: &[T]) -> Result<Vec<WriteReceipt>, SheetsError>
+    where
+        T: Serialize,
+    {
+        let mut by_tab: BTreeMap<String, Vec<&T>> = BTreeMap::new();
+        for obj in objects {
+            by_tab
+                .entry(self.tab_name((self.timestamp)(obj)))
+                .or_default()
+                .push(obj);
+        }
+        let existing = self.existing_tabs().await?;
+
+        let mut receipts = Vec::with_capacity(by_tab.len());
+        for (tab_name, objects) in by_tab {
+            let receipt = if existing.contains(&tab_name) {
+                append_rows(self.sheets, &self.document_id, &tab_name, &objects).await?
+            } else {
+                self.sheets.add_tab(&self.document_id, &tab_name).await?;
+                write_page(self.sheets, &self.document_id, &tab_name, &objects).await?
+            };
+            receipts.push(receipt);
+        }
+        Ok(receipts)
+    }
+
+    /// Every row timestamped from `from` up to but not including `to`, oldest tab first.
+    /// Tabs in the range that don't exist are skipped.
+    pub async fn read_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<T>, SheetsError>
+    where
+        T: DeserializeOwned,
+    {
+        let existing = self.existing_tabs().await?;
+        let mut records = vec![];
+        let mut date = from.date_naive();
+        while date <= to.date_naive() {
+            let tab_name = format!("{}_{}", self.prefix, self.partition.suffix(date));
+            if existing.contains(&tab_name) {
+                let rows: Vec<T> = read_all(self.sheets, &self.document_id, &tab_name).await?;
+                records.extend(rows.into_iter().filter(|obj| {
+                    let at = (self.timestamp)(obj);
+                    from <= at && at < to
+                }));
+            }
+            date = self.partition.next(date);
+        }
+        Ok(records)
+    }
+
+    /// Names of this table's tabs that exist, sorted
+    pub async fn tabs(&self) -> Result<Vec<String>, SheetsError> {
+        let prefix = format!("{}_", self.prefix);
+        let mut tabs: Vec<String> = self
+            .existing_tabs()
+            .await?
+            .into_iter()
+            .filter(|title| title.starts_with(&prefix))
+            .collect();
+        tabs.sort();
+        Ok(tabs)
+    }
+
+    async fn existing_tabs(&self) -> Result<Vec<String>, SheetsError> {
+        Ok(self
+            .sheets
+            .list_tabs(&self.document_id)
+            .await?
+            .into_iter()
+            .map(|tab| tab.title)
+            .collect())
+    }
+}
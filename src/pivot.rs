@@ -0,0 +1,175 @@
+use crate::{batch_update, quote_tab_name, range::CellRef, SheetsBackend, SheetsError};
+use google_sheets4::{
+    api::{
+        CellData, GridCoordinate, GridRange, PivotGroup, PivotValue, Request, RowData,
+        UpdateCellsRequest,
+    },
+    Sheets,
+};
+
+/// How a pivot table summarizes the values of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Sum,
+    /// Number of numeric cells
+    Count,
+    /// Number of non-empty cells
+    CountA,
+    CountUnique,
+    Average,
+    Median,
+    Min,
+    Max,
+}
+
+impl Aggregation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Aggregation::Sum => "SUM",
+            Aggregation::Count => "COUNT",
+            Aggregation::CountA => "COUNTA",
+            Aggregation::CountUnique => "COUNTUNIQUE",
+            Aggregation::Average => "AVERAGE",
+            Aggregation::Median => "MEDIAN",
+            Aggregation::Min => "MIN",
+            Aggregation::Max => "MAX",
+        }
+    }
+}
+
+/// A pivot table over the data in a tab, with groups and values named by header, e.g.
+///
+/// `PivotTable::new("Orders").row_group("region").value("amount", Aggregation::Sum)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PivotTable {
+    source_tab: String,
+    rows: Vec<String>,
+    columns: Vec<String>,
+    values: Vec<(String, Aggregation)>,
+}
+
+impl PivotTable {
+    /// Summarize the whole of tab `source_tab`, whose first row is the header
+    pub fn new(source_tab: impl Into<String>) -> Self {
+        Self {
+            source_tab: source_tab.into(),
+            rows: vec![],
+            columns: vec![],
+            values: vec![],
+        }
+    }
+
+    /// Group rows by the values of `column`, after any groups added before
+    pub fn row_group(mut self, column: impl Into<String>) -> Self {
+        self.rows.push(column.into());
+        self
+    }
+
+    /// Spread the values across columns by the values of `column`
+    pub fn column_group(mut self, column: impl Into<String>) -> Self {
+        self.columns.push(column.into());
+        self
+    }
+
+    /// Summarize `column` with `aggregation`
+    pub fn value(mut self, column: impl Into<String>, aggregation: Aggregation) -> Self {
+        self.values.push((column.into(), aggregation));
+        self
+    }
+
+    /// Place the pivot table with its top-left corner at cell `anchor`, e.g. `A1`, of tab
+    /// `tab_name`, creating the tab if needed. Fails with `SheetsError::ColumnNotFound` if a
+    /// group or value names a column the source header lacks.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, sheets)))]
+    pub async fn add(
+        &self,
+        sheets: &Sheets,
+        document_id: &str,
+        tab_name: &str,
+        anchor: &str,
+    ) -> Result<(), SheetsError> {
+        let anchor = CellRef::parse(anchor)?;
+        let header_range = format!("{}!1:1", quote_tab_name(&self.source_tab));
+        let header = sheets
+            .read_values(document_id, &header_range)
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let offset = |column: &str| {
+            header
+                .iter()
+                .position(|h| h == column)
+                .map(|i| i as i32)
+                .ok_or_else(|| SheetsError::ColumnNotFound {
+                    column: column.to_string(),
+                })
+        };
+        let group = |column: &String| -> Result<PivotGroup, SheetsError> {
+            Ok(PivotGroup {
+                source_column_offset: Some(offset(column)?),
+                show_totals: Some(true),
+                sort_order: Some("ASCENDING".to_string()),
+                ..Default::default()
+            })
+        };
+
+        let rows = self.rows.iter().map(group).collect::<Result<Vec<_>, _>>()?;
+        let columns = self
+            .columns
+            .iter()
+            .map(group)
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = self
+            .values
+            .iter()
+            .map(|(column, aggregation)| -> Result<PivotValue, SheetsError> {
+                Ok(PivotValue {
+                    source_column_offset: Some(offset(column)?),
+                    summarize_function: Some(aggregation.as_str().to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let source_id = sheets.tab(document_id, &self.source_tab).await?.sheet_id;
+        let target_id = match sheets.tab(document_id, tab_name).await {
+            Ok(tab) => tab.sheet_id,
+            Err(SheetsError::TabNotFound { .. }) => {
+                sheets.add_tab(document_id, tab_name).await?.sheet_id
+            }
+            Err(e) => return Err(e),
+        };
+
+        let pivot_table = google_sheets4::api::PivotTable {
+            source: Some(GridRange {
+                sheet_id: Some(source_id),
+                ..Default::default()
+            }),
+            rows: Some(rows),
+            columns: Some(columns),
+            values: Some(values),
+            ..Default::default()
+        };
+        let request = Request {
+            update_cells: Some(UpdateCellsRequest {
+                start: Some(GridCoordinate {
+                    sheet_id: Some(target_id),
+                    row_index: Some(anchor.row as i32),
+                    column_index: Some(anchor.column as i32),
+                }),
+                rows: Some(vec![RowData {
+                    values: Some(vec![CellData {
+                        pivot_table: Some(pivot_table),
+                        ..Default::default()
+                    }]),
+                }]),
+                fields: Some("pivotTable".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        batch_update(sheets, document_id, vec![request]).await?;
+        Ok(())
+    }
+}
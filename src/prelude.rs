@@ -0,0 +1,14 @@
+//! The types and traits most programs need, for a glob import:
+//!
+//! ```ignore
+//! use serde_sheets::prelude::*;
+//!
+//! let sheets = get_sheets(service_account_from_env()?, Some("token_cache.json")).await?;
+//! let tab = sheets.spreadsheet(DOCUMENT_ID).open_worksheet(TAB_NAME).await?;
+//! ```
+
+pub use crate::{
+    get_sheets, service_account_from_env, service_account_from_file, ReadOptions,
+    ServiceAccountKey, Sheets, SheetsBackend, SheetsConfig, SheetsError, Spreadsheet, TabDefaults,
+    TabInfo, Worksheet, WriteOptions,
+};
@@ -0,0 +1,482 @@
+//! A1 and R1C1 references to cells and ranges, for building sub-ranges without hand-rolling
+//! the column letters and tab name quoting.
+
+use crate::SheetsError;
+use std::fmt;
+
+/// One cell, by zero-based row and column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellRef {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl CellRef {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+
+    /// Parse an A1 reference like `B2` or `$B$2`
+    pub fn parse(a1: &str) -> Result<Self, SheetsError> {
+        match parse_a1(a1) {
+            Some((Some(column), Some(row))) => Ok(Self { row, column }),
+            _ => Err(malformed(a1)),
+        }
+    }
+
+    /// Parse an R1C1 reference like `R2C3`
+    pub fn parse_r1c1(r1c1: &str) -> Result<Self, SheetsError> {
+        let upper = r1c1.to_ascii_uppercase();
+        let (row, column) = upper
+            .strip_prefix('R')
+            .and_then(|rest| rest.split_once('C'))
+            .ok_or_else(|| malformed(r1c1))?;
+        match (one_based(row), one_based(column)) {
+            (Some(row), Some(column)) => Ok(Self { row, column }),
+            _ => Err(malformed(r1c1)),
+        }
+    }
+
+    /// This cell in R1C1 notation, e.g. `R2C3`
+    pub fn to_r1c1(&self) -> String {
+        format!("R{}C{}", self.row + 1, self.column + 1)
+    }
+
+    /// The cell `rows` down and `columns` right of this one, or `None` if that is above row 1
+    /// or left of column A
+    pub fn offset(&self, rows: isize, columns: isize) -> Option<Self> {
+        Some(Self {
+            row: self.row.checked_add_signed(rows)?,
+            column: self.column.checked_add_signed(columns)?,
+        })
+    }
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", column_letter(self.column), self.row + 1)
+    }
+}
+
+/// A rectangle of cells in tab `tab_name`. Open ends are `None`, so `'Data'!B:D` has no
+/// `end_row`, `'Data'!3:7` has no `end_column` and plain `Data` has neither.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RangeRef {
+    pub tab_name: String,
+    pub start: CellRef,
+    pub end_row: Option<usize>,
+    pub end_column: Option<usize>,
+}
+
+impl RangeRef {
+    /// The cells from `start` to `end` inclusive, in either order
+    pub fn new(tab_name: impl Into<String>, start: CellRef, end: CellRef) -> Self {
+        Self {
+            tab_name: tab_name.into(),
+            start: CellRef::new(start.row.min(end.row), start.column.min(end.column)),
+            end_row: Some(start.row.max(end.row)),
+            end_column: Some(start.column.max(end.column)),
+        }
+    }
+
+    /// Every cell of tab `tab_name`
+    pub fn whole_tab(tab_name: impl Into<String>) -> Self {
+        Self {
+            tab_name: tab_name.into(),
+            start: CellRef::new(0, 0),
+            end_row: None,
+            end_column: None,
+        }
+    }
+
+    /// Parse a range like `'My Tab'!B2:C3`, `Data!A:C`, `Data!3:7` or `Data`. As in the API, a
+    /// range without `!` is a whole tab.
+    pub fn parse(range: &str) -> Result<Self, SheetsError> {
+        let (tab_name, cells) = split_tab_name(range).ok_or_else(|| malformed(range))?;
+        let cells = match cells {
+            Some(cells) => cells,
+            None => return Ok(Self::whole_tab(tab_name)),
+        };
+
+        let (start, end) = match cells.split_once(':') {
+            Some((start, end)) => (parse_a1(start), parse_a1(end)),
+            None => {
+                let cell = CellRef::parse(cells)?;
+                return Ok(Self::new(tab_name, cell, cell));
+            }
+        };
+        let ((start_column, start_row), (end_column, end_row)) =
+            start.zip(end).ok_or_else(|| malformed(range))?;
+        // `B:D` and `3:7` are whole columns and rows; `A5:A` runs to the bottom of the tab
+        let consistent = match (start_column, start_row) {
+            (Some(_), Some(_)) => end_column.is_some() || end_row.is_some(),
+            (Some(_), None) => end_column.is_some() && end_row.is_none(),
+            (None, Some(_)) => end_column.is_none() && end_row.is_some(),
+            (None, None) => false,
+        };
+        if !consistent {
+            return Err(malformed(range));
+        }
+
+        let start = CellRef::new(start_row.unwrap_or(0), start_column.unwrap_or(0));
+        if end_row.map_or(false, |end| end < start.row)
+            || end_column.map_or(false, |end| end < start.column)
+        {
+            return Err(malformed(range));
+        }
+        Ok(Self {
+            tab_name,
+            start,
+            end_row,
+            end_column,
+        })
+    }
+
+    /// The bottom-right cell, if neither end is open
+    pub fn end(&self) -> Option<CellRef> {
+        Some(CellRef::new(self.end_row?, self.end_column?))
+    }
+
+    /// Whether `cell` is inside the range
+    pub fn contains(&self, cell: CellRef) -> bool {
+        cell.row >= self.start.row
+            && cell.column >= self.start.column
+            && self.end_row.map_or(true, |end| cell.row <= end)
+            && self.end_column.map_or(true, |end| cell.column <= end)
+    }
+
+    /// The same-sized range `rows` down and `columns` right, or `None` if it would start above
+    /// row 1 or left of column A
+    pub fn offset(&self, rows: isize, columns: isize) -> Option<Self> {
+        Some(Self {
+            tab_name: self.tab_name.clone(),
+            start: self.start.offset(rows, columns)?,
+            end_row: match self.end_row {
+                Some(end) => Some(end.checked_add_signed(rows)?),
+                None => None,
+            },
+            end_column: match self.end_column {
+                Some(end) => Some(end.checked_add_signed(columns)?),
+                None => None,
+            },
+        })
+    }
+
+    /// The cells in both ranges, or `None` if they are on different tabs or don't overlap
+    pub fn intersection(&self, other: &RangeRef) -> Option<Self> {
+        if self.tab_name != other.tab_name {
+            return None;
+        }
+        let start = CellRef::new(
+            self.start.row.max(other.start.row),
+            self.start.column.max(other.start.column),
+        );
+        let end_row = min_end(self.end_row, other.end_row);
+        let end_column = min_end(self.end_column, other.end_column);
+        if end_row.map_or(false, |end| end < start.row)
+            || end_column.map_or(false, |end| end < start.column)
+        {
+            return None;
+        }
+        Some(Self {
+            tab_name: self.tab_name.clone(),
+            start,
+            end_row,
+            end_column,
+        })
+    }
+}
+
+impl fmt::Display for RangeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tab_name = quote_tab_name(&self.tab_name);
+        match (self.end_row, self.end_column) {
+            // a single cell
+            (Some(row), Some(column)) if row == self.start.row && column == self.start.column => {
+                write!(f, "{}!{}", tab_name, self.start)
+            }
+            (Some(row), Some(column)) => {
+                write!(
+                    f,
+                    "{}!{}:{}",
+                    tab_name,
+                    self.start,
+                    CellRef::new(row, column)
+                )
+            }
+            (None, Some(column)) if self.start.row == 0 => write!(
+                f,
+                "{}!{}:{}",
+                tab_name,
+                column_letter(self.start.column),
+                column_letter(column)
+            ),
+            (None, Some(column)) => {
+                write!(f, "{}!{}:{}", tab_name, self.start, column_letter(column))
+            }
+            (Some(row), None) if self.start.column == 0 => {
+                write!(f, "{}!{}:{}", tab_name, self.start.row + 1, row + 1)
+            }
+            // the API has no syntax for rows cut off on the left only, so run to the last column
+            (Some(row), None) => write!(
+                f,
+                "{}!{}:{}",
+                tab_name,
+                self.start,
+                CellRef::new(row, MAX_COLUMN)
+            ),
+            (None, None) if self.start == CellRef::new(0, 0) => f.write_str(&tab_name),
+            (None, None) => write!(
+                f,
+                "{}!{}:{}",
+                tab_name,
+                self.start,
+                column_letter(MAX_COLUMN)
+            ),
+        }
+    }
+}
+
+/// Last column a tab can have, `ZZZ`
+pub(crate) const MAX_COLUMN: usize = 18_277;
+
+/// Column letters for zero-based column `index`, e.g. `0` => `A`, `27` => `AB`
+pub fn column_letter(index: usize) -> String {
+    let mut letters = vec![];
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap()
+}
+
+/// Zero-based index of column `letters`, e.g. `AB` => `27`; case-insensitive
+pub fn column_index(letters: &str) -> Option<usize> {
+    if letters.is_empty() || !letters.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+    letters
+        .to_ascii_uppercase()
+        .bytes()
+        .try_fold(0usize, |acc, b| {
+            acc.checked_mul(26)?.checked_add((b - b'A' + 1) as usize)
+        })
+        .map(|n| n - 1)
+}
+
+/// Quote a tab name for use in an A1 range, e.g. `'My Tab'!A1:B2`
+pub fn quote_tab_name(tab_name: &str) -> String {
+    format!("'{}'", tab_name.replace('\'', "''"))
+}
+
+/// Split `range` into its unquoted tab name and the cells after `!`, if any
+fn split_tab_name(range: &str) -> Option<(String, Option<&str>)> {
+    let quoted = match range.strip_prefix('\'') {
+        Some(quoted) => quoted,
+        None => {
+            return Some(match range.split_once('!') {
+                Some((tab_name, cells)) => (tab_name.to_string(), Some(cells)),
+                None => (range.to_string(), None),
+            })
+        }
+    };
+
+    let mut tab_name = String::new();
+    let mut chars = quoted.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\'' {
+            if matches!(chars.peek(), Some((_, '\''))) {
+                chars.next();
+            } else {
+                let rest = &quoted[i + 1..];
+                return match rest.strip_prefix('!') {
+                    Some(cells) => Some((tab_name, Some(cells))),
+                    None if rest.is_empty() => Some((tab_name, None)),
+                    None => None,
+                };
+            }
+        }
+        tab_name.push(c);
+    }
+    // no closing quote
+    None
+}
+
+/// Zero-based `(column, row)` of a reference like `B2`, `B`, `2` or `$B$2`, or `None` if it is
+/// none of those
+fn parse_a1(cell: &str) -> Option<(Option<usize>, Option<usize>)> {
+    let cell = cell.trim().strip_prefix('$').unwrap_or(cell.trim());
+    let letters_len = cell
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(cell.len());
+    let (letters, digits) = cell.split_at(letters_len);
+    let digits = match letters {
+        "" => digits,
+        _ => digits.strip_prefix('$').unwrap_or(digits),
+    };
+
+    let column = match letters {
+        "" => None,
+        letters => Some(column_index(letters)?),
+    };
+    let row = match digits {
+        "" => None,
+        digits => Some(one_based(digits)?),
+    };
+    if column.is_none() && row.is_none() {
+        return None;
+    }
+    Some((column, row))
+}
+
+/// Zero-based index of one-based number `digits`
+fn one_based(digits: &str) -> Option<usize> {
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// The nearer of two open-ended bounds
+fn min_end(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn malformed(range: &str) -> SheetsError {
+    SheetsError::MalformedRange {
+        range: range.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(a1: &str) -> RangeRef {
+        RangeRef::parse(a1).unwrap()
+    }
+
+    #[test]
+    fn unquotes_tab_names_with_doubled_quotes() {
+        let parsed = range("'Bob''s Tab'!A1:B2");
+        assert_eq!(parsed.tab_name, "Bob's Tab");
+        assert_eq!(parsed.start, CellRef::new(0, 0));
+        assert_eq!(parsed.end(), Some(CellRef::new(1, 1)));
+        assert_eq!(parsed.to_string(), "'Bob''s Tab'!A1:B2");
+        assert_eq!(quote_tab_name("it's"), "'it''s'");
+        assert!(RangeRef::parse("'Unclosed!A1").is_err());
+        assert!(RangeRef::parse("'Tab'A1").is_err());
+    }
+
+    #[test]
+    fn parses_open_ended_ranges() {
+        let to_bottom = range("Data!A5:A");
+        assert_eq!(to_bottom.start, CellRef::new(4, 0));
+        assert_eq!((to_bottom.end_row, to_bottom.end_column), (None, Some(0)));
+
+        let rows = range("Data!3:7");
+        assert_eq!(rows.start, CellRef::new(2, 0));
+        assert_eq!((rows.end_row, rows.end_column), (Some(6), None));
+
+        let columns = range("Data!B:D");
+        assert_eq!(columns.start, CellRef::new(0, 1));
+        assert_eq!((columns.end_row, columns.end_column), (None, Some(3)));
+
+        assert_eq!(range("Data"), RangeRef::whole_tab("Data"));
+        assert!(RangeRef::parse("Data!A:3").is_err());
+        assert!(RangeRef::parse("Data!3:C").is_err());
+    }
+
+    #[test]
+    fn parses_absolute_references() {
+        assert_eq!(CellRef::parse("$B$2").unwrap(), CellRef::new(1, 1));
+        assert_eq!(CellRef::parse("B$2").unwrap(), CellRef::new(1, 1));
+        assert_eq!(range("Data!$B$2").to_string(), "'Data'!B2");
+        assert_eq!(range("Data!$A$1:$C$3").end(), Some(CellRef::new(2, 2)));
+        assert!(CellRef::parse("B").is_err());
+        assert!(CellRef::parse("B0").is_err());
+    }
+
+    #[test]
+    fn rejects_reversed_bounds() {
+        assert!(RangeRef::parse("Data!C3:A1").is_err());
+        assert!(RangeRef::parse("Data!7:3").is_err());
+        assert!(RangeRef::parse("Data!D:B").is_err());
+        let built = RangeRef::new("Data", CellRef::new(2, 2), CellRef::new(0, 0));
+        assert_eq!(built, range("Data!A1:C3"));
+    }
+
+    #[test]
+    fn converts_columns_past_z_and_zz() {
+        for (index, letters) in [
+            (0, "A"),
+            (25, "Z"),
+            (26, "AA"),
+            (51, "AZ"),
+            (701, "ZZ"),
+            (702, "AAA"),
+            (MAX_COLUMN, "ZZZ"),
+        ] {
+            assert_eq!(column_letter(index), letters);
+            assert_eq!(column_index(letters), Some(index));
+        }
+        assert_eq!(column_index("zz"), Some(701));
+        assert_eq!(column_index(""), None);
+        assert_eq!(column_index("A1"), None);
+        assert_eq!(range("Data!AA10:ZZ20").to_string(), "'Data'!AA10:ZZ20");
+    }
+
+    #[test]
+    fn formats_ranges_that_parse_back_the_same() {
+        for a1 in [
+            "'Data'!B2",
+            "'Data'!A1:C3",
+            "'Data'!A5:A",
+            "'Data'!3:7",
+            "'Data'!B:D",
+            "'My ''Tab'''!AA1:AB2",
+            "'Data'",
+        ] {
+            let parsed = range(a1);
+            assert_eq!(parsed.to_string(), a1);
+            assert_eq!(range(&parsed.to_string()), parsed);
+        }
+        let cell = CellRef::parse_r1c1("R2C3").unwrap();
+        assert_eq!(cell, CellRef::new(1, 2));
+        assert_eq!(cell.to_r1c1(), "R2C3");
+    }
+
+    #[test]
+    fn offsets_ranges() {
+        assert_eq!(range("Data!B2:C3").offset(1, -1), Some(range("Data!A3:B4")));
+        assert_eq!(range("Data!B:D").offset(0, 2), Some(range("Data!D:F")));
+        assert_eq!(range("Data!B2:C3").offset(-2, 0), None);
+        assert_eq!(range("Data!B2:C3").offset(0, -2), None);
+    }
+
+    #[test]
+    fn intersects_ranges() {
+        assert_eq!(
+            range("Data!B2:D5").intersection(&range("Data!C4:F9")),
+            Some(range("Data!C4:D5"))
+        );
+        assert_eq!(
+            range("Data!A:A").intersection(&range("Data!3:7")),
+            Some(range("Data!A3:A7"))
+        );
+        assert_eq!(range("Data!A1:B2").intersection(&range("Data!C3:D4")), None);
+        assert_eq!(
+            range("Data!A1:B2").intersection(&range("Other!A1:B2")),
+            None
+        );
+        assert!(range("Data!B2:C3").contains(CellRef::new(2, 2)));
+        assert!(!range("Data!B2:C3").contains(CellRef::new(3, 2)));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    clear_tab,
+    columns::{cell, format_bool, infer_column_type, ColumnType},
+    delete, trace, SheetsBackend, SheetsError,
+};
+use arrow::{
+    array::{Array, ArrayRef, AsArray, StringArray},
+    compute::cast,
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use std::sync::Arc;
+
+/// Read tab `tab_name` in document `document_id` into a `RecordBatch`.
+///
+/// With a `schema`, each field is read from the header column of the same name and cells are
+/// cast to its type, invalid cells becoming nulls. Without one, a column is created per header
+/// cell with type `Boolean`, `Int64`, `Float64` or `Utf8` inferred from its cells. Empty cells
+/// are nulls, and soft-deleted rows are skipped like in `read_all`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, schema), fields(rows = tracing::field::Empty))
+)]
+pub async fn read_record_batch<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    schema: Option<SchemaRef>,
+) -> Result<RecordBatch, SheetsError> {
+    let rows = delete::without_soft_deleted(sheets.read_values(document_id, tab_name).await?);
+    let (header, rows) = match rows.split_first() {
+        Some((header, rows)) => (header.as_slice(), rows),
+        None => (&[][..], &[][..]),
+    };
+    trace::record("rows", rows.len() as u64);
+
+    let schema = schema.unwrap_or_else(|| {
+        let fields: Vec<Field> = header
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Field::new(name, data_type(infer_column_type(rows, index)), true))
+            .collect();
+        Arc::new(Schema::new(fields))
+    });
+    if rows.is_empty() {
+        return Ok(RecordBatch::new_empty(schema));
+    }
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let index = header
+                .iter()
+                .position(|h| h == field.name())
+                .ok_or_else(|| SheetsError::ColumnNotFound {
+                    column: field.name().to_string(),
+                })?;
+            let text: ArrayRef = Arc::new(
+                rows.iter()
+                    .map(|row| cell(row, index))
+                    .collect::<StringArray>(),
+            );
+            Ok(cast(&text, field.data_type())?)
+        })
+        .collect::<Result<Vec<_>, SheetsError>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Write `batch` to tab `tab_name` in document `document_id`, with the field names as the
+/// header row. The sheet will be cleared before writing. Nulls are written as empty cells.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, batch), fields(rows = batch.num_rows()))
+)]
+pub async fn write_record_batch<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    batch: &RecordBatch,
+) -> Result<(), SheetsError> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(column_cells)
+        .collect::<Result<Vec<_>, SheetsError>>()?;
+
+    let mut rows = Vec::with_capacity(batch.num_rows() + 1);
+    rows.push(
+        batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().to_string())
+            .collect(),
+    );
+    for i in 0..batch.num_rows() {
+        rows.push(columns.iter().map(|column| column[i].clone()).collect());
+    }
+
+    clear_tab(sheets, document_id, tab_name).await?;
+    sheets.write_values(document_id, tab_name, rows).await
+}
+
+fn data_type(column_type: ColumnType) -> DataType {
+    match column_type {
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Integer => DataType::Int64,
+        ColumnType::Float => DataType::Float64,
+        ColumnType::Text => DataType::Utf8,
+    }
+}
+
+/// Cell text for every value of `column`
+fn column_cells(column: &ArrayRef) -> Result<Vec<String>, SheetsError> {
+    if let Some(values) = column.as_boolean_opt() {
+        return Ok((0..values.len())
+            .map(|i| {
+                if values.is_null(i) {
+                    String::new()
+                } else {
+                    format_bool(values.value(i))
+                }
+            })
+            .collect());
+    }
+
+    let text = cast(column, &DataType::Utf8)?;
+    let text = text.as_string::<i32>();
+    Ok((0..text.len())
+        .map(|i| {
+            if text.is_null(i) {
+                String::new()
+            } else {
+                text.value(i).to_string()
+            }
+        })
+        .collect())
+}
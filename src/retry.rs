@@ -0,0 +1,434 @@
+use crate::{SheetsBackend, SheetsError, TabInfo, WriteReceipt};
+use async_trait::async_trait;
+use std::{future::Future, sync::Arc, time::Duration};
+
+type RetryHook = Arc<dyn Fn(&'static str, u32, Duration) + Send + Sync>;
+
+/// Decides whether, and after how long, `Retrying` retries a failed call
+pub trait RetryStrategy: Send + Sync {
+    /// Delay before retry number `attempt` (starting at 1) after `error`, or `None` to give up.
+    /// `idempotent` is false for calls that must not be repeated if they may have landed.
+    fn delay(&self, error: &SheetsError, attempt: u32, idempotent: bool) -> Option<Duration>;
+}
+
+/// Which failures `RetryPolicy` retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryOn {
+    /// Quota errors
+    pub quota: bool,
+    /// 5xx responses from the API, for idempotent calls only
+    pub server_errors: bool,
+    /// Network errors, for idempotent calls only
+    pub network: bool,
+}
+
+impl Default for RetryOn {
+    fn default() -> Self {
+        Self {
+            quota: true,
+            server_errors: true,
+            network: true,
+        }
+    }
+}
+
+/// How `Retrying` backs off between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per call, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each retry after it
+    pub base_delay: Duration,
+    /// Upper bound on the backoff delay. A `Retry-After` from the API is always honoured.
+    pub max_delay: Duration,
+    /// Failures worth retrying
+    pub retry_on: RetryOn,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(32),
+            retry_on: RetryOn::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (starting at 1) after `error`, or `None` if the
+    /// error should not be retried. Quota errors are retried after the delay the API asked for.
+    /// Network errors and 5xx responses are only retried when `idempotent`, since the request
+    /// may have been applied before it failed.
+    pub fn delay(&self, error: &SheetsError, attempt: u32, idempotent: bool) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        match error {
+            SheetsError::QuotaExceeded { retry_after, .. } if self.retry_on.quota => {
+                Some(retry_after.unwrap_or(backoff))
+            }
+            // the server may have applied the call before failing, like a dropped connection
+            SheetsError::ServerError { .. } if self.retry_on.server_errors && idempotent => {
+                Some(backoff)
+            }
+            // 5xx responses without a JSON body the API's error shape could be read from
+            SheetsError::SheetsError(google_sheets4::Error::Failure(response))
+                if self.retry_on.server_errors
+                    && idempotent
+                    && response.status().is_server_error() =>
+            {
+                Some(backoff)
+            }
+            SheetsError::Network(_) if self.retry_on.network && idempotent => Some(backoff),
+            // rewriting the rows already saved is harmless, re-appending them is not
+            SheetsError::PartialWrite { source, .. } if idempotent => {
+                self.delay(source, attempt, idempotent)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl RetryStrategy for RetryPolicy {
+    fn delay(&self, error: &SheetsError, attempt: u32, idempotent: bool) -> Option<Duration> {
+        RetryPolicy::delay(self, error, attempt, idempotent)
+    }
+}
+
+/// Backend wrapper retrying failed calls according to a `RetryPolicy` or custom `RetryStrategy`
+pub struct Retrying<B> {
+    inner: B,
+    policy: Box<dyn RetryStrategy>,
+    on_retry: Option<RetryHook>,
+}
+
+impl<B: SheetsBackend> Retrying<B> {
+    /// Retry calls made through `inner` according to `policy`, usually a `RetryPolicy`
+    pub fn new(inner: B, policy: impl RetryStrategy + 'static) -> Self {
+        Self {
+            inner,
+            policy: Box::new(policy),
+            on_retry: None,
+        }
+    }
+
+    /// Call `hook` with the operation, attempt number and delay before each retry, e.g. to
+    /// forward to `MetricsRecorder::record_retry`
+    pub fn on_retry(
+        mut self,
+        hook: impl Fn(&'static str, u32, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_retry = Some(Arc::new(hook));
+        self
+    }
+
+    /// The wrapped backend
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    async fn call<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        idempotent: bool,
+        call: F,
+    ) -> Result<T, SheetsError>
+    where
+        F: Fn() -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T, SheetsError>> + Send,
+    {
+        with_retries(
+            &*self.policy,
+            self.on_retry.as_deref(),
+            operation,
+            idempotent,
+            call,
+        )
+        .await
+    }
+}
+
+/// `call`, retried after failures while `policy` says to, with `on_retry` told of each retry
+pub(crate) async fn with_retries<T, F, Fut>(
+    policy: &dyn RetryStrategy,
+    on_retry: Option<&(dyn Fn(&'static str, u32, Duration) + Send + Sync)>,
+    operation: &'static str,
+    idempotent: bool,
+    call: F,
+) -> Result<T, SheetsError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, SheetsError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let error = match call().await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        let delay = match policy.delay(&error, attempt, idempotent) {
+            Some(delay) => delay,
+            None => return Err(error),
+        };
+        if let Some(hook) = on_retry {
+            hook(operation, attempt, delay);
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[async_trait]
+impl<B: SheetsBackend> SheetsBackend for Retrying<B> {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.call("read_values", true, || {
+            self.inner.read_values(document_id, range)
+        })
+        .await
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        self.call("read_values", true, || {
+            self.inner.read_formulas(document_id, range)
+        })
+        .await
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        self.call("read_ranges", true, || {
+            self.inner.read_ranges(document_id, ranges)
+        })
+        .await
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.call("write_values", true, || {
+            self.inner.write_values(document_id, range, rows.clone())
+        })
+        .await
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        self.call("write_values", true, || {
+            self.inner
+                .write_raw_values(document_id, range, rows.clone())
+        })
+        .await
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        self.call("write_ranges", true, || {
+            self.inner.write_ranges(document_id, data.clone())
+        })
+        .await
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        self.call("append_values", false, || {
+            self.inner.append_values(document_id, range, rows.clone())
+        })
+        .await
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        self.call("clear_range", true, || {
+            self.inner.clear_range(document_id, range)
+        })
+        .await
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        self.call("delete_rows", false, || {
+            self.inner.delete_rows(document_id, tab_name, start, end)
+        })
+        .await
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        self.call("list_tabs", true, || self.inner.list_tabs(document_id))
+            .await
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        self.call("add_tab", false, || {
+            self.inner.add_tab(document_id, tab_name)
+        })
+        .await
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        self.call("delete_tab", false, || {
+            self.inner.delete_tab(document_id, tab_name)
+        })
+        .await
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        self.call("duplicate_tab", false, || {
+            self.inner.duplicate_tab(document_id, tab_name, new_name)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApiErrorDetails;
+
+    fn details(status: u16) -> ApiErrorDetails {
+        ApiErrorDetails {
+            status,
+            reason: None,
+            message: "failed".to_string(),
+        }
+    }
+
+    fn failure(status: u16) -> SheetsError {
+        let response = hyper::Response::builder()
+            .status(status)
+            .body(hyper::Body::empty())
+            .unwrap();
+        SheetsError::SheetsError(google_sheets4::Error::Failure(response))
+    }
+
+    fn network() -> SheetsError {
+        let error = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        SheetsError::Network(google_sheets4::Error::Io(error))
+    }
+
+    fn quota(retry_after: Option<Duration>) -> SheetsError {
+        SheetsError::QuotaExceeded {
+            retry_after,
+            details: details(429),
+        }
+    }
+
+    const BACKOFF: Option<Duration> = Some(Duration::from_millis(500));
+
+    #[test]
+    fn retries_quota_errors_whether_or_not_idempotent() {
+        let policy = RetryPolicy::default();
+        let asked = Some(Duration::from_secs(30));
+        for idempotent in [true, false] {
+            assert_eq!(policy.delay(&quota(None), 1, idempotent), BACKOFF);
+            assert_eq!(policy.delay(&quota(asked), 1, idempotent), asked);
+        }
+    }
+
+    #[test]
+    fn retries_server_errors_only_when_idempotent() {
+        let policy = RetryPolicy::default();
+        let server_error = SheetsError::ServerError {
+            details: details(503),
+        };
+        assert_eq!(policy.delay(&server_error, 1, true), BACKOFF);
+        assert_eq!(policy.delay(&server_error, 1, false), None);
+        assert_eq!(policy.delay(&failure(500), 1, true), BACKOFF);
+        assert_eq!(policy.delay(&failure(500), 1, false), None);
+    }
+
+    #[test]
+    fn retries_network_errors_only_when_idempotent() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(&network(), 1, true), BACKOFF);
+        assert_eq!(policy.delay(&network(), 1, false), None);
+    }
+
+    #[test]
+    fn retries_partial_writes_only_when_idempotent() {
+        let policy = RetryPolicy::default();
+        let partial = SheetsError::PartialWrite {
+            range: "Sheet1!A3".to_string(),
+            rows_written: 2,
+            source: Box::new(quota(None)),
+        };
+        assert_eq!(policy.delay(&partial, 1, true), BACKOFF);
+        assert_eq!(policy.delay(&partial, 1, false), None);
+    }
+
+    #[test]
+    fn never_retries_other_errors() {
+        let policy = RetryPolicy::default();
+        let denied = SheetsError::PermissionDenied {
+            details: details(403),
+        };
+        for idempotent in [true, false] {
+            assert_eq!(policy.delay(&denied, 1, idempotent), None);
+            assert_eq!(policy.delay(&failure(400), 1, idempotent), None);
+        }
+    }
+
+    #[test]
+    fn respects_retry_on_and_max_attempts() {
+        let policy = RetryPolicy {
+            retry_on: RetryOn {
+                quota: false,
+                server_errors: false,
+                network: false,
+            },
+            ..RetryPolicy::default()
+        };
+        let server_error = SheetsError::ServerError {
+            details: details(500),
+        };
+        assert_eq!(policy.delay(&quota(None), 1, true), None);
+        assert_eq!(policy.delay(&server_error, 1, true), None);
+        assert_eq!(policy.delay(&network(), 1, true), None);
+
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            policy.delay(&network(), 4, true),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(policy.delay(&network(), 5, true), None);
+    }
+}
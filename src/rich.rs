@@ -0,0 +1,124 @@
+use crate::SheetsError;
+use google_sheets4::{api::CellData, Sheets};
+use serde::{Deserialize, Serialize};
+
+const RICH_FIELDS: &str = "sheets.data.rowData.values(formattedValue,note,hyperlink,\
+    effectiveFormat.backgroundColor,effectiveFormat.numberFormat)";
+
+/// An RGB color, each channel from `0.0` to `1.0`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    /// The sheets UI's "yellow"
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+
+    pub const fn rgb(red: f32, green: f32, blue: f32) -> Self {
+        Self { red, green, blue }
+    }
+
+    /// Parse `#rrggbb`, with or without the `#`
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .ok()
+                .map(|c| c as f32 / 255.0)
+        };
+        Some(Self::rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// `#rrggbb`
+    pub fn to_hex(&self) -> String {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            channel(self.red),
+            channel(self.green),
+            channel(self.blue)
+        )
+    }
+
+    /// Whether `other` is the same color once both are rounded to 8 bits per channel, as the
+    /// API returns them with float noise
+    pub fn matches(&self, other: &Color) -> bool {
+        self.to_hex() == other.to_hex()
+    }
+}
+
+/// A cell's displayed value with what a human may have added around it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RichCell {
+    /// The value as displayed
+    pub value: String,
+    pub note: Option<String>,
+    pub hyperlink: Option<String>,
+    /// Fill color, white when the cell has none
+    pub background: Option<Color>,
+    /// Number format pattern such as `#,##0.00`, or its type such as `DATE` if it has no
+    /// pattern
+    pub number_format: Option<String>,
+}
+
+/// Cells of `range` with their notes, links, fill colors and number formats, row by row, for
+/// tools that interpret how humans marked up a sheet, e.g. rows highlighted yellow being on
+/// hold. Like `read_values`, trailing empty rows and cells are left out.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn read_cells_rich(
+    sheets: &Sheets,
+    document_id: &str,
+    range: &str,
+) -> Result<Vec<Vec<RichCell>>, SheetsError> {
+    let (_, spreadsheet) = sheets
+        .spreadsheets()
+        .get(document_id)
+        .add_ranges(range)
+        .include_grid_data(true)
+        .param("fields", RICH_FIELDS)
+        .doit()
+        .await
+        .map_err(|e| SheetsError::from_api(e, document_id))?;
+
+    Ok(spreadsheet
+        .sheets
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|sheet| sheet.data.unwrap_or_default())
+        .flat_map(|grid| grid.row_data.unwrap_or_default())
+        .map(|row| {
+            row.values
+                .unwrap_or_default()
+                .into_iter()
+                .map(rich_cell)
+                .collect()
+        })
+        .collect())
+}
+
+fn rich_cell(cell: CellData) -> RichCell {
+    let format = cell.effective_format.unwrap_or_default();
+    RichCell {
+        value: cell.formatted_value.unwrap_or_default(),
+        note: cell.note,
+        hyperlink: cell.hyperlink,
+        // the API leaves out channels that are zero
+        background: format.background_color.map(|color| {
+            Color::rgb(
+                color.red.unwrap_or(0.0),
+                color.green.unwrap_or(0.0),
+                color.blue.unwrap_or(0.0),
+            )
+        }),
+        number_format: format
+            .number_format
+            .and_then(|format| format.pattern.filter(|p| !p.is_empty()).or(format.type_)),
+    }
+}
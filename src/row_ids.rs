@@ -0,0 +1,174 @@
+use crate::{batch_update, quote_tab_name, serialize_rows, write_page, SheetsBackend, SheetsError};
+use google_sheets4::{
+    api::{
+        CreateDeveloperMetadataRequest, DataFilter, DeleteDeveloperMetadataRequest,
+        DeleteDimensionRequest, DeveloperMetadata, DeveloperMetadataLocation,
+        DeveloperMetadataLookup, DimensionRange, Request, SearchDeveloperMetadataRequest,
+    },
+    Sheets,
+};
+use uuid::Uuid;
+
+/// Developer metadata key under which row IDs are stored
+pub const ROW_ID_KEY: &str = "serde_sheets_row_id";
+
+/// Write `objects` like `write_page`, attaching a generated ID to each data row as
+/// developer metadata. The metadata moves with the row when humans sort or insert rows.
+/// Returns the IDs in the same order as `objects`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn write_with_ids(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[impl serde::Serialize],
+) -> Result<Vec<String>, SheetsError> {
+    write_page(sheets, document_id, tab_name, objects).await?;
+
+    let sheet_id = sheets.tab(document_id, tab_name).await?.sheet_id;
+
+    // clearing the tab leaves metadata of the previous write attached to the rows
+    let mut requests: Vec<Request> = search_row_ids(sheets, document_id, None)
+        .await?
+        .into_iter()
+        .filter(|metadata| row_location(metadata).map(|(sheet, _)| sheet) == Some(sheet_id))
+        .filter_map(|metadata| metadata.metadata_id)
+        .map(|metadata_id| Request {
+            delete_developer_metadata: Some(DeleteDeveloperMetadataRequest {
+                data_filter: Some(DataFilter {
+                    developer_metadata_lookup: Some(DeveloperMetadataLookup {
+                        metadata_id: Some(metadata_id),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        })
+        .collect();
+
+    let ids: Vec<String> = objects.iter().map(|_| Uuid::new_v4().to_string()).collect();
+
+    requests.extend(ids.iter().enumerate().map(|(i, id)| Request {
+        create_developer_metadata: Some(CreateDeveloperMetadataRequest {
+            developer_metadata: Some(DeveloperMetadata {
+                metadata_key: Some(ROW_ID_KEY.to_string()),
+                metadata_value: Some(id.clone()),
+                visibility: Some("DOCUMENT".to_string()),
+                location: Some(DeveloperMetadataLocation {
+                    dimension_range: Some(row_dimension(sheet_id, i + 1)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        }),
+        ..Default::default()
+    }));
+
+    if !requests.is_empty() {
+        batch_update(sheets, document_id, requests).await?;
+    }
+
+    Ok(ids)
+}
+
+/// Overwrite the row tagged with `id` by `write_with_ids` with `obj`. Fails with
+/// `SheetsError::RowIdNotFound` if the row is in another tab than `tab_name`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, obj)))]
+pub async fn update_by_id(
+    sheets: &Sheets,
+    document_id: &str,
+    tab_name: &str,
+    id: &str,
+    obj: impl serde::Serialize,
+) -> Result<(), SheetsError> {
+    let (sheet_id, row_index) = find_row(sheets, document_id, id).await?;
+    if sheets.tab(document_id, tab_name).await?.sheet_id != sheet_id {
+        return Err(SheetsError::RowIdNotFound { id: id.to_string() });
+    }
+    let range = format!("{}!A{}", quote_tab_name(tab_name), row_index + 1);
+
+    sheets
+        .write_values(document_id, &range, serialize_rows(&[obj], false)?)
+        .await
+}
+
+/// Delete the row tagged with `id` by `write_with_ids`, shifting later rows up
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn delete_by_id(sheets: &Sheets, document_id: &str, id: &str) -> Result<(), SheetsError> {
+    let (sheet_id, row_index) = find_row(sheets, document_id, id).await?;
+
+    let request = Request {
+        delete_dimension: Some(DeleteDimensionRequest {
+            range: Some(row_dimension(sheet_id, row_index)),
+        }),
+        ..Default::default()
+    };
+    batch_update(sheets, document_id, vec![request]).await?;
+
+    Ok(())
+}
+
+/// Locate the row tagged with `id`, as `(sheet_id, zero-based row index)`
+async fn find_row(
+    sheets: &Sheets,
+    document_id: &str,
+    id: &str,
+) -> Result<(i32, usize), SheetsError> {
+    search_row_ids(sheets, document_id, Some(id))
+        .await?
+        .iter()
+        .find_map(row_location)
+        .ok_or_else(|| SheetsError::RowIdNotFound { id: id.to_string() })
+}
+
+/// All row ID metadata in the document, optionally restricted to a single `id`
+async fn search_row_ids(
+    sheets: &Sheets,
+    document_id: &str,
+    id: Option<&str>,
+) -> Result<Vec<DeveloperMetadata>, SheetsError> {
+    let req = SearchDeveloperMetadataRequest {
+        data_filters: Some(vec![DataFilter {
+            developer_metadata_lookup: Some(DeveloperMetadataLookup {
+                metadata_key: Some(ROW_ID_KEY.to_string()),
+                metadata_value: id.map(str::to_string),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]),
+    };
+
+    let (_body, response) = sheets
+        .spreadsheets()
+        .developer_metadata_search(req, document_id)
+        .param("fields", "matchedDeveloperMetadata.developerMetadata")
+        .doit()
+        .await
+        .map_err(|e| SheetsError::from_api(e, document_id))?;
+
+    Ok(response
+        .matched_developer_metadata
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|matched| matched.developer_metadata)
+        .collect())
+}
+
+/// `(sheet_id, zero-based row index)` of row-level metadata
+fn row_location(metadata: &DeveloperMetadata) -> Option<(i32, usize)> {
+    let range = metadata.location.as_ref()?.dimension_range.as_ref()?;
+    Some((range.sheet_id?, range.start_index? as usize))
+}
+
+/// Single row `row_index` (zero-based) of sheet `sheet_id`
+fn row_dimension(sheet_id: i32, row_index: usize) -> DimensionRange {
+    DimensionRange {
+        dimension: Some("ROWS".to_string()),
+        sheet_id: Some(sheet_id),
+        start_index: Some(row_index as i32),
+        end_index: Some(row_index as i32 + 1),
+    }
+}
@@ -0,0 +1,134 @@
+//! Row-level conversion for types that don't fit serde's flat model, e.g. with computed
+//! columns or values spread over several cells.
+//!
+//! Types that are already `Serialize` and `DeserializeOwned` can implement both traits with
+//! `impl_row_via_serde!`, and hand-written impls can start from `serde_to_row` and
+//! `serde_from_row`.
+
+use crate::{clear_tab, de::serde_error, serialize_rows, SheetsBackend, SheetsError};
+use csv::StringRecord;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Converts a value into one row of cells
+pub trait ToRow {
+    /// Column names for the cells of `to_row`, in the same order
+    fn header(&self) -> Vec<String>;
+
+    fn to_row(&self) -> Result<Vec<String>, SheetsError>;
+}
+
+/// Builds a value from one row of cells
+pub trait FromRow: Sized {
+    /// The value in `row`, whose cells are named by `header`. `row` may be shorter than
+    /// `header` when trailing cells are empty.
+    fn from_row(header: &[String], row: &[String]) -> Result<Self, SheetsError>;
+}
+
+/// Implement `ToRow` and `FromRow` for types through their serde impls, exactly as
+/// `write_page` and `read_all` convert them
+#[macro_export]
+macro_rules! impl_row_via_serde {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl $crate::rows::ToRow for $ty {
+                fn header(&self) -> Vec<String> {
+                    $crate::rows::serde_to_row(self)
+                        .map(|(header, _)| header)
+                        .unwrap_or_default()
+                }
+
+                fn to_row(&self) -> Result<Vec<String>, $crate::SheetsError> {
+                    $crate::rows::serde_to_row(self).map(|(_, row)| row)
+                }
+            }
+
+            impl $crate::rows::FromRow for $ty {
+                fn from_row(
+                    header: &[String],
+                    row: &[String],
+                ) -> Result<Self, $crate::SheetsError> {
+                    $crate::rows::serde_from_row(header, row)
+                }
+            }
+        )+
+    };
+}
+
+/// The header and cells `obj` serializes to
+pub fn serde_to_row<T: Serialize>(obj: &T) -> Result<(Vec<String>, Vec<String>), SheetsError> {
+    let mut rows = serialize_rows(std::slice::from_ref(obj), true)?.into_iter();
+    Ok((
+        rows.next().unwrap_or_default(),
+        rows.next().unwrap_or_default(),
+    ))
+}
+
+/// Deserialize `row` against `header`, padding it with empty cells to the header's width
+pub fn serde_from_row<T: DeserializeOwned>(
+    header: &[String],
+    row: &[String],
+) -> Result<T, SheetsError> {
+    let mut record: StringRecord = row.iter().collect();
+    for _ in row.len()..header.len() {
+        record.push_field("");
+    }
+    // the row number is filled in by the caller, which knows it
+    record
+        .deserialize(Some(&header.iter().collect::<StringRecord>()))
+        .map_err(|e| serde_error(e, 0, header))
+}
+
+/// Like `write_page`, converting `objects` with their `ToRow` impls. The header is taken from
+/// the first object.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, objects), fields(rows = objects.len()))
+)]
+pub async fn write_rows<T: ToRow, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    objects: &[T],
+) -> Result<(), SheetsError> {
+    let mut rows = Vec::with_capacity(objects.len() + 1);
+    if let Some(first) = objects.first() {
+        rows.push(first.header());
+    }
+    for obj in objects {
+        rows.push(obj.to_row()?);
+    }
+
+    clear_tab(sheets, document_id, tab_name).await?;
+    sheets.write_values(document_id, tab_name, rows).await
+}
+
+/// Like `read_all`, converting rows with `T`'s `FromRow` impl. Empty rows are skipped, and the
+/// first row that fails to convert fails the read.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn read_rows<T: FromRow, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Vec<T>, SheetsError> {
+    let mut rows = sheets.read_values(document_id, tab_name).await?.into_iter();
+    let header = rows.next().unwrap_or_default();
+
+    rows.enumerate()
+        .filter(|(_, row)| !row.is_empty())
+        .map(|(i, row)| {
+            T::from_row(&header, &row).map_err(|error| match error {
+                // one-based, after the header
+                SheetsError::Serde {
+                    row: 0,
+                    column,
+                    message,
+                } => SheetsError::Serde {
+                    row: i + 2,
+                    column,
+                    message,
+                },
+                error => error,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,396 @@
+//! A `_schema` tab recording the fields and field types of the record type last written to
+//! each tab, so a read by code whose type has since changed fails with
+//! `SheetsError::SchemaDrift` instead of quietly mis-reading columns.
+
+use crate::{batch_update, quote_tab_name, SheetsBackend, SheetsError};
+use google_sheets4::{
+    api::{Request, SheetProperties, UpdateSheetPropertiesRequest},
+    Sheets,
+};
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, MapAccess, Visitor};
+use std::fmt;
+
+/// Tab holding one row per registered tab
+pub const SCHEMA_TAB: &str = "_schema";
+
+const SCHEMA_HEADER: [&str; 5] = ["tab", "type", "fields", "version", "written_at"];
+
+/// The shape of a record type as it is stored in a tab
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    pub type_name: String,
+    /// `(field, type)` pairs in column order, with types as serde asks for them, e.g. `u64`,
+    /// `string` or `option<f64>`
+    pub fields: Vec<(String, String)>,
+    /// Hash of `fields`, changing whenever a field is added, removed, renamed or retyped
+    pub version: String,
+}
+
+impl Schema {
+    /// The schema of `T`, which must be a struct with named fields
+    pub fn of<T: DeserializeOwned>() -> Self {
+        let fields = crate::de::struct_fields::<T>()
+            .iter()
+            .map(|field| (field.to_string(), field_type::<T>(field)))
+            .collect();
+        Self::new(std::any::type_name::<T>().to_string(), fields)
+    }
+
+    fn new(type_name: String, fields: Vec<(String, String)>) -> Self {
+        let version = format!("{:016x}", fnv1a(&fields_cell(&fields)));
+        Self {
+            type_name,
+            fields,
+            version,
+        }
+    }
+
+    /// How `self` differs from `recorded`, e.g. `+sku`, `-code`, `price: string -> f64`
+    fn changes_from(&self, recorded: &Schema) -> Vec<String> {
+        let mut changes = vec![];
+        for (field, field_type) in &self.fields {
+            match recorded.fields.iter().find(|(name, _)| name == field) {
+                None => changes.push(format!("+{}", field)),
+                Some((_, old)) if old != field_type => {
+                    changes.push(format!("{}: {} -> {}", field, old, field_type))
+                }
+                Some(_) => {}
+            }
+        }
+        for (field, _) in &recorded.fields {
+            if !self.fields.iter().any(|(name, _)| name == field) {
+                changes.push(format!("-{}", field));
+            }
+        }
+        changes
+    }
+}
+
+/// Record `T`'s schema as the one of tab `tab_name`, creating the `_schema` tab if needed.
+/// Call it after writing `T`s to the tab.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn record_schema<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<(), SheetsError> {
+    let schema = Schema::of::<T>();
+    let row = vec![
+        tab_name.to_string(),
+        schema.type_name.clone(),
+        fields_cell(&schema.fields),
+        schema.version.clone(),
+        chrono::Utc::now().to_rfc3339(),
+    ];
+
+    let rows = match sheets.tab(document_id, SCHEMA_TAB).await {
+        Ok(_) => sheets.read_values(document_id, SCHEMA_TAB).await?,
+        Err(SheetsError::TabNotFound { .. }) => {
+            sheets.add_tab(document_id, SCHEMA_TAB).await?;
+            vec![]
+        }
+        Err(e) => return Err(e),
+    };
+    if rows.is_empty() {
+        let header = SCHEMA_HEADER.iter().map(|h| h.to_string()).collect();
+        return sheets
+            .write_values(document_id, SCHEMA_TAB, vec![header, row])
+            .await;
+    }
+    match rows
+        .iter()
+        .position(|r| r.first().map(String::as_str) == Some(tab_name))
+    {
+        Some(i) => {
+            let range = format!("{}!A{}", quote_tab_name(SCHEMA_TAB), i + 1);
+            sheets.write_values(document_id, &range, vec![row]).await
+        }
+        None => sheets
+            .append_values(document_id, SCHEMA_TAB, vec![row])
+            .await
+            .map(|_| ()),
+    }
+}
+
+/// Check that tab `tab_name` was last written with `T`'s schema, failing with
+/// `SheetsError::SchemaDrift` listing the differences if not. Tabs with no recorded schema
+/// pass.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn check_schema<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<(), SheetsError> {
+    let recorded = match recorded_schema(sheets, document_id, tab_name).await? {
+        Some(recorded) => recorded,
+        None => return Ok(()),
+    };
+    let schema = Schema::of::<T>();
+    if schema.version == recorded.version {
+        return Ok(());
+    }
+    Err(SheetsError::SchemaDrift {
+        tab: tab_name.to_string(),
+        changes: schema.changes_from(&recorded),
+    })
+}
+
+/// The schema recorded for tab `tab_name`, if any
+pub async fn recorded_schema<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<Option<Schema>, SheetsError> {
+    let rows = match sheets.tab(document_id, SCHEMA_TAB).await {
+        Ok(_) => sheets.read_values(document_id, SCHEMA_TAB).await?,
+        Err(SheetsError::TabNotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let cell = |row: &[String], i: usize| row.get(i).cloned().unwrap_or_default();
+    Ok(rows
+        .iter()
+        .skip(1)
+        .find(|row| row.first().map(String::as_str) == Some(tab_name))
+        .map(|row| {
+            let fields = cell(row, 2)
+                .split(", ")
+                .filter(|field| !field.is_empty())
+                .map(|field| match field.split_once(": ") {
+                    Some((name, field_type)) => (name.to_string(), field_type.to_string()),
+                    None => (field.to_string(), String::new()),
+                })
+                .collect();
+            Schema {
+                type_name: cell(row, 1),
+                fields,
+                version: cell(row, 3),
+            }
+        }))
+}
+
+/// Hide the `_schema` tab from the sheet's tab bar, so people don't edit it by accident
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn hide_schema_tab(sheets: &Sheets, document_id: &str) -> Result<(), SheetsError> {
+    let sheet_id = sheets.tab(document_id, SCHEMA_TAB).await?.sheet_id;
+    let request = Request {
+        update_sheet_properties: Some(UpdateSheetPropertiesRequest {
+            properties: Some(SheetProperties {
+                sheet_id: Some(sheet_id),
+                hidden: Some(true),
+                ..Default::default()
+            }),
+            fields: Some("hidden".to_string()),
+        }),
+        ..Default::default()
+    };
+    batch_update(sheets, document_id, vec![request]).await?;
+    Ok(())
+}
+
+/// `id: u64, name: string`
+fn fields_cell(fields: &[(String, String)]) -> String {
+    fields
+        .iter()
+        .map(|(name, field_type)| format!("{}: {}", name, field_type))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is the same in every Rust release
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The type serde asks for when deserializing `field` of `T`, found by deserializing `T`
+/// from a map holding only that field with a value that records what it is asked for
+fn field_type<T: DeserializeOwned>(field: &'static str) -> String {
+    let mut found = String::new();
+    let _ = T::deserialize(OneField {
+        field,
+        found: &mut found,
+    });
+    if found.is_empty() {
+        "unknown".to_string()
+    } else {
+        found
+    }
+}
+
+struct OneField<'a> {
+    field: &'static str,
+    found: &'a mut String,
+}
+
+impl<'de, 'a> Deserializer<'de> for OneField<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(OneFieldMap {
+            field: Some(self.field),
+            found: self.found,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+struct OneFieldMap<'a> {
+    field: Option<&'static str>,
+    found: &'a mut String,
+}
+
+impl<'de, 'a> MapAccess<'de> for OneFieldMap<'a> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.field.take() {
+            Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        seed.deserialize(TypeProbe(self.found))
+    }
+}
+
+/// Deserializer that writes the name of the type it is asked for, then fails
+struct TypeProbe<'a>(&'a mut String);
+
+macro_rules! probe {
+    ($($method:ident => $name:literal),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+                self.0.push_str($name);
+                Err(de::Error::custom("only the type was wanted"))
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for TypeProbe<'a> {
+    type Error = de::value::Error;
+
+    probe!(
+        deserialize_any => "any",
+        deserialize_bool => "bool",
+        deserialize_i8 => "i8",
+        deserialize_i16 => "i16",
+        deserialize_i32 => "i32",
+        deserialize_i64 => "i64",
+        deserialize_i128 => "i128",
+        deserialize_u8 => "u8",
+        deserialize_u16 => "u16",
+        deserialize_u32 => "u32",
+        deserialize_u64 => "u64",
+        deserialize_u128 => "u128",
+        deserialize_f32 => "f32",
+        deserialize_f64 => "f64",
+        deserialize_char => "char",
+        deserialize_str => "string",
+        deserialize_string => "string",
+        deserialize_bytes => "bytes",
+        deserialize_byte_buf => "bytes",
+        deserialize_unit => "unit",
+        deserialize_seq => "seq",
+        deserialize_map => "map",
+        deserialize_identifier => "identifier",
+        deserialize_ignored_any => "ignored",
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.push_str("option<");
+        let result = visitor.visit_some(TypeProbe(&mut *self.0));
+        self.0.push('>');
+        result
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.push_str(name);
+        Err(de::Error::custom("only the type was wanted"))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.push_str(&format!("tuple{}", len));
+        Err(de::Error::custom("only the type was wanted"))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.push_str(name);
+        Err(de::Error::custom("only the type was wanted"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.push_str(name);
+        Err(de::Error::custom("only the type was wanted"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.push_str(name);
+        Err(de::Error::custom("only the type was wanted"))
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {{ {} }} v{}",
+            self.type_name,
+            fields_cell(&self.fields),
+            self.version
+        )
+    }
+}
@@ -0,0 +1,536 @@
+//! Serializes records straight into rows of cells, producing the same cells as a round trip
+//! through `csv` without the intermediate CSV text.
+
+use crate::{DecimalSeparator, SheetsError};
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+use std::fmt;
+
+/// Rows for `objects`, starting with a header row if `include_header` and the records are
+/// structs, with floats written with `decimal`. `user_entered` rows are parsed by the API, so
+/// 128-bit integers get a leading apostrophe to keep them text rather than lossy floats.
+pub(crate) fn to_rows<S: Serialize>(
+    objects: &[S],
+    include_header: bool,
+    decimal: DecimalSeparator,
+    user_entered: bool,
+) -> Result<Vec<Vec<String>>, SheetsError> {
+    let mut rows = Vec::with_capacity(objects.len() + 1);
+    for (i, obj) in objects.iter().enumerate() {
+        let mut record = RecordSerializer {
+            row: vec![],
+            fields: None,
+            decimal,
+            user_entered,
+        };
+        obj.serialize(&mut record)
+            .map_err(|e| SheetsError::InvalidRecord { reason: e.0 })?;
+
+        if i == 0 && include_header {
+            if let Some(fields) = record.fields {
+                rows.push(fields.into_iter().map(str::to_string).collect());
+            }
+        }
+        rows.push(record.row);
+    }
+    Ok(rows)
+}
+
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, Error> {
+    Err(Error(format!("serializing {} is not supported", what)))
+}
+
+/// Serializes one record, a struct or a sequence of scalars, into a row
+struct RecordSerializer {
+    row: Vec<String>,
+    /// Field names, if the record is a struct
+    fields: Option<Vec<&'static str>>,
+    decimal: DecimalSeparator,
+    user_entered: bool,
+}
+
+impl RecordSerializer {
+    fn cell<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let cell = value.serialize(CellSerializer {
+            decimal: self.decimal,
+            user_entered: self.user_entered,
+        })?;
+        self.row.push(cell);
+        Ok(())
+    }
+}
+
+macro_rules! serialize_cell {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                self.cell(&v)
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut RecordSerializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    serialize_cell!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    );
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.row.push(String::from_utf8_lossy(v).into_owned());
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.row.push(String::new());
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.row.push(String::new());
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), Error> {
+        self.row.push(name.to_string());
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.row.push(variant.to_string());
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("tuple enum variants")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("maps")
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self, Error> {
+        self.fields = Some(Vec::with_capacity(len));
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("struct enum variants")
+    }
+}
+
+impl<'a> ser::SerializeSeq for &'a mut RecordSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.cell(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for &'a mut RecordSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.cell(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for &'a mut RecordSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.cell(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for &'a mut RecordSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        if let Some(fields) = self.fields.as_mut() {
+            fields.push(key);
+        }
+        self.cell(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serializes a scalar into the text of one cell
+#[derive(Clone, Copy)]
+struct CellSerializer {
+    decimal: DecimalSeparator,
+    user_entered: bool,
+}
+
+impl CellSerializer {
+    fn float(&self, formatted: &str) -> String {
+        match self.decimal {
+            DecimalSeparator::Period => formatted.to_string(),
+            DecimalSeparator::Comma => formatted.replace('.', ","),
+        }
+    }
+
+    /// `number` as text, since Sheets stores numbers as 64-bit floats
+    fn wide(&self, number: impl fmt::Display) -> String {
+        if self.user_entered {
+            format!("'{}", number)
+        } else {
+            number.to_string()
+        }
+    }
+}
+
+impl ser::Serializer for CellSerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, Error> {
+        Ok(self.wide(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, Error> {
+        Ok(self.wide(v))
+    }
+
+    // csv formats floats with ryu, e.g. `1.0` rather than `1`
+    fn serialize_f32(self, v: f32) -> Result<String, Error> {
+        Ok(self.float(ryu::Buffer::new().format(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<String, Error> {
+        Ok(self.float(ryu::Buffer::new().format(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(v).into_owned())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<String, Error> {
+        Ok(name.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        unsupported("sequences inside a record")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        unsupported("tuples inside a record")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        unsupported("tuple structs inside a record")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("tuple enum variants")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("maps")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        unsupported("structs inside a record")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("struct enum variants")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_all, serialize_rows_with_options, write_page, CsvOptions, MockSheets};
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        id: u32,
+        text: String,
+    }
+
+    /// Cells that a trip through CSV text used to split, unquote or mangle
+    const AWKWARD: [&str; 7] = [
+        "line one\nline two",
+        "windows\r\nline",
+        "say \"hi\"",
+        "a, b, c",
+        "col\tumn",
+        "naïve café ☕ 日本語",
+        "\"\n,\t\"",
+    ];
+
+    fn notes() -> Vec<Note> {
+        AWKWARD
+            .iter()
+            .enumerate()
+            .map(|(i, text)| Note {
+                id: i as u32,
+                text: text.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn to_rows_keeps_cells_verbatim() {
+        let notes = notes();
+        let rows = to_rows(&notes, true, DecimalSeparator::Period, true).unwrap();
+        assert_eq!(rows.len(), notes.len() + 1);
+        assert_eq!(rows[0], ["id", "text"]);
+        for (row, note) in rows[1..].iter().zip(&notes) {
+            assert_eq!(row, &[note.id.to_string(), note.text.clone()]);
+        }
+    }
+
+    #[test]
+    fn trimming_keeps_inner_newlines_and_quotes() {
+        let notes = vec![Note {
+            id: 1,
+            text: "  say \"hi\",\nthen\tleave \n".to_string(),
+        }];
+        let options = CsvOptions {
+            trim: csv::Trim::All,
+            ..CsvOptions::default()
+        };
+        let rows = serialize_rows_with_options(&notes, true, &options).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][1], "say \"hi\",\nthen\tleave");
+    }
+
+    #[tokio::test]
+    async fn awkward_cells_round_trip() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "Notes", vec![]);
+        let notes = notes();
+        write_page(&sheets, "doc", "Notes", &notes).await.unwrap();
+        let read: Vec<Note> = read_all(&sheets, "doc", "Notes").await.unwrap();
+        assert_eq!(read, notes);
+    }
+}
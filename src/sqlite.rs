@@ -0,0 +1,149 @@
+use crate::{
+    clear_tab,
+    columns::{cell, infer_column_type, parse_bool, ColumnType},
+    delete, trace, SheetsBackend, SheetsError,
+};
+use rusqlite::{
+    params_from_iter,
+    types::{Value, ValueRef},
+    Connection,
+};
+
+/// Copy tab `tab_name` in document `document_id` into SQLite table `table`, replacing it.
+///
+/// Header cells become columns, typed `INTEGER`, `REAL` or `TEXT` from their cells, with
+/// booleans stored as `0` and `1`. Empty cells become `NULL`, and soft-deleted rows are skipped
+/// like in `read_all`. Returns the number of rows copied.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, conn), fields(rows = tracing::field::Empty))
+)]
+pub async fn mirror_to_sqlite<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    conn: &mut Connection,
+    table: &str,
+) -> Result<usize, SheetsError> {
+    let rows = delete::without_soft_deleted(sheets.read_values(document_id, tab_name).await?);
+    let (header, rows) = match rows.split_first() {
+        Some((header, rows)) => (header, rows),
+        None => return Ok(0),
+    };
+    trace::record("rows", rows.len() as u64);
+
+    let types: Vec<ColumnType> = (0..header.len())
+        .map(|index| infer_column_type(rows, index))
+        .collect();
+    let columns: Vec<String> = header
+        .iter()
+        .zip(&types)
+        .map(|(name, column_type)| format!("{} {}", quote_identifier(name), sql_type(*column_type)))
+        .collect();
+    let placeholders = vec!["?"; header.len()].join(", ");
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        &format!("DROP TABLE IF EXISTS {}", quote_identifier(table)),
+        [],
+    )?;
+    tx.execute(
+        &format!(
+            "CREATE TABLE {} ({})",
+            quote_identifier(table),
+            columns.join(", ")
+        ),
+        [],
+    )?;
+    {
+        let mut insert = tx.prepare(&format!(
+            "INSERT INTO {} VALUES ({})",
+            quote_identifier(table),
+            placeholders
+        ))?;
+        for row in rows {
+            let values = types
+                .iter()
+                .enumerate()
+                .map(|(index, column_type)| sql_value(cell(row, index), *column_type));
+            insert.execute(params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(rows.len())
+}
+
+/// Write every row of SQLite table `table` to tab `tab_name` in document `document_id`, with
+/// the column names as the header row. The sheet will be cleared before writing. Returns the
+/// number of rows written.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, conn), fields(rows = tracing::field::Empty))
+)]
+pub async fn sync_from_sqlite<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    conn: &mut Connection,
+    table: &str,
+) -> Result<usize, SheetsError> {
+    let rows = {
+        let mut select = conn.prepare(&format!("SELECT * FROM {}", quote_identifier(table)))?;
+        let header: Vec<String> = select
+            .column_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let width = header.len();
+
+        let mut rows = vec![header];
+        let mut results = select.query([])?;
+        while let Some(row) = results.next()? {
+            rows.push(
+                (0..width)
+                    .map(|index| Ok(cell_text(row.get_ref(index)?)))
+                    .collect::<Result<_, SheetsError>>()?,
+            );
+        }
+        rows
+    };
+    let written = rows.len() - 1;
+    trace::record("rows", written as u64);
+
+    clear_tab(sheets, document_id, tab_name).await?;
+    sheets.write_values(document_id, tab_name, rows).await?;
+    Ok(written)
+}
+
+fn sql_type(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::Boolean | ColumnType::Integer => "INTEGER",
+        ColumnType::Float => "REAL",
+        ColumnType::Text => "TEXT",
+    }
+}
+
+fn sql_value(cell: Option<&str>, column_type: ColumnType) -> Value {
+    let value = cell.and_then(|cell| match column_type {
+        ColumnType::Boolean => parse_bool(cell).map(|b| Value::Integer(i64::from(b))),
+        ColumnType::Integer => cell.parse().ok().map(Value::Integer),
+        ColumnType::Float => cell.parse().ok().map(Value::Real),
+        ColumnType::Text => Some(Value::Text(cell.to_string())),
+    });
+    value.unwrap_or(Value::Null)
+}
+
+fn cell_text(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) | ValueRef::Blob(text) => String::from_utf8_lossy(text).into_owned(),
+    }
+}
+
+/// Quote an SQL identifier, e.g. a column named after a header cell
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,268 @@
+use crate::{SheetsBackend, SheetsError, TabInfo, WriteReceipt};
+use async_trait::async_trait;
+use std::time::{Duration, Instant};
+
+/// A single backend call, as reported to a `MetricsRecorder`
+#[derive(Debug, Clone, Copy)]
+pub struct RequestEvent<'a> {
+    /// Backend operation, e.g. `read_values`
+    pub operation: &'static str,
+    pub document_id: &'a str,
+    pub latency: Duration,
+    /// Bytes of cell text sent to the API
+    pub bytes_sent: usize,
+    /// Bytes of cell text received from the API
+    pub bytes_received: usize,
+    pub error: Option<&'a SheetsError>,
+}
+
+/// Receives telemetry for every API interaction made through `Metered`
+pub trait MetricsRecorder: Send + Sync {
+    /// Called once per backend call, after it completes
+    fn record_request(&self, event: &RequestEvent<'_>);
+
+    /// Called when a request is retried after `delay`
+    fn record_retry(&self, _operation: &'static str, _attempt: u32, _delay: Duration) {}
+}
+
+/// Backend wrapper reporting every call to a `MetricsRecorder`
+pub struct Metered<B, R> {
+    inner: B,
+    recorder: R,
+}
+
+impl<B: SheetsBackend, R: MetricsRecorder> Metered<B, R> {
+    /// Report calls made through `inner` to `recorder`
+    pub fn new(inner: B, recorder: R) -> Self {
+        Self { inner, recorder }
+    }
+
+    /// The wrapped backend
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn report<T>(
+        &self,
+        operation: &'static str,
+        document_id: &str,
+        started: Instant,
+        bytes_sent: usize,
+        result: &Result<T, SheetsError>,
+        bytes_received: impl FnOnce(&T) -> usize,
+    ) {
+        self.recorder.record_request(&RequestEvent {
+            operation,
+            document_id,
+            latency: started.elapsed(),
+            bytes_sent,
+            bytes_received: result.as_ref().map_or(0, bytes_received),
+            error: result.as_ref().err(),
+        });
+    }
+}
+
+/// Total length of the cell text in `rows`
+fn cell_bytes(rows: &[Vec<String>]) -> usize {
+    rows.iter().flatten().map(String::len).sum()
+}
+
+#[async_trait]
+impl<B: SheetsBackend, R: MetricsRecorder> SheetsBackend for Metered<B, R> {
+    async fn read_values(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.read_values(document_id, range).await;
+        self.report("read_values", document_id, started, 0, &result, |rows| {
+            cell_bytes(rows)
+        });
+        result
+    }
+
+    async fn read_formulas(
+        &self,
+        document_id: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<String>>, SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.read_formulas(document_id, range).await;
+        self.report("read_values", document_id, started, 0, &result, |rows| {
+            cell_bytes(rows)
+        });
+        result
+    }
+
+    async fn read_ranges(
+        &self,
+        document_id: &str,
+        ranges: &[String],
+    ) -> Result<Vec<Vec<Vec<String>>>, SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.read_ranges(document_id, ranges).await;
+        self.report("read_ranges", document_id, started, 0, &result, |values| {
+            values.iter().map(|rows| cell_bytes(rows)).sum()
+        });
+        result
+    }
+
+    async fn write_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let bytes = cell_bytes(&rows);
+        let started = Instant::now();
+        let result = self.inner.write_values(document_id, range, rows).await;
+        self.report("write_values", document_id, started, bytes, &result, |_| 0);
+        result
+    }
+
+    async fn write_raw_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SheetsError> {
+        let bytes = cell_bytes(&rows);
+        let started = Instant::now();
+        let result = self.inner.write_raw_values(document_id, range, rows).await;
+        self.report("write_values", document_id, started, bytes, &result, |_| 0);
+        result
+    }
+
+    async fn write_ranges(
+        &self,
+        document_id: &str,
+        data: Vec<(String, Vec<Vec<String>>)>,
+    ) -> Result<(), SheetsError> {
+        let bytes = data.iter().map(|(_, rows)| cell_bytes(rows)).sum();
+        let started = Instant::now();
+        let result = self.inner.write_ranges(document_id, data).await;
+        self.report("write_ranges", document_id, started, bytes, &result, |_| 0);
+        result
+    }
+
+    async fn append_values(
+        &self,
+        document_id: &str,
+        range: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<WriteReceipt, SheetsError> {
+        let bytes = cell_bytes(&rows);
+        let started = Instant::now();
+        let result = self.inner.append_values(document_id, range, rows).await;
+        self.report("append_values", document_id, started, bytes, &result, |_| 0);
+        result
+    }
+
+    async fn clear_range(&self, document_id: &str, range: &str) -> Result<(), SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.clear_range(document_id, range).await;
+        self.report("clear_range", document_id, started, 0, &result, |_| 0);
+        result
+    }
+
+    async fn delete_rows(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), SheetsError> {
+        let started = Instant::now();
+        let result = self
+            .inner
+            .delete_rows(document_id, tab_name, start, end)
+            .await;
+        self.report("delete_rows", document_id, started, 0, &result, |_| 0);
+        result
+    }
+
+    async fn list_tabs(&self, document_id: &str) -> Result<Vec<TabInfo>, SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.list_tabs(document_id).await;
+        self.report("list_tabs", document_id, started, 0, &result, |_| 0);
+        result
+    }
+
+    async fn add_tab(&self, document_id: &str, tab_name: &str) -> Result<TabInfo, SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.add_tab(document_id, tab_name).await;
+        self.report("add_tab", document_id, started, 0, &result, |_| 0);
+        result
+    }
+
+    async fn delete_tab(&self, document_id: &str, tab_name: &str) -> Result<(), SheetsError> {
+        let started = Instant::now();
+        let result = self.inner.delete_tab(document_id, tab_name).await;
+        self.report("delete_tab", document_id, started, 0, &result, |_| 0);
+        result
+    }
+
+    async fn duplicate_tab(
+        &self,
+        document_id: &str,
+        tab_name: &str,
+        new_name: &str,
+    ) -> Result<TabInfo, SheetsError> {
+        let started = Instant::now();
+        let result = self
+            .inner
+            .duplicate_tab(document_id, tab_name, new_name)
+            .await;
+        self.report("duplicate_tab", document_id, started, 0, &result, |_| 0);
+        result
+    }
+}
+
+/// `MetricsRecorder` publishing to the `metrics` crate facade:
+///
+/// * `serde_sheets_requests_total{operation, outcome}` counter
+/// * `serde_sheets_request_duration_seconds{operation}` histogram
+/// * `serde_sheets_bytes_total{operation, direction}` counter
+/// * `serde_sheets_retries_total{operation}` counter
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsCrateRecorder;
+
+#[cfg(feature = "metrics")]
+impl MetricsRecorder for MetricsCrateRecorder {
+    fn record_request(&self, event: &RequestEvent<'_>) {
+        let outcome = if event.error.is_some() {
+            "error"
+        } else {
+            "success"
+        };
+        metrics::counter!(
+            "serde_sheets_requests_total",
+            1,
+            "operation" => event.operation,
+            "outcome" => outcome
+        );
+        metrics::histogram!(
+            "serde_sheets_request_duration_seconds",
+            event.latency.as_secs_f64(),
+            "operation" => event.operation
+        );
+        metrics::counter!(
+            "serde_sheets_bytes_total",
+            event.bytes_sent as u64,
+            "operation" => event.operation,
+            "direction" => "sent"
+        );
+        metrics::counter!(
+            "serde_sheets_bytes_total",
+            event.bytes_received as u64,
+            "operation" => event.operation,
+            "direction" => "received"
+        );
+    }
+
+    fn record_retry(&self, operation: &'static str, _attempt: u32, _delay: Duration) {
+        metrics::counter!("serde_sheets_retries_total", 1, "operation" => operation);
+    }
+}
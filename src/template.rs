@@ -0,0 +1,80 @@
+use crate::{
+    column_letter, quote_tab_name, rows::serde_to_row, serialize_rows, SheetsBackend, SheetsError,
+    TabInfo,
+};
+use serde::Serialize;
+
+/// Copy the formatted tab `template_tab` to a new tab `new_name`, fill in its placeholders
+/// from `substitutions`, and write `data` as a table starting at cell `data_at`, e.g. `A6`.
+///
+/// Placeholders are `{{field}}` anywhere in a cell's text, replaced with that field of
+/// `substitutions` as it serializes; a cell may hold several, and unknown ones are left as
+/// they are. Only cells with placeholders are rewritten, so the rest of the template keeps its
+/// formulas. `data` is written without a header, under the one the template provides.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(sheets, substitutions, data), fields(rows = data.len()))
+)]
+pub async fn instantiate_template<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    template_tab: &str,
+    new_name: &str,
+    substitutions: &impl Serialize,
+    data_at: &str,
+    data: &[impl Serialize],
+) -> Result<TabInfo, SheetsError> {
+    let tab = sheets
+        .duplicate_tab(document_id, template_tab, new_name)
+        .await?;
+    let (fields, values) = serde_to_row(substitutions)?;
+    let tab_prefix = quote_tab_name(new_name);
+
+    let mut filled = vec![];
+    for (row, cells) in sheets
+        .read_values(document_id, new_name)
+        .await?
+        .into_iter()
+        .enumerate()
+    {
+        for (column, cell) in cells.into_iter().enumerate() {
+            let substituted = substitute(&cell, &fields, &values);
+            if substituted != cell {
+                let range = format!("{}!{}{}", tab_prefix, column_letter(column), row + 1);
+                filled.push((range, vec![vec![substituted]]));
+            }
+        }
+    }
+    if !filled.is_empty() {
+        sheets.write_ranges(document_id, filled).await?;
+    }
+
+    if !data.is_empty() {
+        let range = format!("{}!{}", tab_prefix, data_at);
+        sheets
+            .write_values(document_id, &range, serialize_rows(data, false)?)
+            .await?;
+    }
+    Ok(tab)
+}
+
+/// `cell` with each `{{field}}` replaced by its value
+fn substitute(cell: &str, fields: &[String], values: &[String]) -> String {
+    let mut out = String::with_capacity(cell.len());
+    let mut rest = cell;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start + 2..].find("}}") {
+            Some(end) => start + 2 + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        out.push_str(&rest[..start]);
+        match fields.iter().position(|field| field == name) {
+            Some(i) => out.push_str(values.get(i).map_or("", String::as_str)),
+            None => out.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,78 @@
+use crate::{SheetsBackend, SheetsError};
+use futures::{future::BoxFuture, FutureExt};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    time::Duration,
+};
+
+/// Prefix of the tabs created by `with_test_tab`
+pub const TEST_TAB_PREFIX: &str = "IntegrationTest_";
+
+/// Create a tab called `IntegrationTest_<unix seconds>_<uuid>` in document `document_id`, run
+/// `test` against it and delete the tab afterwards, even if `test` panics.
+///
+/// Concurrent tests each get their own tab, so they can share one test spreadsheet:
+///
+/// ```ignore
+/// with_test_tab(&sheets, DOCUMENT_ID, |sheets, tab| {
+///     Box::pin(async move {
+///         serde_sheets::write_page(sheets, DOCUMENT_ID, tab, &objects).await.unwrap();
+///     })
+/// })
+/// .await?;
+/// ```
+pub async fn with_test_tab<B, T, F>(
+    sheets: &B,
+    document_id: &str,
+    test: F,
+) -> Result<T, SheetsError>
+where
+    B: SheetsBackend + ?Sized,
+    F: for<'a> FnOnce(&'a B, &'a str) -> BoxFuture<'a, T>,
+{
+    let tab_name = format!(
+        "{}{}_{}",
+        TEST_TAB_PREFIX,
+        chrono::Utc::now().timestamp(),
+        uuid::Uuid::new_v4().simple()
+    );
+    sheets.add_tab(document_id, &tab_name).await?;
+
+    let result = AssertUnwindSafe(test(sheets, &tab_name))
+        .catch_unwind()
+        .await;
+    let cleanup = sheets.delete_tab(document_id, &tab_name).await;
+
+    match result {
+        Ok(value) => cleanup.map(|()| value),
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Delete tabs left behind by `with_test_tab` runs that were killed before cleaning up,
+/// returning how many were deleted. Only tabs created more than `max_age` ago are deleted, so
+/// tests running at the same time keep theirs; make it longer than the slowest test. Tabs
+/// named without a creation time count as stale.
+pub async fn delete_stale_test_tabs<B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    max_age: Duration,
+) -> Result<usize, SheetsError> {
+    let now = chrono::Utc::now().timestamp();
+    let max_age = i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX);
+    let mut deleted = 0;
+    for tab in sheets.list_tabs(document_id).await? {
+        let created = match tab.title.strip_prefix(TEST_TAB_PREFIX) {
+            Some(rest) => rest
+                .split('_')
+                .next()
+                .and_then(|secs| secs.parse::<i64>().ok()),
+            None => continue,
+        };
+        if created.map_or(true, |created| now.saturating_sub(created) > max_age) {
+            sheets.delete_tab(document_id, &tab.title).await?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
@@ -0,0 +1,15 @@
+use std::time::Instant;
+
+/// Record `value` on field `field` of the current span when the `tracing` feature is enabled.
+/// The field must have been declared by the span, e.g. as `tracing::field::Empty`.
+pub(crate) fn record(field: &'static str, value: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record(field, value);
+    #[cfg(not(feature = "tracing"))]
+    let _ = (field, value);
+}
+
+/// Record the milliseconds since `started` as `latency_ms` on the current span
+pub(crate) fn record_latency(started: Instant) {
+    record("latency_ms", started.elapsed().as_millis() as u64);
+}
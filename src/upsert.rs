@@ -0,0 +1,402 @@
+use crate::{
+    de::serde_error, quote_tab_name, serialize_rows, write_page, SheetsBackend, SheetsError,
+    WriteReceipt,
+};
+use csv::StringRecord;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// How `upsert_row` combines an existing row with the new object
+pub enum MergeStrategy<T> {
+    /// Replace every column with the new object's value
+    Overwrite,
+    /// Take the new object's values, but keep the sheet's value wherever the new one is blank
+    FillBlanks,
+    /// Keep the sheet's non-blank values (human edits win), only filling blank cells
+    PreferSheet,
+    /// Merge the existing row (deserialized) and the new object with a closure `(old, new)`
+    Custom(Box<dyn Fn(T, T) -> T + Send + Sync>),
+}
+
+impl<T> MergeStrategy<T> {
+    /// Build a `MergeStrategy::Custom` from a closure taking `(old, new)`
+    pub fn custom(f: impl Fn(T, T) -> T + Send + Sync + 'static) -> Self {
+        MergeStrategy::Custom(Box::new(f))
+    }
+}
+
+/// Insert `obj` into tab `tab_name` in document `document_id`, or merge it into the existing row
+/// whose `key_column` has the same value as `obj`, according to `strategy`.
+/// Columns of the sheet that `T` does not serialize are left untouched.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, obj, strategy)))]
+pub async fn upsert_row<T: Serialize + DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    key_column: &str,
+    obj: T,
+    strategy: &MergeStrategy<T>,
+) -> Result<(), SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    let header = match rows.first() {
+        Some(header) => header.clone(),
+        None => {
+            write_page(sheets, document_id, tab_name, &[obj]).await?;
+            return Ok(());
+        }
+    };
+
+    let key_index = column_index(&header, key_column)?;
+    let new_values = aligned_values(&header, &obj)?;
+    let key = new_values[key_index]
+        .clone()
+        .ok_or_else(|| SheetsError::InvalidRecord {
+            reason: format!("no value for key column {:?}", key_column),
+        })?;
+
+    let row_index = match rows
+        .iter()
+        .skip(1)
+        .position(|row| row.get(key_index) == Some(&key))
+    {
+        Some(position) => position + 1,
+        None => {
+            let row = new_values
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect();
+            sheets
+                .append_values(document_id, tab_name, vec![row])
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut existing = rows[row_index].clone();
+    existing.resize(header.len(), String::new());
+
+    let merged: Vec<String> = match strategy {
+        MergeStrategy::Overwrite => merge_cells(existing, new_values, |_old, new| new),
+        MergeStrategy::FillBlanks => {
+            merge_cells(
+                existing,
+                new_values,
+                |old, new| {
+                    if new.is_empty() {
+                        old
+                    } else {
+                        new
+                    }
+                },
+            )
+        }
+        MergeStrategy::PreferSheet => {
+            merge_cells(
+                existing,
+                new_values,
+                |old, new| {
+                    if old.is_empty() {
+                        new
+                    } else {
+                        old
+                    }
+                },
+            )
+        }
+        MergeStrategy::Custom(f) => {
+            let old: T = existing
+                .iter()
+                .collect::<StringRecord>()
+                .deserialize(Some(&header.iter().collect::<StringRecord>()))
+                .map_err(|e| serde_error(e, row_index + 1, &header))?;
+            let merged = f(old, obj);
+            merge_cells(existing, aligned_values(&header, &merged)?, |_old, new| new)
+        }
+    };
+
+    let range = format!("{}!A{}", quote_tab_name(tab_name), row_index + 1);
+    sheets.write_values(document_id, &range, vec![merged]).await
+}
+
+/// Append `obj` to tab `tab_name` in document `document_id` unless it matches the last row,
+/// or with `key_column`, the last row with the same key. For status and heartbeat tabs that
+/// should only grow when something changes. Returns the receipt if a row was appended.
+///
+/// Only the columns `obj` serializes are compared, so a timestamp or note column the sheet
+/// adds doesn't count as a change.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets, obj)))]
+pub async fn append_if_changed<T: Serialize, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+    key_column: Option<&str>,
+    obj: T,
+) -> Result<Option<WriteReceipt>, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    let header = match rows.first() {
+        Some(header) => header.clone(),
+        None => {
+            return write_page(sheets, document_id, tab_name, &[obj])
+                .await
+                .map(Some)
+        }
+    };
+
+    let new_values = aligned_values(&header, &obj)?;
+    let key = match key_column {
+        Some(column) => {
+            let index = column_index(&header, column)?;
+            Some((index, new_values[index].clone().unwrap_or_default()))
+        }
+        None => None,
+    };
+    let previous = rows.iter().skip(1).rev().find(|row| match &key {
+        Some((index, key)) => row.get(*index) == Some(key),
+        None => row.iter().any(|cell| !cell.is_empty()),
+    });
+
+    let unchanged = previous.map_or(false, |row| {
+        new_values.iter().enumerate().all(|(i, new)| match new {
+            Some(new) => row.get(i).map_or("", String::as_str) == new,
+            None => true,
+        })
+    });
+    if unchanged {
+        return Ok(None);
+    }
+
+    let row = new_values
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .collect();
+    sheets
+        .append_values(document_id, tab_name, vec![row])
+        .await
+        .map(Some)
+}
+
+/// Position of `column` in `header`
+fn column_index(header: &[String], column: &str) -> Result<usize, SheetsError> {
+    header
+        .iter()
+        .position(|h| h == column)
+        .ok_or_else(|| SheetsError::ColumnNotFound {
+            column: column.to_string(),
+        })
+}
+
+/// Serialize `obj` and reorder its values to match `header`, `None` for columns it lacks
+fn aligned_values<T: Serialize>(
+    header: &[String],
+    obj: &T,
+) -> Result<Vec<Option<String>>, SheetsError> {
+    let mut rows = serialize_rows(std::slice::from_ref(obj), true)?.into_iter();
+    let obj_header = rows.next().unwrap_or_default();
+    let obj_values = rows.next().unwrap_or_default();
+
+    Ok(header
+        .iter()
+        .map(|column| {
+            obj_header
+                .iter()
+                .position(|h| h == column)
+                .and_then(|i| obj_values.get(i).cloned())
+        })
+        .collect())
+}
+
+/// Combine cells pairwise with `f(old, new)`, keeping `old` where there is no new value
+fn merge_cells(
+    existing: Vec<String>,
+    new_values: Vec<Option<String>>,
+    f: impl Fn(String, String) -> String,
+) -> Vec<String> {
+    existing
+        .into_iter()
+        .zip(new_values)
+        .map(|(old, new)| match new {
+            Some(new) => f(old, new),
+            None => old,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockSheets;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        email: String,
+        name: String,
+        visits: u32,
+    }
+
+    fn person(email: &str, name: &str, visits: u32) -> Person {
+        Person {
+            email: email.to_string(),
+            name: name.to_string(),
+            visits,
+        }
+    }
+
+    fn strings(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    /// A tab whose header is in a different order from `Person` and has a column it lacks
+    fn people() -> MockSheets {
+        let sheets = MockSheets::new();
+        sheets.set_tab(
+            "doc",
+            "People",
+            vec![
+                strings(&["email", "name", "notes", "visits"]),
+                strings(&["a@x", "Alice", "vip", "3"]),
+                strings(&["b@x", "", "", "1"]),
+            ],
+        );
+        sheets
+    }
+
+    async fn upsert(sheets: &MockSheets, obj: Person, strategy: &MergeStrategy<Person>) {
+        upsert_row(sheets, "doc", "People", "email", obj, strategy)
+            .await
+            .unwrap();
+    }
+
+    fn row(sheets: &MockSheets, index: usize) -> Vec<String> {
+        sheets.get_tab("doc", "People").unwrap()[index].clone()
+    }
+
+    #[tokio::test]
+    async fn writes_a_header_to_an_empty_tab() {
+        let sheets = MockSheets::new();
+        sheets.set_tab("doc", "People", vec![]);
+
+        upsert(
+            &sheets,
+            person("a@x", "Alice", 1),
+            &MergeStrategy::Overwrite,
+        )
+        .await;
+        assert_eq!(
+            sheets.get_tab("doc", "People").unwrap(),
+            vec![
+                strings(&["email", "name", "visits"]),
+                strings(&["a@x", "Alice", "1"])
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn appends_a_new_key_in_header_order() {
+        let sheets = people();
+
+        upsert(&sheets, person("c@x", "Cara", 1), &MergeStrategy::Overwrite).await;
+        assert_eq!(sheets.get_tab("doc", "People").unwrap().len(), 4);
+        assert_eq!(row(&sheets, 3), strings(&["c@x", "Cara", "", "1"]));
+    }
+
+    #[tokio::test]
+    async fn overwrites_every_serialized_column() {
+        let sheets = people();
+
+        upsert(&sheets, person("a@x", "", 5), &MergeStrategy::Overwrite).await;
+        assert_eq!(row(&sheets, 1), strings(&["a@x", "", "vip", "5"]));
+        assert_eq!(row(&sheets, 2), strings(&["b@x", "", "", "1"]));
+    }
+
+    #[tokio::test]
+    async fn fills_blanks_from_the_sheet() {
+        let sheets = people();
+
+        upsert(&sheets, person("a@x", "", 5), &MergeStrategy::FillBlanks).await;
+        assert_eq!(row(&sheets, 1), strings(&["a@x", "Alice", "vip", "5"]));
+    }
+
+    #[tokio::test]
+    async fn prefers_non_blank_sheet_cells() {
+        let sheets = people();
+
+        upsert(
+            &sheets,
+            person("b@x", "Bob", 9),
+            &MergeStrategy::PreferSheet,
+        )
+        .await;
+        assert_eq!(row(&sheets, 2), strings(&["b@x", "Bob", "", "1"]));
+    }
+
+    #[tokio::test]
+    async fn merges_with_a_custom_closure() {
+        let sheets = people();
+        let strategy = MergeStrategy::custom(|old: Person, new: Person| Person {
+            visits: old.visits + new.visits,
+            ..new
+        });
+
+        upsert(&sheets, person("a@x", "Alice", 2), &strategy).await;
+        assert_eq!(row(&sheets, 1), strings(&["a@x", "Alice", "vip", "5"]));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_key_column_missing_from_the_sheet_or_the_object() {
+        let sheets = people();
+
+        let err = upsert_row(
+            &sheets,
+            "doc",
+            "People",
+            "id",
+            person("a@x", "Alice", 1),
+            &MergeStrategy::Overwrite,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, SheetsError::ColumnNotFound { column } if column == "id"));
+
+        let err = upsert_row(
+            &sheets,
+            "doc",
+            "People",
+            "notes",
+            person("a@x", "Alice", 1),
+            &MergeStrategy::Overwrite,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, SheetsError::InvalidRecord { .. }));
+        assert_eq!(sheets.get_tab("doc", "People").unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn appends_only_when_the_row_changes() {
+        let sheets = people();
+
+        let unchanged = append_if_changed(
+            &sheets,
+            "doc",
+            "People",
+            Some("email"),
+            person("a@x", "Alice", 3),
+        )
+        .await
+        .unwrap();
+        assert!(unchanged.is_none());
+
+        let receipt = append_if_changed(
+            &sheets,
+            "doc",
+            "People",
+            Some("email"),
+            person("a@x", "Alice", 4),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(receipt.first_row_index, 4);
+        assert_eq!(row(&sheets, 3), strings(&["a@x", "Alice", "", "4"]));
+    }
+}
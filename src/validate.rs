@@ -0,0 +1,337 @@
+use crate::{SheetsBackend, SheetsError};
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+    Deserializer, Serialize,
+};
+use std::str::FromStr;
+
+/// One problem found by `validate_rows`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    /// One-based sheet row
+    pub row: usize,
+    /// Header of the offending cell, if the problem is with one cell
+    pub column: Option<String>,
+    /// The cell as it is in the sheet
+    pub value: Option<String>,
+    /// The type the cell should have held, e.g. `u32`, if known
+    pub expected: Option<String>,
+    pub message: String,
+}
+
+/// Every problem with the rows of a tab, rather than just the first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    /// Non-empty data rows looked at
+    pub rows_checked: usize,
+    /// Rows that deserialized without any issue
+    pub valid_rows: usize,
+    pub issues: Vec<ValidationIssue>,
+    /// Header columns the record type has no field for. They don't make the rows invalid,
+    /// but may be a sign the tab's layout has drifted.
+    pub unknown_columns: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Whether no issues were found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Check `rows`, whose first row is the header, against `T`. `first_row` is the one-based
+    /// sheet row of the header.
+    pub fn check<T: DeserializeOwned>(rows: &[Vec<String>], first_row: usize) -> Self {
+        let mut report = Self::default();
+        let (header, data) = match rows.split_first() {
+            Some(split) => split,
+            None => return report,
+        };
+        report.unknown_columns = crate::de::unknown_columns(
+            header.iter().map(String::as_str),
+            crate::de::struct_fields::<T>(),
+        );
+
+        for (i, row) in data.iter().enumerate() {
+            if row.is_empty() {
+                continue;
+            }
+            let row_number = first_row + i + 1;
+            let issues_before = report.issues.len();
+            let mut access = RowAccess {
+                header,
+                row,
+                row_number,
+                next: 0,
+                failed_at: None,
+                issues: &mut report.issues,
+            };
+            let result = T::deserialize(RowDeserializer(&mut access));
+            if let Err(e) = result {
+                let failed_at = access.failed_at;
+                report.issues.push(ValidationIssue {
+                    row: row_number,
+                    column: failed_at.and_then(|i| header.get(i)).cloned(),
+                    value: failed_at.map(|i| row.get(i).cloned().unwrap_or_default()),
+                    expected: None,
+                    message: e.to_string(),
+                });
+            }
+
+            report.rows_checked += 1;
+            if report.issues.len() == issues_before {
+                report.valid_rows += 1;
+            }
+        }
+        report
+    }
+}
+
+/// Try to deserialize every row of tab `tab_name` in document `document_id` into a `T`,
+/// collecting each cell that doesn't parse, with its row, column, value and expected type.
+///
+/// A cell that doesn't parse stands in as zero, `false` or empty, so checking carries on to
+/// the rest of the row; errors raised by the type itself, such as an unknown enum variant or
+/// a missing column, end the row. The issues can be written to an errors tab with
+/// `write_page`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(sheets)))]
+pub async fn validate_rows<T: DeserializeOwned, B: SheetsBackend + ?Sized>(
+    sheets: &B,
+    document_id: &str,
+    tab_name: &str,
+) -> Result<ValidationReport, SheetsError> {
+    let rows = sheets.read_values(document_id, tab_name).await?;
+    Ok(ValidationReport::check::<T>(&rows, 1))
+}
+
+struct RowAccess<'a> {
+    header: &'a [String],
+    row: &'a [String],
+    row_number: usize,
+    next: usize,
+    /// Column whose value the type rejected
+    failed_at: Option<usize>,
+    issues: &'a mut Vec<ValidationIssue>,
+}
+
+/// Deserializer of one row into a struct or map keyed by the header
+struct RowDeserializer<'a, 'b>(&'a mut RowAccess<'b>);
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_, '_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self.0)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+impl<'de> MapAccess<'de> for &mut RowAccess<'_> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.header.get(self.next) {
+            Some(name) => seed
+                .deserialize(name.as_str().into_deserializer())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let column = self.next;
+        self.next += 1;
+        let cell = CellDeserializer {
+            value: self.row.get(column).map_or("", String::as_str),
+            column: &self.header[column],
+            row_number: self.row_number,
+            issues: &mut *self.issues,
+        };
+        seed.deserialize(cell).map_err(|e| {
+            self.failed_at = Some(column);
+            e
+        })
+    }
+}
+
+/// Deserializer of one cell, parsing it as `csv` would. A cell that doesn't parse is
+/// recorded as an issue and replaced with a default, so the rest of the row is checked too.
+struct CellDeserializer<'a> {
+    value: &'a str,
+    column: &'a str,
+    row_number: usize,
+    issues: &'a mut Vec<ValidationIssue>,
+}
+
+impl CellDeserializer<'_> {
+    fn parse<T: FromStr + Default>(self, expected: &str) -> T
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.value.parse().unwrap_or_else(|e: T::Err| {
+            self.issues.push(ValidationIssue {
+                row: self.row_number,
+                column: Some(self.column.to_string()),
+                value: Some(self.value.to_string()),
+                expected: Some(expected.to_string()),
+                message: e.to_string(),
+            });
+            T::default()
+        })
+    }
+}
+
+macro_rules! parse_cell {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty)))
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for CellDeserializer<'_> {
+    type Error = de::value::Error;
+
+    /// Like `csv`, the narrowest of bool, integer and float the cell parses as, or a string
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if let Ok(value) = self.value.parse::<bool>() {
+            visitor.visit_bool(value)
+        } else if let Ok(value) = self.value.parse::<u64>() {
+            visitor.visit_u64(value)
+        } else if let Ok(value) = self.value.parse::<i64>() {
+            visitor.visit_i64(value)
+        } else if let Ok(value) = self.value.parse::<f64>() {
+            visitor.visit_f64(value)
+        } else {
+            visitor.visit_str(self.value)
+        }
+    }
+
+    parse_cell! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+        deserialize_char => visit_char(char),
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(self.value.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bytes(self.value.as_bytes())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom(
+            "nested sequences are not supported in a cell",
+        ))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("tuples are not supported in a cell"))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom(
+            "tuple structs are not supported in a cell",
+        ))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("maps are not supported in a cell"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("structs are not supported in a cell"))
+    }
+}
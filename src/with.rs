@@ -0,0 +1,251 @@
+//! Field adapters for `#[serde(with = "...")]`, for values that need more than one plain cell.
+//!
+//! Every field goes through the `csv` (de)serializer, so any `with`, `serialize_with`,
+//! `deserialize_with` or `serde_with` adapter works as long as it produces and accepts a
+//! single scalar such as a string or number. Nested structs, maps and `#[serde(flatten)]` are
+//! not supported by `csv`; use `json` to keep such values in one cell.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Row {
+//!     #[serde(with = "serde_sheets::with::comma_separated")]
+//!     tags: Vec<String>,
+//!     #[serde(with = "serde_sheets::with::json")]
+//!     metadata: HashMap<String, String>,
+//! }
+//! ```
+
+/// A `Vec` stored as comma-separated values in one cell, e.g. `a, b, c`. Items are trimmed
+/// when reading and an empty cell is an empty `Vec`; items must not contain commas.
+pub mod comma_separated {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::{fmt::Display, str::FromStr};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        values: &[T],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let cell: Vec<String> = values.iter().map(ToString::to_string).collect();
+        serializer.serialize_str(&cell.join(", "))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let cell = String::deserialize(deserializer)?;
+        if cell.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        cell.split(',')
+            .map(|item| item.trim().parse().map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+/// A value kept as text in its cell, for numbers Sheets would round or reformat: `u64` IDs
+/// above 2^53, EANs, phone numbers with a leading zero or `+`. Written with a leading
+/// apostrophe, which Sheets hides and which is dropped again when reading. `u128` and `i128`
+/// fields are kept as text without this.
+pub mod text {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::{fmt::Display, str::FromStr};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("'{}", value))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let cell = String::deserialize(deserializer)?;
+        cell.strip_prefix('\'')
+            .unwrap_or(&cell)
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Any serializable value stored as JSON text in one cell
+pub mod json {
+    use serde::{de, de::DeserializeOwned, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let cell = serde_json::to_string(value).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&cell)
+    }
+
+    pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let cell = String::deserialize(deserializer)?;
+        serde_json::from_str(&cell).map_err(de::Error::custom)
+    }
+}
+
+/// A `Duration` stored as seconds, e.g. `90` or `1.5`, so the sheet can do arithmetic on it.
+/// Cells may also hold ISO 8601 durations such as `PT1H30M` or `P1DT12H`; years and months
+/// are rejected, having no fixed length.
+pub mod duration {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.subsec_nanos() == 0 {
+            serializer.serialize_u64(value.as_secs())
+        } else {
+            serializer.serialize_f64(value.as_secs_f64())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let cell = String::deserialize(deserializer)?;
+        let cell = cell.trim();
+        let seconds = match cell.parse::<f64>() {
+            Ok(seconds) => Some(seconds),
+            Err(_) => iso8601_seconds(cell),
+        };
+        seconds
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .ok_or_else(|| de::Error::custom(format!("{:?} is not a duration", cell)))
+    }
+
+    /// Seconds in an ISO 8601 duration of weeks, days, hours, minutes and seconds
+    fn iso8601_seconds(text: &str) -> Option<f64> {
+        let rest = text.strip_prefix('P')?;
+        let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+        if date.is_empty() && time.is_empty() {
+            return None;
+        }
+        let sum = |part: &str, units: &[(char, f64)]| -> Option<f64> {
+            let mut seconds = 0.0;
+            let mut number = String::new();
+            let mut units = units.iter();
+            for c in part.chars() {
+                if c.is_ascii_digit() || c == '.' || c == ',' {
+                    number.push(if c == ',' { '.' } else { c });
+                    continue;
+                }
+                // units must come in order, each once
+                let (_, unit) = units.find(|(u, _)| *u == c)?;
+                seconds += number.parse::<f64>().ok()? * unit;
+                number.clear();
+            }
+            number.is_empty().then_some(seconds)
+        };
+        Some(
+            sum(date, &[('W', 604_800.0), ('D', 86_400.0)])?
+                + sum(time, &[('H', 3_600.0), ('M', 60.0), ('S', 1.0)])?,
+        )
+    }
+}
+
+/// Bytes such as `Vec<u8>` or `[u8; 32]` stored as standard base64 text
+#[cfg(feature = "base64")]
+pub mod base64 {
+    use ::base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: AsRef<[u8]>, S: Serializer>(
+        bytes: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        let cell = String::deserialize(deserializer)?;
+        let bytes = STANDARD.decode(cell.trim()).map_err(de::Error::custom)?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| de::Error::custom(format!("unexpected {} bytes", len)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{de::RowDecoder, serialize_rows, ReadOptions, SheetsError};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Tagged {
+        #[serde(with = "super::comma_separated")]
+        tags: Vec<String>,
+        #[serde(with = "super::json")]
+        metadata: BTreeMap<String, String>,
+    }
+
+    fn tagged(tags: &[&str], metadata: &[(&str, &str)]) -> Tagged {
+        Tagged {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// The cells `record` is written as, and the record read back from them
+    fn round_trip(record: &Tagged) -> (Vec<String>, Tagged) {
+        let mut rows = serialize_rows(std::slice::from_ref(record), true)
+            .unwrap()
+            .into_iter();
+        let header = rows.next().unwrap();
+        let cells = rows.next().unwrap();
+        let mut decoder = RowDecoder::new::<Tagged>(header, &ReadOptions::default()).unwrap();
+        let read = decoder.try_decode(&cells, 2).unwrap().unwrap();
+        (cells, read)
+    }
+
+    #[test]
+    fn comma_separated_and_json_round_trip() {
+        let record = tagged(&["red", "blue"], &[("note", "big, heavy"), ("size", "L")]);
+        let (cells, read) = round_trip(&record);
+        assert_eq!(
+            cells,
+            vec![
+                "red, blue".to_string(),
+                r#"{"note":"big, heavy","size":"L"}"#.to_string()
+            ]
+        );
+        assert_eq!(read, record);
+    }
+
+    #[test]
+    fn empty_vec_is_an_empty_cell() {
+        let record = tagged(&[], &[]);
+        let (cells, read) = round_trip(&record);
+        assert_eq!(cells, vec!["".to_string(), "{}".to_string()]);
+        assert_eq!(read, record);
+    }
+
+    #[test]
+    fn comma_separated_items_split_at_commas() {
+        let (cells, read) = round_trip(&tagged(&["big, heavy"], &[]));
+        assert_eq!(cells[0], "big, heavy");
+        assert_eq!(read.tags, vec!["big".to_string(), "heavy".to_string()]);
+    }
+
+    #[test]
+    fn empty_json_cell_is_an_error() {
+        let header = vec!["tags".to_string(), "metadata".to_string()];
+        let mut decoder = RowDecoder::new::<Tagged>(header, &ReadOptions::default()).unwrap();
+        let result = decoder.try_decode::<Tagged>(&["red".to_string(), "".to_string()], 2);
+        assert!(matches!(result, Err(SheetsError::Serde { row: 2, .. })));
+    }
+}